@@ -0,0 +1,52 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use aoc_2019::intcode::bench::{measure, workloads, Baseline, Variant};
+use std::error::Error;
+use std::time::Duration;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // cargo bench --bench intcode [-- [--save] [--quick] [--strict]].
+    // Compares against the baseline in benches/baseline.txt, or replaces it
+    // with --save. Anything more than 20% slower counts as a regression,
+    // since timings are pretty noisy. The baseline is from whatever machine
+    // saved it, so regressions only fail the run with --strict, for when it's
+    // your own
+    let args: Vec<String> = std::env::args().collect();
+    let save = args.iter().any(|arg| arg == "--save");
+    let strict = args.iter().any(|arg| arg == "--strict");
+    let time = if args.iter().any(|arg| arg == "--quick") {
+        Duration::from_millis(100)
+    } else {
+        Duration::from_secs(1)
+    };
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/baseline.txt");
+    let mut measurements = Vec::new();
+    for workload in workloads(100) {
+        for &variant in Variant::all().iter().filter(|variant| variant.supports(&workload)) {
+            measurements.push(measure(&workload, variant, time)?);
+        }
+    }
+    let baseline = Baseline::load(path).unwrap_or_default();
+    let (table, regressed) = baseline.compare(&measurements, 0.2);
+    print!("{}", table);
+    if save {
+        Baseline::from_measurements(&measurements).save(path)?;
+        println!("Saved the baseline to {}", path);
+    } else if regressed && strict {
+        return Err("Some benchmarks got slower than the baseline".into());
+    }
+    Ok(())
+}
@@ -0,0 +1,184 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Known answers, for checking solutions still get them after a refactor.
+//! Everyone's inputs are different, so answers are kept against a hash of
+//! the input they're for, and ones for other inputs are just ignored.
+//!
+//! They're saved as text, one answer a line, as the day, part, input hash
+//! and answer, with any backslashes and line breaks in the answer escaped:
+//!
+//! ```text
+//! # aoc answers
+//! 1 1 5c1f8d2e07a9b3f4 3315133
+//! ```
+
+use crate::{fnv1a, Part};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, prelude::*, BufReader, BufWriter};
+use std::path::Path;
+
+/// Where the answers are kept unless told otherwise.
+pub const DEFAULT_PATH: &str = "input/answers.txt";
+
+/// The hash answers are kept against. Trailing whitespace is ignored, since
+/// editors like to add or remove a final line break.
+pub fn input_hash(input: &str) -> u64 {
+    fnv1a(input.trim_end().as_bytes())
+}
+
+/// How an answer compares to the known one.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Check {
+    Match,
+    Mismatch {
+        expected: String,
+    },
+    /// Nothing's known for this input yet.
+    Unknown,
+}
+
+/// Answers by day, part and input hash.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Answers {
+    pub answers: BTreeMap<(u32, Part, u64), String>,
+}
+
+fn escape(answer: &str) -> String {
+    answer.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut answer = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                answer.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                answer.push('\\');
+                chars.next();
+            }
+            _ => answer.push(c),
+        }
+    }
+    answer
+}
+
+impl Answers {
+    pub fn get(&self, day: u32, part: Part, hash: u64) -> Option<&str> {
+        self.answers.get(&(day, part, hash)).map(String::as_str)
+    }
+
+    pub fn insert(&mut self, day: u32, part: Part, hash: u64, answer: &str) {
+        self.answers.insert((day, part, hash), answer.to_string());
+    }
+
+    pub fn check(&self, day: u32, part: Part, hash: u64, answer: &str) -> Check {
+        match self.get(day, part, hash) {
+            Some(expected) if expected == answer => Check::Match,
+            Some(expected) => Check::Mismatch {
+                expected: expected.to_string(),
+            },
+            None => Check::Unknown,
+        }
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut answers = Answers::default();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.splitn(4, ' ').collect();
+            let bad = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Can't read line {} of the answers", index + 1),
+                )
+            };
+            match fields[..] {
+                [day, part, hash, answer] => {
+                    let day = day.parse().map_err(|_| bad())?;
+                    let part = part.parse().map_err(|_| bad())?;
+                    let hash = u64::from_str_radix(hash, 16).map_err(|_| bad())?;
+                    answers.insert(day, part, hash, &unescape(answer));
+                }
+                _ => return Err(bad()),
+            }
+        }
+        Ok(answers)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "# aoc answers")?;
+        for ((day, part, hash), answer) in &self.answers {
+            writeln!(writer, "{} {} {:016x} {}", day, part, hash, escape(answer))?;
+        }
+        Ok(())
+    }
+
+    /// Loads the answers, or none at all if there's no file yet.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match File::open(path) {
+            Ok(file) => Answers::read(BufReader::new(file)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Answers::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let mut answers = Answers::default();
+    answers.insert(1, Part::Two, 0xabc, "970");
+    answers.insert(11, Part::Two, 0xabc, "#..#\n.##. \\o/");
+    let mut text = Vec::new();
+    answers.write(&mut text).unwrap();
+    assert_eq!(
+        String::from_utf8(text.clone()).unwrap(),
+        "# aoc answers\n1 2 0000000000000abc 970\n11 2 0000000000000abc #..#\\n.##. \\\\o/\n"
+    );
+    assert_eq!(Answers::read(&text[..]).unwrap(), answers);
+    assert!(Answers::read("1 3 abc 970\n".as_bytes()).is_err());
+    assert!(Answers::read("1 1 abc\n".as_bytes()).is_err());
+}
+
+#[test]
+fn test_check() {
+    let hash = input_hash("12\n14\n");
+    assert_eq!(hash, input_hash("12\n14"));
+    let mut answers = Answers::default();
+    answers.insert(1, Part::One, hash, "4");
+    assert_eq!(answers.check(1, Part::One, hash, "4"), Check::Match);
+    assert_eq!(
+        answers.check(1, Part::One, hash, "5"),
+        Check::Mismatch {
+            expected: "4".to_string()
+        }
+    );
+    assert_eq!(answers.check(1, Part::Two, hash, "4"), Check::Unknown);
+    assert_eq!(answers.check(1, Part::One, input_hash("13\n"), "4"), Check::Unknown);
+}
@@ -0,0 +1,283 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The day 13 arcade cabinet: an IntCode program that draws a breakout game
+//! by outputting `(x, y, tile)` triples, and reads the joystick as input.
+
+use crate::intcode::{InterpreterError, Machine, State};
+use num_enum::TryFromPrimitive;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Hash, TryFromPrimitive)]
+#[repr(i64)]
+/// A tile on the arcade screen.
+pub enum Tile {
+    Empty = 0,
+    Wall = 1,
+    Block = 2,
+    Paddle = 3,
+    Ball = 4,
+}
+
+impl Tile {
+    /// The character used to draw the tile in a terminal.
+    pub fn glyph(self) -> char {
+        match self {
+            Tile::Empty => ' ',
+            Tile::Wall => '#',
+            Tile::Block => '=',
+            Tile::Paddle => '-',
+            Tile::Ball => 'o',
+        }
+    }
+}
+
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy)]
+#[repr(i64)]
+/// Joystick positions, as the values the game reads them as.
+pub enum Joystick {
+    Left = -1,
+    Neutral = 0,
+    Right = 1,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+/// Things that can go wrong running the arcade.
+pub enum ArcadeError {
+    /// The IntCode program itself failed.
+    Interpreter(InterpreterError),
+    /// The program output something that isn't a whole number of triples.
+    PartialOutput(Vec<i64>),
+    /// The program tried to draw a tile that doesn't exist.
+    InvalidTile { x: i64, y: i64, id: i64 },
+}
+
+impl fmt::Display for ArcadeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArcadeError::Interpreter(err) => write!(f, "Arcade error: {}", err),
+            ArcadeError::PartialOutput(output) => {
+                write!(f, "Arcade error: output {:?} is not a whole triple", output)
+            }
+            ArcadeError::InvalidTile { x, y, id } => {
+                write!(f, "Arcade error: invalid tile {} at ({}, {})", id, x, y)
+            }
+        }
+    }
+}
+
+impl Error for ArcadeError {}
+
+impl From<InterpreterError> for ArcadeError {
+    fn from(err: InterpreterError) -> Self {
+        ArcadeError::Interpreter(err)
+    }
+}
+
+/// The screen, plus the score segment display. Anything never drawn is
+/// `Tile::Empty`.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Screen {
+    tiles: HashMap<(i64, i64), Tile>,
+    score: i64,
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Updates the screen from program output. `(-1, 0, score)` sets the
+    /// score instead of drawing a tile.
+    pub fn apply(&mut self, output: &[i64]) -> Result<(), ArcadeError> {
+        let mut chunks = output.chunks_exact(3);
+        for triple in &mut chunks {
+            let (x, y, id) = (triple[0], triple[1], triple[2]);
+            if x == -1 && y == 0 {
+                self.score = id;
+            } else {
+                let tile = Tile::try_from(id).map_err(|_| ArcadeError::InvalidTile { x, y, id })?;
+                self.tiles.insert((x, y), tile);
+            }
+        }
+        if chunks.remainder().is_empty() {
+            Ok(())
+        } else {
+            Err(ArcadeError::PartialOutput(chunks.remainder().to_vec()))
+        }
+    }
+
+    pub fn tile(&self, x: i64, y: i64) -> Tile {
+        self.tiles.get(&(x, y)).copied().unwrap_or(Tile::Empty)
+    }
+
+    pub fn score(&self) -> i64 {
+        self.score
+    }
+
+    /// How many of a tile are currently on screen.
+    pub fn count(&self, tile: Tile) -> usize {
+        self.tiles.values().filter(|&&t| t == tile).count()
+    }
+
+    /// Finds where a tile is. Only really useful for the ball and paddle,
+    /// since there should only be one of each.
+    pub fn find(&self, tile: Tile) -> Option<(i64, i64)> {
+        self.tiles
+            .iter()
+            .find(|(_, &t)| t == tile)
+            .map(|(&pos, _)| pos)
+    }
+
+    /// Draws the screen, with the score underneath.
+    pub fn render(&self) -> String {
+        let max_x = self.tiles.keys().map(|&(x, _)| x).max().unwrap_or(-1);
+        let max_y = self.tiles.keys().map(|&(_, y)| y).max().unwrap_or(-1);
+        let mut res = String::new();
+        for y in 0..=max_y {
+            let line: String = (0..=max_x).map(|x| self.tile(x, y).glyph()).collect();
+            res.push_str(line.trim_end());
+            res.push('\n');
+        }
+        res.push_str(&format!("Score: {}\n", self.score));
+        res
+    }
+}
+
+/// Moves the paddle towards wherever the ball is.
+pub fn autopilot(screen: &Screen) -> Joystick {
+    match (screen.find(Tile::Ball), screen.find(Tile::Paddle)) {
+        (Some((ball, _)), Some((paddle, _))) if ball < paddle => Joystick::Left,
+        (Some((ball, _)), Some((paddle, _))) if ball > paddle => Joystick::Right,
+        _ => Joystick::Neutral,
+    }
+}
+
+/// The arcade cabinet itself: the game program, and the screen it draws on.
+pub struct Arcade {
+    machine: Machine,
+    screen: Screen,
+    frames: Option<Vec<Screen>>,
+}
+
+impl Arcade {
+    pub fn new(program: Vec<i64>) -> Self {
+        Arcade {
+            machine: Machine::new(program),
+            screen: Screen::new(),
+            frames: None,
+        }
+    }
+
+    /// Like `new`, but with memory address 0 set to 2 so the game can be
+    /// played without quarters.
+    pub fn free_play(program: Vec<i64>) -> Self {
+        let mut arcade = Arcade::new(program);
+        arcade.machine.poke(0, 2);
+        arcade
+    }
+
+    /// Starts keeping a copy of the screen every time the game stops for
+    /// input (and when it halts), for playing back later.
+    pub fn record(&mut self) {
+        self.frames.get_or_insert_with(Vec::new);
+    }
+
+    /// The recorded frames, if recording.
+    pub fn frames(&self) -> &[Screen] {
+        self.frames.as_deref().unwrap_or(&[])
+    }
+
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    /// The machine running the game, for things like recording a session.
+    pub fn machine_mut(&mut self) -> &mut Machine {
+        &mut self.machine
+    }
+
+    /// Runs the game until it wants the joystick or halts, and draws what it
+    /// output.
+    pub fn run(&mut self) -> Result<State, ArcadeError> {
+        let state = self.machine.run()?;
+        self.screen.apply(&self.machine.take_output())?;
+        if let Some(frames) = &mut self.frames {
+            frames.push(self.screen.clone());
+        }
+        Ok(state)
+    }
+
+    pub fn joystick(&mut self, position: Joystick) {
+        self.machine.push_input(position as i64);
+    }
+
+    /// Plays the game to the end, asking the controller where to put the
+    /// joystick every time the game wants to know. Returns the final score.
+    pub fn play<F: FnMut(&Screen) -> Joystick>(&mut self, mut controller: F) -> Result<i64, ArcadeError> {
+        while self.run()? != State::Halted {
+            let position = controller(&self.screen);
+            self.joystick(position);
+        }
+        Ok(self.screen.score())
+    }
+}
+
+/// Draws recorded frames one after another, clearing the terminal in between.
+pub fn playback<W: Write>(frames: &[Screen], out: &mut W, delay: Duration) -> io::Result<()> {
+    for frame in frames {
+        write!(out, "\x1b[2J\x1b[H{}", frame.render())?;
+        out.flush()?;
+        thread::sleep(delay);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_screen() {
+    let mut screen = Screen::new();
+    assert_eq!(screen.apply(&[1, 2, 3, 6, 5, 4, -1, 0, 12345]), Ok(()));
+    assert_eq!(screen.tile(1, 2), Tile::Paddle);
+    assert_eq!(screen.tile(6, 5), Tile::Ball);
+    assert_eq!(screen.tile(0, 0), Tile::Empty);
+    assert_eq!(screen.score(), 12345);
+    assert_eq!(screen.apply(&[0, 0, 7]), Err(ArcadeError::InvalidTile { x: 0, y: 0, id: 7 }));
+    assert_eq!(screen.apply(&[0, 0]), Err(ArcadeError::PartialOutput(vec![0, 0])));
+
+    let mut screen = Screen::new();
+    screen.apply(&[0, 0, 1, 1, 0, 1, 2, 0, 1, 1, 1, 4, 0, 2, 2, 2, 2, 3]).unwrap();
+    assert_eq!(screen.render(), "###\n o\n= -\nScore: 0\n");
+    assert_eq!(autopilot(&screen), Joystick::Left);
+}
+
+#[test]
+fn test_play() {
+    // Draws the ball left of the paddle, reads the joystick, and then shows
+    // whatever it read as the score
+    let program = vec![
+        104, 1, 104, 2, 104, 4, 104, 3, 104, 2, 104, 3, 3, 100, 104, -1, 104, 0, 4, 100, 99,
+    ];
+    let mut arcade = Arcade::new(program);
+    arcade.record();
+    assert_eq!(arcade.play(autopilot), Ok(Joystick::Left as i64));
+    assert_eq!(arcade.frames().len(), 2);
+    assert_eq!(arcade.frames()[0].score(), 0);
+}
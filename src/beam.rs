@@ -0,0 +1,263 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The day 19 tractor beam. The drone program answers whether a single
+//! `(x, y)` is in the beam and then halts, so every probe costs a whole run.
+//! The beam is a cone out of the origin, so each row is one unbroken run of
+//! points, and tracking where each row starts and ends keeps the number of
+//! probes down.
+
+use crate::geometry::Point;
+use crate::intcode::{InterpreterError, Machine};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq)]
+/// Things that can go wrong scanning the beam.
+pub enum BeamError {
+    /// The IntCode program itself failed.
+    Interpreter(InterpreterError),
+    /// The drone program finished without saying anything about a point.
+    NoOutput { x: i64, y: i64 },
+}
+
+impl fmt::Display for BeamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let err: String = match self {
+            BeamError::Interpreter(err) => err.to_string(),
+            BeamError::NoOutput { x, y } => format!("No output for ({}, {})", x, y),
+        };
+        write!(f, "Beam error: {}", err)
+    }
+}
+
+impl Error for BeamError {}
+
+impl From<InterpreterError> for BeamError {
+    fn from(err: InterpreterError) -> Self {
+        BeamError::Interpreter(err)
+    }
+}
+
+/// Anything that can say whether a point is in the beam.
+pub trait Probe {
+    fn probe(&mut self, x: i64, y: i64) -> Result<bool, BeamError>;
+}
+
+/// Probes by running a fresh copy of the drone program.
+pub struct IntcodeProbe {
+    machine: Machine,
+}
+
+impl IntcodeProbe {
+    pub fn new(program: Vec<i64>) -> Self {
+        IntcodeProbe {
+            machine: Machine::new(program),
+        }
+    }
+}
+
+impl Probe for IntcodeProbe {
+    fn probe(&mut self, x: i64, y: i64) -> Result<bool, BeamError> {
+        let mut machine = self.machine.clone();
+        let output = machine.run_with_input(vec![x, y])?;
+        output.first().map(|&v| v == 1).ok_or(BeamError::NoOutput { x, y })
+    }
+}
+
+/// Any function of a point will do for a beam, which is handy for tests.
+impl<F: FnMut(i64, i64) -> bool> Probe for F {
+    fn probe(&mut self, x: i64, y: i64) -> Result<bool, BeamError> {
+        Ok(self(x, y))
+    }
+}
+
+/// Scans the beam, remembering every probe so nothing is run twice.
+pub struct Scanner<P: Probe> {
+    probe: P,
+    cache: HashMap<(i64, i64), bool>,
+    /// The first and last x in the beam for each row scanned so far, or
+    /// `None` if the row missed the beam entirely. Close to the origin the
+    /// beam is thin enough to fall between points.
+    rows: BTreeMap<i64, Option<(i64, i64)>>,
+    max_slope: i64,
+}
+
+impl<P: Probe> Scanner<P> {
+    pub fn new(probe: P) -> Self {
+        Scanner {
+            probe,
+            cache: HashMap::new(),
+            rows: BTreeMap::new(),
+            max_slope: 10,
+        }
+    }
+
+    /// How far right the beam can be, as x per row of y, which bounds the
+    /// search for rows the beam might have missed. Defaults to 10.
+    pub fn with_max_slope(mut self, max_slope: i64) -> Self {
+        self.max_slope = max_slope;
+        self
+    }
+
+    /// How many times the probe has actually been run.
+    pub fn runs(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Whether a point is in the beam.
+    pub fn is_pulled(&mut self, x: i64, y: i64) -> Result<bool, BeamError> {
+        if let Some(&pulled) = self.cache.get(&(x, y)) {
+            return Ok(pulled);
+        }
+        let pulled = self.probe.probe(x, y)?;
+        self.cache.insert((x, y), pulled);
+        Ok(pulled)
+    }
+
+    /// The first and last x in the beam for a row, working down from the last
+    /// row that was scanned.
+    pub fn row_edges(&mut self, y: i64) -> Result<Option<(i64, i64)>, BeamError> {
+        if let Some(&edges) = self.rows.get(&y) {
+            return Ok(edges);
+        }
+        let first = self.rows.keys().next_back().map_or(0, |&last| last + 1);
+        for row in first..=y {
+            let edges = self.scan_row(row)?;
+            self.rows.insert(row, edges);
+        }
+        Ok(self.rows[&y])
+    }
+
+    fn scan_row(&mut self, y: i64) -> Result<Option<(i64, i64)>, BeamError> {
+        // Both edges only ever move right, so start from the last row that hit
+        let (prev_start, prev_end) = self
+            .rows
+            .values()
+            .rev()
+            .find_map(|&edges| edges)
+            .unwrap_or((0, 0));
+        let mut start = prev_start;
+        while !self.is_pulled(start, y)? {
+            start += 1;
+            if start > y * self.max_slope {
+                return Ok(None);
+            }
+        }
+        let mut end = prev_end.max(start);
+        if !self.is_pulled(end, y)? {
+            end = start;
+        }
+        while self.is_pulled(end + 1, y)? {
+            end += 1;
+        }
+        Ok(Some((start, end)))
+    }
+
+    /// How many points are in the beam in the square from the origin out to
+    /// `size` points across.
+    pub fn count_area(&mut self, size: i64) -> Result<usize, BeamError> {
+        let mut count = 0;
+        for y in 0..size {
+            if let Some((start, end)) = self.row_edges(y)? {
+                if start < size {
+                    count += (end.min(size - 1) - start + 1) as usize;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Finds the top left corner of the square closest to the origin that fits
+    /// entirely in the beam, looking no further down than `max_y`.
+    pub fn find_square(&mut self, size: i64, max_y: i64) -> Result<Option<Point>, BeamError> {
+        // The bottom left corner of the square sits on the start of a row, so
+        // check whether the row at the top of the square reaches far enough
+        for bottom in (size - 1)..=max_y {
+            let top = bottom - size + 1;
+            if let (Some((start, _)), Some((_, top_end))) = (self.row_edges(bottom)?, self.row_edges(top)?) {
+                if top_end >= start + size - 1 {
+                    return Ok(Some(Point::new(start as isize, top as isize)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Draws every point probed so far in a region from the origin, with `#`
+    /// for the beam, `.` for empty space, and a blank for points never probed.
+    pub fn render(&self, width: i64, height: i64) -> Vec<String> {
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| match self.cache.get(&(x, y)) {
+                        Some(true) => '#',
+                        Some(false) => '.',
+                        None => ' ',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+/// A beam between the lines x = y/2 and x = y, not quite touching either, which misses the
+/// first couple of rows past the origin.
+fn fake_beam(x: i64, y: i64) -> bool {
+    (x == 0 && y == 0) || (2 * x > y && x < y)
+}
+
+#[test]
+fn test_edges_and_area() {
+    let mut scanner = Scanner::new(fake_beam);
+    assert_eq!(scanner.row_edges(0), Ok(Some((0, 0))));
+    assert_eq!(scanner.row_edges(1), Ok(None));
+    assert_eq!(scanner.row_edges(3), Ok(Some((2, 2))));
+    assert_eq!(scanner.row_edges(9), Ok(Some((5, 8))));
+    let brute = (0..10)
+        .flat_map(|y| (0..10).map(move |x| (x, y)))
+        .filter(|&(x, y)| fake_beam(x, y))
+        .count();
+    assert_eq!(scanner.count_area(10), Ok(brute));
+    // Edge tracking means far fewer probes than checking every point
+    assert!(scanner.runs() < 100);
+    assert_eq!(scanner.render(6, 4), vec!["#.    ", "......", "......", "..#.  "]);
+}
+
+#[test]
+fn test_find_square() {
+    let mut scanner = Scanner::new(fake_beam);
+    let corner = scanner.find_square(3, 100).unwrap().unwrap();
+    assert_eq!(corner, Point::new(6, 9));
+    for y in 9..12 {
+        for x in 6..9 {
+            assert!(fake_beam(x, y));
+        }
+    }
+    assert_eq!(Scanner::new(fake_beam).find_square(3, 10), Ok(None));
+}
+
+#[test]
+fn test_intcode_probe() {
+    // In the beam when x == y, for a diagonal beam
+    let program = vec![3, 11, 3, 12, 8, 11, 12, 13, 4, 13, 99, 0, 0, 0];
+    let mut scanner = Scanner::new(IntcodeProbe::new(program));
+    assert_eq!(scanner.is_pulled(4, 4), Ok(true));
+    assert_eq!(scanner.is_pulled(4, 5), Ok(false));
+    assert_eq!(scanner.is_pulled(4, 4), Ok(true));
+    assert_eq!(scanner.runs(), 2);
+}
@@ -0,0 +1,260 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use aoc_2019::answers::{self, input_hash, Answers, Check};
+use aoc_2019::days::{Day, DAYS};
+use aoc_2019::timing::{self, Timed};
+use aoc_2019::Part;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "Usage: aoc [verify [--record] [--answers FILE]] [DAY] [--part 1|2] [--input FILE] \
+                     [--time] [--runs N] [--json]";
+
+/// What was asked for on the command line.
+struct Options {
+    day: Option<u32>,
+    part: Option<Part>,
+    input: Option<String>,
+    /// Check the answers against the known ones instead of printing them.
+    verify: bool,
+    /// Save any answers that weren't known yet.
+    record: bool,
+    answers: Option<String>,
+    /// How many times to run each day for timing, if it's being timed.
+    runs: Option<usize>,
+    /// Give the answers and timings as JSON, rather than as text.
+    json: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        day: None,
+        part: None,
+        input: None,
+        verify: args.first().map(String::as_str) == Some("verify"),
+        record: false,
+        answers: None,
+        runs: None,
+        json: false,
+    };
+    let mut args = args.iter().skip(options.verify as usize);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--part" => options.part = Some(value()?.parse()?),
+            "--input" => options.input = Some(value()?.clone()),
+            "--record" if options.verify => options.record = true,
+            "--answers" if options.verify => options.answers = Some(value()?.clone()),
+            "--time" => options.runs = Some(options.runs.unwrap_or(1)),
+            "--runs" => options.runs = Some(value()?.parse().map_err(|_| "--runs needs a number".to_string())?),
+            "--json" if options.verify => return Err("--json doesn't go with verify".to_string()),
+            "--json" => options.json = true,
+            _ if options.day.is_none() => {
+                options.day = Some(arg.parse().map_err(|_| format!("`{}` isn't a day", arg))?);
+            }
+            _ => return Err(format!("Unexpected `{}`", arg)),
+        }
+    }
+    if options.input.is_some() && options.day.is_none() {
+        return Err("--input needs a day to go with it".to_string());
+    }
+    if options.json {
+        options.runs = Some(options.runs.unwrap_or(1));
+    }
+    Ok(options)
+}
+
+fn print_answer(day: &Day, part: Part, answer: &str) {
+    // Some answers are pictures, which want to start on their own line
+    if answer.contains('\n') {
+        println!("Day {} part {}:\n{}", day.number, part, answer.trim_end());
+    } else {
+        println!("Day {} part {}: {}", day.number, part, answer);
+    }
+}
+
+/// How verifying's gone so far.
+#[derive(Default)]
+struct Tally {
+    matched: usize,
+    mismatched: usize,
+    unknown: usize,
+}
+
+/// Checks a day's answers against the known ones for its input, adding any
+/// that aren't known if `record` is set.
+fn verify(day: &Day, input: &str, answers: &mut Answers, record: bool, tally: &mut Tally, found: Vec<(Part, String)>) {
+    let hash = input_hash(input);
+    // Pictures go on one line here, same as in the answers file
+    let show = |answer: &str| answer.trim_end().replace('\n', "\\n");
+    for (part, answer) in found {
+        match answers.check(day.number, part, hash, &answer) {
+            Check::Match => {
+                tally.matched += 1;
+                println!("Day {} part {}: ok", day.number, part);
+            }
+            Check::Mismatch { expected } => {
+                tally.mismatched += 1;
+                println!(
+                    "Day {} part {}: MISMATCH, expected {} but got {}",
+                    day.number,
+                    part,
+                    show(&expected),
+                    show(&answer)
+                );
+            }
+            Check::Unknown => {
+                tally.unknown += 1;
+                let note = if record { "recorded" } else { "no known answer" };
+                println!("Day {} part {}: {} ({})", day.number, part, show(&answer), note);
+                if record {
+                    answers.insert(day.number, part, hash, &answer);
+                }
+            }
+        }
+    }
+}
+
+fn main() {
+    // aoc [DAY] [--part 1|2] [--input FILE]. Without a day, runs every day
+    // there's an input for in input/. With verify first, the answers are
+    // checked against the ones in input/answers.txt (or --answers FILE)
+    // instead, and --record adds any that aren't in there yet. --time times
+    // each phase, over N runs with --runs N, and --json gives the answers and
+    // timings as JSON
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = parse_args(&args).unwrap_or_else(|err| {
+        eprintln!("{}\n{}", err, USAGE);
+        process::exit(2);
+    });
+    let parts = match options.part {
+        Some(part) => vec![part],
+        None => Part::both().to_vec(),
+    };
+    let days: Vec<&Day> = match options.day {
+        Some(number) => match Day::find(number) {
+            Some(day) => vec![day],
+            None => {
+                eprintln!("Day {} isn't solved", number);
+                process::exit(2);
+            }
+        },
+        None => DAYS.iter().collect(),
+    };
+    let answers_path = options
+        .answers
+        .clone()
+        .unwrap_or_else(|| answers::DEFAULT_PATH.to_string());
+    let mut answers = if options.verify {
+        Answers::load(&answers_path).unwrap_or_else(|err| {
+            eprintln!("Can't load the answers from {}: {}", answers_path, err);
+            process::exit(1);
+        })
+    } else {
+        Answers::default()
+    };
+    let mut tally = Tally::default();
+    let mut timings: Vec<Timed> = Vec::new();
+    let mut failed = false;
+    for day in days {
+        let path = options
+            .input
+            .clone()
+            .map(Into::into)
+            .unwrap_or_else(|| day.input_path());
+        let input = match fs::read_to_string(&path) {
+            Ok(input) => input,
+            // Not everyone has every input, so that's fine when running them all
+            Err(_) if options.day.is_none() && !path.exists() => continue,
+            Err(err) => {
+                eprintln!("Day {}: can't read {}: {}", day.number, path.display(), err);
+                failed = true;
+                continue;
+            }
+        };
+        let solved = match options.runs {
+            Some(runs) => timing::time(day, &input, &parts, runs).map(|timed| {
+                let found = timed.answers.clone();
+                timings.push(timed);
+                found
+            }),
+            None => day.solve(&input, &parts),
+        };
+        match solved {
+            Ok(_) if options.json => {}
+            Ok(found) if options.verify => verify(day, &input, &mut answers, options.record, &mut tally, found),
+            Ok(found) => {
+                for (part, answer) in found {
+                    print_answer(day, part, &answer);
+                }
+            }
+            Err(err) => {
+                eprintln!("Day {}: {}", day.number, err);
+                failed = true;
+            }
+        }
+    }
+    if options.verify {
+        println!(
+            "{} matched, {} mismatched, {} with no known answer",
+            tally.matched, tally.mismatched, tally.unknown
+        );
+        if options.record && tally.unknown > 0 {
+            if let Err(err) = answers.save(&answers_path) {
+                eprintln!("Can't save the answers to {}: {}", answers_path, err);
+                failed = true;
+            }
+        }
+        failed |= tally.mismatched > 0;
+    }
+    if options.json {
+        println!("{:#}", timing::to_json(&timings));
+    } else if options.runs.is_some() {
+        print!("\n{}", timing::table(&timings));
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+#[test]
+fn test_parse_args() {
+    let args = |text: &str| text.split_whitespace().map(String::from).collect::<Vec<_>>();
+    let options = parse_args(&args("3 --part 2 --input wires.txt")).unwrap();
+    assert_eq!(options.day, Some(3));
+    assert_eq!(options.part, Some(Part::Two));
+    assert_eq!(options.input.as_deref(), Some("wires.txt"));
+    assert_eq!(parse_args(&args("")).unwrap().day, None);
+    let options = parse_args(&args("verify 2 --record")).unwrap();
+    assert!(options.verify && options.record);
+    assert_eq!(options.day, Some(2));
+    assert_eq!(parse_args(&args("2 --record")).err().unwrap(), "Unexpected `--record`");
+    assert_eq!(parse_args(&args("4 --time")).unwrap().runs, Some(1));
+    let options = parse_args(&args("--json --runs 5")).unwrap();
+    assert_eq!((options.runs, options.json), (Some(5), true));
+    assert_eq!(parse_args(&args("--json")).unwrap().runs, Some(1));
+    assert_eq!(parse_args(&args("verify --json")).err().unwrap(), "--json doesn't go with verify");
+    assert_eq!(parse_args(&args("3 --part")).err().unwrap(), "--part needs a value");
+    assert_eq!(
+        parse_args(&args("3 --part 3")).err().unwrap(),
+        "There's no part `3`, only 1 and 2"
+    );
+    assert_eq!(parse_args(&args("three")).err().unwrap(), "`three` isn't a day");
+    assert_eq!(
+        parse_args(&args("--input x")).err().unwrap(),
+        "--input needs a day to go with it"
+    );
+}
@@ -0,0 +1,25 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use aoc_2019::intcode::dap::DapServer;
+use std::io;
+
+fn main() -> io::Result<()> {
+    // Speaks the Debug Adapter Protocol over stdin and stdout, so point an
+    // editor's debug adapter config at this
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    DapServer::new().serve(stdin.lock(), stdout.lock())
+}
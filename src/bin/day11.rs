@@ -0,0 +1,36 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use aoc_2019::days::day11::Day11;
+use aoc_2019::hull::{Color, Simulator};
+use aoc_2019::Solution;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // Same answers as `aoc 11`, plus --image to also get the registration
+    // identifier as an image, for when the text is hard to read
+    let program = Day11.parse(&fs::read_to_string("input/day11.txt")?)?;
+    println!("Day 11 part 1: {} panels painted", Day11.part1(&program)?);
+    println!("Day 11 part 2:\n{}", Day11.part2(&program)?);
+    if std::env::args().any(|arg| arg == "--image") {
+        let mut sim = Simulator::new(program, Color::White);
+        let hull = sim.run()?;
+        hull.write_pbm(&mut BufWriter::new(File::create("day11.pbm")?), 8)?;
+        println!("Wrote day11.pbm");
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use aoc_2019::arcade::{autopilot, playback, Arcade};
+use aoc_2019::days::day13::Day13;
+use aoc_2019::intcode::session::Session;
+use aoc_2019::Solution;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // Same answers as `aoc 13`. Then pass --watch to see a game played back
+    // after it's been beaten, --record FILE to save the joystick inputs, or
+    // --replay FILE to play them back instead of using the autopilot
+    let args: Vec<String> = std::env::args().collect();
+    let watch = args.iter().any(|arg| arg == "--watch");
+    let option = |name: &str| args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1));
+    let (record, replay) = (option("--record"), option("--replay"));
+    let program = Day13.parse(&fs::read_to_string("input/day13.txt")?)?;
+    println!("Day 13 part 1: {} block tiles", Day13.part1(&program)?);
+    println!("Day 13 part 2: final score {}", Day13.part2(&program)?);
+    if !watch && record.is_none() && replay.is_none() {
+        return Ok(());
+    }
+
+    let mut arcade = Arcade::free_play(program);
+    if watch {
+        arcade.record();
+    }
+    if record.is_some() {
+        arcade.machine_mut().start_recording();
+    }
+    let score = match replay {
+        Some(path) => {
+            Session::load(path)?.replay(arcade.machine_mut())?;
+            arcade.run()?;
+            arcade.screen().score()
+        }
+        None => arcade.play(autopilot)?,
+    };
+    if let (Some(path), Some(session)) = (record, arcade.machine_mut().take_recording()) {
+        session.save(path)?;
+    }
+    if watch {
+        playback(arcade.frames(), &mut io::stdout(), Duration::from_millis(15))?;
+    }
+    if replay.is_some() {
+        println!("Replayed game: final score {}", score);
+    }
+    Ok(())
+}
@@ -0,0 +1,34 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use aoc_2019::days::day15::Day15;
+use aoc_2019::droid::{IntcodeDroid, Map};
+use aoc_2019::Solution;
+use std::error::Error;
+use std::fs;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // Same answers as `aoc 15`, plus --map to see what the droid found
+    let program = Day15.parse(&fs::read_to_string("input/day15.txt")?)?;
+    if std::env::args().any(|arg| arg == "--map") {
+        let map = Map::explore(&mut IntcodeDroid::new(program.clone()))?;
+        for line in map.render() {
+            println!("{}", line);
+        }
+    }
+    println!("Day 15 part 1: {} moves to the oxygen system", Day15.part1(&program)?);
+    println!("Day 15 part 2: {} minutes to fill with oxygen", Day15.part2(&program)?);
+    Ok(())
+}
@@ -0,0 +1,37 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use aoc_2019::beam::{IntcodeProbe, Scanner};
+use aoc_2019::days::day19::Day19;
+use aoc_2019::Solution;
+use std::error::Error;
+use std::fs;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // Same answers as `aoc 19`, plus --show to see the part of the beam that
+    // was scanned, and how many runs of the program that took
+    let program = Day19.parse(&fs::read_to_string("input/day19.txt")?)?;
+    if std::env::args().any(|arg| arg == "--show") {
+        let mut scanner = Scanner::new(IntcodeProbe::new(program.clone()));
+        scanner.count_area(50)?;
+        for line in scanner.render(50, 50) {
+            println!("{}", line);
+        }
+        println!("({} runs)", scanner.runs());
+    }
+    println!("Day 19 part 1: {} points affected", Day19.part1(&program)?);
+    println!("Day 19 part 2: result {}", Day19.part2(&program)?);
+    Ok(())
+}
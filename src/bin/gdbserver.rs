@@ -0,0 +1,34 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use aoc_2019::intcode::debug::Debugger;
+use aoc_2019::intcode::gdb::GdbStub;
+use aoc_2019::intcode::{Machine, Program};
+use std::error::Error;
+use std::net::TcpListener;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // gdbserver PROGRAM [PORT], then `target remote localhost:PORT` in GDB
+    let args: Vec<String> = std::env::args().collect();
+    let path = args.get(1).ok_or("Usage: gdbserver PROGRAM [PORT]")?;
+    let port: u16 = args.get(2).map_or(Ok(1234), |port| port.parse())?;
+    let program = Program::from_file(path)?.into_vec();
+
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("Listening on {}", listener.local_addr()?);
+    let mut stub = GdbStub::new(Debugger::new(Machine::new(program)));
+    stub.accept(&listener)?;
+    Ok(())
+}
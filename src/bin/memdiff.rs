@@ -0,0 +1,52 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use aoc_2019::intcode::diff::MemoryDiff;
+use aoc_2019::intcode::table::InstructionTable;
+use aoc_2019::intcode::Program;
+use std::error::Error;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // memdiff BEFORE AFTER [--program FILE] [--json], with the images in the
+    // same format as programs. Code is worked out from the program if it's
+    // given, or BEFORE if not
+    let args: Vec<String> = std::env::args().collect();
+    let json = args.iter().any(|arg| arg == "--json");
+    let program = args
+        .iter()
+        .position(|arg| arg == "--program")
+        .and_then(|i| args.get(i + 1));
+    let mut files = args
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|&(i, arg)| !arg.starts_with("--") && args[i - 1] != "--program")
+        .map(|(_, arg)| arg);
+    let usage = "Usage: memdiff BEFORE AFTER [--program FILE] [--json]";
+    let before = Program::from_file(files.next().ok_or(usage)?)?.into_vec();
+    let after = Program::from_file(files.next().ok_or(usage)?)?.into_vec();
+    let program = match program {
+        Some(path) => Program::from_file(path)?.into_vec(),
+        None => before.clone(),
+    };
+
+    let diff = MemoryDiff::new(&before, &after, &program, &InstructionTable::new());
+    if json {
+        println!("{}", diff.to_json());
+    } else {
+        print!("{}", diff.report());
+    }
+    Ok(())
+}
@@ -0,0 +1,27 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use aoc_2019::intcode::translate::translate_file;
+use std::error::Error;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // translate PROGRAM MODULE OUTPUT, then `include!` OUTPUT somewhere
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        return Err("Usage: translate PROGRAM MODULE OUTPUT".into());
+    }
+    translate_file(&args[1], &args[2], &args[3])?;
+    Ok(())
+}
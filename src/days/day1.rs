@@ -13,44 +13,59 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use aoc_2019::*;
+//! Day 1: The Tyranny of the Rocket Equation
+
+use crate::Solution;
 use std::error::Error;
 
-fn simple_fuel_needed(module_masses: Vec<i64>) -> i64 {
-    module_masses.iter().map(|mass| mass/3-2).sum()
+fn simple_fuel_needed(module_masses: &[i64]) -> i64 {
+    module_masses.iter().map(|mass| mass / 3 - 2).sum()
 }
 
 fn mass_with_fuel(mass: &i64) -> i64 {
     // TODO: Feel like there should be a more elegant solution to this...
     let mut total = 0;
-    let mut fuel = mass/3-2;
+    let mut fuel = mass / 3 - 2;
     while fuel > 0 {
-        total = total + fuel;
-        fuel = fuel/3-2;
+        total += fuel;
+        fuel = fuel / 3 - 2;
     }
     total
 }
 
-fn realistic_fuel_needed(module_masses: Vec<i64>) -> i64 {
+fn realistic_fuel_needed(module_masses: &[i64]) -> i64 {
     module_masses.iter().map(mass_with_fuel).sum()
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let lines: Vec<i64> = read_input("input/day1.txt")?
-        .iter()
-        .map(|l| l.parse().unwrap())
-        .collect();
-    println!("part 1 total: {}", simple_fuel_needed(lines.clone()));
-    println!("part 2 total: {}", realistic_fuel_needed(lines));
-    Ok(())
+pub struct Day1;
+
+impl Solution for Day1 {
+    type Input = Vec<i64>;
+
+    fn parse(&self, input: &str) -> Result<Vec<i64>, Box<dyn Error>> {
+        Ok(input
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn part1(&self, masses: &Vec<i64>) -> Result<String, Box<dyn Error>> {
+        Ok(simple_fuel_needed(masses).to_string())
+    }
+
+    fn part2(&self, masses: &Vec<i64>) -> Result<String, Box<dyn Error>> {
+        Ok(realistic_fuel_needed(masses).to_string())
+    }
 }
 
 #[test]
 fn test_simple_fuel() {
-    assert_eq!(simple_fuel_needed(vec![12]), 2);
-    assert_eq!(simple_fuel_needed(vec![14]), 2);
-    assert_eq!(simple_fuel_needed(vec![1969]), 654);
-    assert_eq!(simple_fuel_needed(vec![100756]), 33583);
+    assert_eq!(simple_fuel_needed(&[12]), 2);
+    assert_eq!(simple_fuel_needed(&[14]), 2);
+    assert_eq!(simple_fuel_needed(&[1969]), 654);
+    assert_eq!(simple_fuel_needed(&[100756]), 33583);
 }
 
 #[test]
@@ -58,4 +73,4 @@ fn test_full_fuel() {
     assert_eq!(mass_with_fuel(&14), 2);
     assert_eq!(mass_with_fuel(&1969), 966);
     assert_eq!(mass_with_fuel(&100756), 50346);
-}
\ No newline at end of file
+}
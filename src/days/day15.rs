@@ -0,0 +1,41 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Day 15: Oxygen System. The droid is in `droid`.
+
+use crate::droid::{IntcodeDroid, Map};
+use crate::intcode::Program;
+use crate::Solution;
+use std::error::Error;
+
+pub struct Day15;
+
+impl Solution for Day15 {
+    type Input = Vec<i64>;
+
+    fn parse(&self, input: &str) -> Result<Vec<i64>, Box<dyn Error>> {
+        Ok(input.parse::<Program>()?.into_vec())
+    }
+
+    fn part1(&self, program: &Vec<i64>) -> Result<String, Box<dyn Error>> {
+        let map = Map::explore(&mut IntcodeDroid::new(program.clone()))?;
+        Ok(map.shortest_path().ok_or("No path to the oxygen system")?.to_string())
+    }
+
+    fn part2(&self, program: &Vec<i64>) -> Result<String, Box<dyn Error>> {
+        let map = Map::explore(&mut IntcodeDroid::new(program.clone()))?;
+        Ok(map.fill_time().ok_or("No oxygen system")?.to_string())
+    }
+}
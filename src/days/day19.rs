@@ -0,0 +1,42 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Day 19: Tractor Beam. The scanning is in `beam`.
+
+use crate::beam::{IntcodeProbe, Scanner};
+use crate::intcode::Program;
+use crate::Solution;
+use std::error::Error;
+
+pub struct Day19;
+
+impl Solution for Day19 {
+    type Input = Vec<i64>;
+
+    fn parse(&self, input: &str) -> Result<Vec<i64>, Box<dyn Error>> {
+        Ok(input.parse::<Program>()?.into_vec())
+    }
+
+    fn part1(&self, program: &Vec<i64>) -> Result<String, Box<dyn Error>> {
+        let mut scanner = Scanner::new(IntcodeProbe::new(program.clone()));
+        Ok(scanner.count_area(50)?.to_string())
+    }
+
+    fn part2(&self, program: &Vec<i64>) -> Result<String, Box<dyn Error>> {
+        let mut scanner = Scanner::new(IntcodeProbe::new(program.clone()));
+        let corner = scanner.find_square(100, 10_000)?.ok_or("No room for the ship")?;
+        Ok((corner.x * 10000 + corner.y).to_string())
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Day 2: 1202 Program Alarm
+
+use crate::intcode::search::{search_first, Options, Patch, SearchError};
+use crate::intcode::{Machine, Program};
+use crate::Solution;
+use std::error::Error;
+
+// XXX: Not sure if the desired result of 19690720 is specific to me...
+pub const TARGET: i64 = 19690720;
+
+/// Finds the noun and verb that make the program leave `result` in cell 0.
+pub fn find_inputs(memory: &[i64], result: i64) -> Result<(i64, i64), SearchError> {
+    let patches = [Patch::new(1, 0..=99), Patch::new(2, 0..=99)];
+    let (values, _) = search_first(memory, &patches, &Options::default(), |machine| machine.peek(0) == result)?;
+    Ok((values[0], values[1]))
+}
+
+pub struct Day2;
+
+impl Solution for Day2 {
+    type Input = Vec<i64>;
+
+    fn parse(&self, input: &str) -> Result<Vec<i64>, Box<dyn Error>> {
+        Ok(input.parse::<Program>()?.into_vec())
+    }
+
+    // Both parts run on `Machine`, like the search does, so they agree on
+    // things like reads past the end of memory (0, where
+    // `intcode_interpreter` would fail)
+    fn part1(&self, input: &Vec<i64>) -> Result<String, Box<dyn Error>> {
+        let mut machine = Machine::new(input.clone());
+        machine.poke(1, 12);
+        machine.poke(2, 2);
+        machine.run()?;
+        Ok(machine.peek(0).to_string())
+    }
+
+    fn part2(&self, input: &Vec<i64>) -> Result<String, Box<dyn Error>> {
+        let (noun, verb) = find_inputs(input, TARGET)?;
+        Ok((100 * noun + verb).to_string())
+    }
+}
+
+#[test]
+fn test_simple_programs() {
+    use crate::intcode;
+    use crate::intcode::intcode_interpreter;
+    //assert_eq!(*intcode_interpreter(&mut vec![]).unwrap(), vec![]);
+    assert_eq!(
+        *intcode_interpreter(&mut intcode! { add [0], [0] -> [0]; halt }).unwrap(),
+        vec![2, 0, 0, 0, 99]
+    );
+    assert_eq!(
+        *intcode_interpreter(&mut intcode! { mul [3], [0] -> [3]; halt }).unwrap(),
+        vec![2, 3, 0, 6, 99]
+    );
+    assert_eq!(
+        *intcode_interpreter(&mut intcode! { mul [4], [4] -> [5]; halt; data 0 }).unwrap(),
+        vec![2, 4, 4, 5, 99, 9801]
+    );
+    // The add overwrites the halt with a multiply
+    assert_eq!(
+        *intcode_interpreter(&mut intcode! { add [1], [1] -> [4]; halt; data 5, 6, 0, 99 }).unwrap(),
+        vec![30, 1, 1, 4, 2, 5, 6, 0, 99]
+    );
+}
+
+#[test]
+fn test_find_inputs() {
+    assert_eq!(find_inputs(&[1, 0, 0, 0, 99], 100), Ok((0, 4)));
+    assert!(find_inputs(&[1, 0, 0, 0, 99], 1000).is_err());
+}
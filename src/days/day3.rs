@@ -13,14 +13,17 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use aoc_2019::*;
+//! Day 3: Crossed Wires
+
+use crate::geometry::Point;
+use crate::Solution;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 
 /// Represents a segment of a wire, with direction and length.
 #[derive(Debug, PartialOrd, PartialEq, Eq, Clone, Copy)]
-enum WireSegment {
+pub enum WireSegment {
     Up(usize),
     Down(usize),
     Left(usize),
@@ -57,33 +60,6 @@ fn parse_segment(segment: &str) -> Result<WireSegment, Box<dyn Error>> {
     }
 }
 
-/// Type to represent a point. Probably could have gotten away with just
-/// (isize, isize) since I'm not doing anything more complicated than `add`,
-/// but having that as a function _is_ useful...
-#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Clone, Copy)]
-struct Point {
-    pub x: isize,
-    pub y: isize,
-}
-
-impl Point {
-    /// Returns a new point with specified x and y coordinates.
-    pub fn new(x: isize, y: isize) -> Self {
-        Point { x: x, y: y }
-    }
-
-    /// Adds two points together. Not sure what this is called mathematically,
-    /// but it's just a new point with each point being the sum of the two
-    /// added points. Useful for "moving" a point by any amount with just one
-    /// operation... which is exactly what I use it for.
-    pub fn add(&self, other: &Point) -> Self {
-        Point {
-            x: self.x + other.x,
-            y: self.y + other.y,
-        }
-    }
-}
-
 type Grid = Box<HashMap<Point, HashMap<usize, usize>>>;
 
 /// Takes a set of wire segments and returns the grid
@@ -104,10 +80,7 @@ fn process_wires(wires: Vec<Vec<WireSegment>>) -> Grid {
             for _ in 0..*len {
                 loc = loc.add(&increment);
                 total_len += 1;
-                result
-                    .entry(loc)
-                    .or_insert_with(HashMap::new)
-                    .insert(index, total_len.clone());
+                result.entry(loc).or_insert_with(HashMap::new).insert(index, total_len);
             }
         }
     }
@@ -115,7 +88,7 @@ fn process_wires(wires: Vec<Vec<WireSegment>>) -> Grid {
 }
 
 fn manhattan_distance_origin(point: &Point) -> isize {
-    point.x.abs() + point.y.abs()
+    point.manhattan()
 }
 
 fn closest_manhattan_intersection(grid: &Grid, num_wires: usize) -> Option<(&Point, isize)> {
@@ -163,10 +136,9 @@ fn closest_latency_intersection(grid: &Grid, num_wires: usize) -> Option<(&Point
     result
 }
 
-fn line_to_segments(line: &String) -> Vec<WireSegment> {
-    line.split(',')
-        .map(|seg| parse_segment(seg).unwrap())
-        .collect()
+#[cfg(test)]
+fn line_to_segments(line: &str) -> Vec<WireSegment> {
+    line.split(',').map(|seg| parse_segment(seg).unwrap()).collect()
 }
 
 // Returns a representation of the grid about the center. Not useful for the
@@ -178,7 +150,7 @@ fn visualize_grid(grid: &Grid, radius: isize) -> Vec<String> {
     for line in -radius..radius {
         let mut string: String = String::from("");
         for col in -radius..radius {
-            let key = Point::new(line * -1, col); // lines are top to bottom, so this flips data
+            let key = Point::new(-line, col); // lines are top to bottom, so this flips data
             string.push(if grid.contains_key(&key) {
                 let set = grid.get(&key).unwrap();
                 if set.contains_key(&0) && set.contains_key(&1) {
@@ -199,30 +171,37 @@ fn visualize_grid(grid: &Grid, radius: isize) -> Vec<String> {
     res
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let wires = read_input("input/day3.txt")?;
-    let wires: Vec<Vec<WireSegment>> = wires.iter().map(line_to_segments).collect();
-    // Map<coordinates, Set<wires>>
-    let grid: Grid = process_wires(wires.clone());
-    println!("Grid has {} entries", grid.len());
-    //println!("{:?}", grid);
-    let (intersection, distance) = closest_manhattan_intersection(&grid, wires.len()).unwrap();
-    println!(
-        "Day 3 part 1: intersection at {:?}, dist {}",
-        intersection, distance
-    );
-    let (intersection, distance) = closest_latency_intersection(&grid, wires.len()).unwrap();
-    println!(
-        "Day 3 part 2: intersection at {:?}, dist {}",
-        intersection, distance
-    );
-    Ok(())
+pub struct Day3;
+
+impl Solution for Day3 {
+    type Input = Vec<Vec<WireSegment>>;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, Box<dyn Error>> {
+        input
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.trim().split(',').map(parse_segment).collect())
+            .collect()
+    }
+
+    fn part1(&self, wires: &Self::Input) -> Result<String, Box<dyn Error>> {
+        // Map<coordinates, Set<wires>>
+        let grid: Grid = process_wires(wires.clone());
+        let (_, distance) = closest_manhattan_intersection(&grid, wires.len()).ok_or("The wires never cross")?;
+        Ok(distance.to_string())
+    }
+
+    fn part2(&self, wires: &Self::Input) -> Result<String, Box<dyn Error>> {
+        let grid: Grid = process_wires(wires.clone());
+        let (_, distance) = closest_latency_intersection(&grid, wires.len()).ok_or("The wires never cross")?;
+        Ok(distance.to_string())
+    }
 }
 
 #[test]
 fn test_segment_parsing() {
     assert_eq!(
-        line_to_segments(&"R8,U5,L5,D3".to_string()),
+        line_to_segments("R8,U5,L5,D3"),
         vec![
             WireSegment::Right(8),
             WireSegment::Up(5),
@@ -236,10 +215,7 @@ fn test_segment_parsing() {
 fn test_manhattan_intersection() {
     assert_eq!(
         closest_manhattan_intersection(
-            &process_wires(vec![
-                line_to_segments(&"R8,U5,L5,D3".to_string()),
-                line_to_segments(&"U7,R6,D4,L4".to_string())
-            ]),
+            &process_wires(vec![line_to_segments("R8,U5,L5,D3"), line_to_segments("U7,R6,D4,L4")]),
             2
         ),
         Some((&Point::new(3, 3), 6))
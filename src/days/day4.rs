@@ -13,10 +13,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use aoc_2019::*;
+//! Day 4: Secure Container
+
+use crate::Solution;
 use std::error::Error;
 
-fn has_digit_pair(password: &String) -> bool {
+fn has_digit_pair(password: &str) -> bool {
     let mut prev = ' ';
     for char in password.chars() {
         if char == prev {
@@ -27,7 +29,7 @@ fn has_digit_pair(password: &String) -> bool {
     false
 }
 
-fn has_unique_digit_pair(password: &String) -> bool {
+fn has_unique_digit_pair(password: &str) -> bool {
     //println!("str: `{}`", password);
     let mut prev = ' ';
     let mut iter = " "
@@ -57,7 +59,7 @@ fn has_unique_digit_pair(password: &String) -> bool {
     false
 }
 
-fn digits_always_increase(password: &String) -> bool {
+fn digits_always_increase(password: &str) -> bool {
     let mut prev = 0u8;
     for byte in password.bytes() {
         if byte < prev {
@@ -79,26 +81,38 @@ fn check_password_full(password: usize) -> bool {
     has_unique_digit_pair(&string) && digits_always_increase(&string)
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let input = read_simple_input("input/day4.txt")?;
-    let mut iter = input.split('-').map(|n| n.parse::<usize>());
-    let start = iter.next().unwrap()?;
-    let end = iter.next().unwrap()?;
-    let mut valid_passwords: Vec<usize> = Vec::new();
-    for guess in start..=end {
-        if check_password_simple(guess) {
-            valid_passwords.push(guess);
+pub struct Day4;
+
+impl Solution for Day4 {
+    type Input = (usize, usize);
+
+    fn parse(&self, input: &str) -> Result<(usize, usize), Box<dyn Error>> {
+        let input = input.lines().next().ok_or("No first line in input")?;
+        let mut iter = input.trim().split('-').map(|n| n.parse::<usize>());
+        let start = iter.next().ok_or("No start of the range")??;
+        let end = iter.next().ok_or("No end of the range")??;
+        Ok((start, end))
+    }
+
+    fn part1(&self, &(start, end): &(usize, usize)) -> Result<String, Box<dyn Error>> {
+        let mut valid_passwords: Vec<usize> = Vec::new();
+        for guess in start..=end {
+            if check_password_simple(guess) {
+                valid_passwords.push(guess);
+            }
         }
+        Ok(valid_passwords.len().to_string())
     }
-    println!("Day 4 part 1: {} possible passwords", valid_passwords.len());
-    let mut valid_passwords: Vec<usize> = Vec::new();
-    for guess in start..=end {
-        if check_password_full(guess) {
-            valid_passwords.push(guess);
+
+    fn part2(&self, &(start, end): &(usize, usize)) -> Result<String, Box<dyn Error>> {
+        let mut valid_passwords: Vec<usize> = Vec::new();
+        for guess in start..=end {
+            if check_password_full(guess) {
+                valid_passwords.push(guess);
+            }
         }
+        Ok(valid_passwords.len().to_string())
     }
-    println!("Day 4 part 2: {} possible passwords", valid_passwords.len());
-    Ok(())
 }
 
 #[test]
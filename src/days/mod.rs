@@ -0,0 +1,84 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Every day's solution, and the table the `aoc` runner looks them up in.
+//! Adding a day means a module here and a line in `DAYS`.
+
+use crate::{AnySolution, Part};
+use std::error::Error;
+use std::path::PathBuf;
+
+pub mod day1;
+pub mod day11;
+pub mod day13;
+pub mod day15;
+pub mod day19;
+pub mod day2;
+pub mod day3;
+pub mod day4;
+
+/// A solved day.
+pub struct Day {
+    pub number: u32,
+    pub solution: &'static dyn AnySolution,
+}
+
+/// All the solved days, in order.
+pub static DAYS: &[Day] = &[
+    Day { number: 1, solution: &day1::Day1 },
+    Day { number: 2, solution: &day2::Day2 },
+    Day { number: 3, solution: &day3::Day3 },
+    Day { number: 4, solution: &day4::Day4 },
+    Day { number: 11, solution: &day11::Day11 },
+    Day { number: 13, solution: &day13::Day13 },
+    Day { number: 15, solution: &day15::Day15 },
+    Day { number: 19, solution: &day19::Day19 },
+];
+
+impl Day {
+    pub fn find(number: u32) -> Option<&'static Day> {
+        DAYS.iter().find(|day| day.number == number)
+    }
+
+    /// Where the input is unless told otherwise, like `input/day3.txt`.
+    pub fn input_path(&self) -> PathBuf {
+        PathBuf::from(format!("input/day{}.txt", self.number))
+    }
+
+    /// Parses the input and solves the parts asked for, in order.
+    pub fn solve(&self, input: &str, parts: &[Part]) -> Result<Vec<(Part, String)>, Box<dyn Error>> {
+        let parsed = self.solution.parse_any(input)?;
+        parts
+            .iter()
+            .map(|&part| Ok((part, self.solution.solve_any(parsed.as_ref(), part)?)))
+            .collect()
+    }
+}
+
+#[test]
+fn test_registry() {
+    let numbers: Vec<u32> = DAYS.iter().map(|day| day.number).collect();
+    let mut sorted = numbers.clone();
+    sorted.sort_unstable();
+    sorted.dedup();
+    assert_eq!(numbers, sorted);
+    assert_eq!(Day::find(3).unwrap().input_path(), PathBuf::from("input/day3.txt"));
+    assert!(Day::find(5).is_none());
+    let answers = Day::find(1).unwrap().solve("12\n14\n1969\n", &Part::both()).unwrap();
+    assert_eq!(
+        answers,
+        vec![(Part::One, "658".to_string()), (Part::Two, "970".to_string())]
+    );
+}
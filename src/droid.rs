@@ -0,0 +1,305 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The day 15 repair droid. The droid can only say whether a move hit a wall,
+//! moved, or moved onto the oxygen system, so the maze has to be mapped by
+//! walking all of it.
+
+use crate::geometry::{Direction, Point};
+use crate::intcode::{InterpreterError, Machine};
+use num_enum::TryFromPrimitive;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+const DIRECTIONS: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Hash, TryFromPrimitive)]
+#[repr(i64)]
+/// What the droid reports after trying to move.
+pub enum Status {
+    /// Hit a wall, and didn't move.
+    Wall = 0,
+    Moved = 1,
+    /// Moved, and is now at the oxygen system.
+    Oxygen = 2,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+/// Things that can go wrong driving the droid.
+pub enum DroidError {
+    /// The IntCode program itself failed.
+    Interpreter(InterpreterError),
+    /// The droid didn't report anything after a move.
+    NoStatus,
+    /// The droid reported a status that doesn't exist.
+    InvalidStatus(i64),
+    /// Couldn't go back the way we came. Only possible if the maze changes
+    /// under us.
+    Backtrack(Point),
+}
+
+impl fmt::Display for DroidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let err: String = match self {
+            DroidError::Interpreter(err) => err.to_string(),
+            DroidError::NoStatus => "No status after move".to_string(),
+            DroidError::InvalidStatus(status) => format!("Invalid status: {}", status),
+            DroidError::Backtrack(point) => format!("Couldn't backtrack from {:?}", point),
+        };
+        write!(f, "Droid error: {}", err)
+    }
+}
+
+impl Error for DroidError {}
+
+impl From<InterpreterError> for DroidError {
+    fn from(err: InterpreterError) -> Self {
+        DroidError::Interpreter(err)
+    }
+}
+
+/// Anything that can be driven around the maze.
+pub trait Droid {
+    fn try_move(&mut self, direction: Direction) -> Result<Status, DroidError>;
+}
+
+/// The real droid, driven by its IntCode program.
+pub struct IntcodeDroid {
+    machine: Machine,
+}
+
+impl IntcodeDroid {
+    pub fn new(program: Vec<i64>) -> Self {
+        IntcodeDroid {
+            machine: Machine::new(program),
+        }
+    }
+
+    /// The machine driving the droid, for things like recording a session.
+    pub fn machine_mut(&mut self) -> &mut Machine {
+        &mut self.machine
+    }
+}
+
+impl Droid for IntcodeDroid {
+    fn try_move(&mut self, direction: Direction) -> Result<Status, DroidError> {
+        let command = match direction {
+            Direction::Up => 1,
+            Direction::Down => 2,
+            Direction::Left => 3,
+            Direction::Right => 4,
+        };
+        self.machine.push_input(command);
+        self.machine.run()?;
+        let status = self.machine.pop_output().ok_or(DroidError::NoStatus)?;
+        Status::try_from(status).map_err(|_| DroidError::InvalidStatus(status))
+    }
+}
+
+/// A droid in a maze we already know, for testing. The maze is drawn with `#`
+/// for walls, `.` for open space, `O` for the oxygen system, and `D` for where
+/// the droid starts. Anything else is a wall too.
+pub struct FakeDroid {
+    open: HashSet<Point>,
+    oxygen: Point,
+    position: Point,
+}
+
+impl FakeDroid {
+    pub fn new(maze: &str) -> Self {
+        let mut open = HashSet::new();
+        let (mut oxygen, mut start) = (Point::new(0, 0), Point::new(0, 0));
+        for (row, line) in maze.lines().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                // Rows go down, but up is positive y
+                let point = Point::new(col as isize, -(row as isize));
+                match c {
+                    '.' => (),
+                    'O' => oxygen = point,
+                    'D' => start = point,
+                    _ => continue,
+                }
+                open.insert(point);
+            }
+        }
+        // Move everything so the droid starts at the origin, like the real one
+        let offset = Point::new(-start.x, -start.y);
+        FakeDroid {
+            open: open.iter().map(|p| p.add(&offset)).collect(),
+            oxygen: oxygen.add(&offset),
+            position: Point::new(0, 0),
+        }
+    }
+}
+
+impl Droid for FakeDroid {
+    fn try_move(&mut self, direction: Direction) -> Result<Status, DroidError> {
+        let next = self.position.add(&direction.offset());
+        if !self.open.contains(&next) {
+            return Ok(Status::Wall);
+        }
+        self.position = next;
+        Ok(if next == self.oxygen {
+            Status::Oxygen
+        } else {
+            Status::Moved
+        })
+    }
+}
+
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Hash)]
+/// A known cell in the maze.
+pub enum Cell {
+    Wall,
+    Open,
+    Oxygen,
+}
+
+/// The maze, as far as it's been explored. The droid starts at the origin.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Map {
+    cells: HashMap<Point, Cell>,
+}
+
+impl Map {
+    /// Maps the whole maze, with a depth first search that walks the droid
+    /// back the way it came whenever it hits a dead end.
+    pub fn explore<D: Droid>(droid: &mut D) -> Result<Self, DroidError> {
+        let mut cells = HashMap::new();
+        cells.insert(Point::new(0, 0), Cell::Open);
+        let mut position = Point::new(0, 0);
+        let mut path: Vec<Direction> = Vec::new();
+        loop {
+            let unexplored = DIRECTIONS
+                .iter()
+                .copied()
+                .find(|d| !cells.contains_key(&position.add(&d.offset())));
+            if let Some(direction) = unexplored {
+                let next = position.add(&direction.offset());
+                match droid.try_move(direction)? {
+                    Status::Wall => {
+                        cells.insert(next, Cell::Wall);
+                    }
+                    status => {
+                        let cell = if status == Status::Oxygen { Cell::Oxygen } else { Cell::Open };
+                        cells.insert(next, cell);
+                        path.push(direction);
+                        position = next;
+                    }
+                }
+            } else if let Some(direction) = path.pop() {
+                let back = direction.reverse();
+                if droid.try_move(back)? == Status::Wall {
+                    return Err(DroidError::Backtrack(position));
+                }
+                position = position.add(&back.offset());
+            } else {
+                // Back at the start with nothing left to look at
+                return Ok(Map { cells });
+            }
+        }
+    }
+
+    pub fn cell(&self, point: &Point) -> Option<Cell> {
+        self.cells.get(point).copied()
+    }
+
+    pub fn oxygen(&self) -> Option<Point> {
+        self.cells
+            .iter()
+            .find(|(_, &c)| c == Cell::Oxygen)
+            .map(|(&p, _)| p)
+    }
+
+    /// How many moves it takes to get from a point to every reachable point.
+    pub fn distances(&self, from: Point) -> HashMap<Point, usize> {
+        let mut distances = HashMap::new();
+        let mut queue = VecDeque::new();
+        distances.insert(from, 0);
+        queue.push_back(from);
+        while let Some(point) = queue.pop_front() {
+            let distance = distances[&point];
+            for direction in DIRECTIONS.iter() {
+                let next = point.add(&direction.offset());
+                let open = matches!(self.cell(&next), Some(Cell::Open) | Some(Cell::Oxygen));
+                if open && !distances.contains_key(&next) {
+                    distances.insert(next, distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    /// The fewest moves from the start to the oxygen system.
+    pub fn shortest_path(&self) -> Option<usize> {
+        let oxygen = self.oxygen()?;
+        self.distances(Point::new(0, 0)).get(&oxygen).copied()
+    }
+
+    /// How many minutes it takes oxygen to spread from the oxygen system to
+    /// every open cell, spreading one cell a minute.
+    pub fn fill_time(&self) -> Option<usize> {
+        self.distances(self.oxygen()?).values().max().copied()
+    }
+
+    /// Returns a picture of the map, like `visualize_grid` does for day 3's
+    /// wires. Unexplored cells are blank.
+    pub fn render(&self) -> Vec<String> {
+        let min_x = self.cells.keys().map(|p| p.x).min().unwrap_or(0);
+        let max_x = self.cells.keys().map(|p| p.x).max().unwrap_or(0);
+        let min_y = self.cells.keys().map(|p| p.y).min().unwrap_or(0);
+        let max_y = self.cells.keys().map(|p| p.y).max().unwrap_or(0);
+        let mut res = Vec::new();
+        for y in (min_y..=max_y).rev() {
+            let mut line = String::new();
+            for x in min_x..=max_x {
+                let key = Point::new(x, y);
+                line.push(match self.cell(&key) {
+                    _ if key == Point::new(0, 0) => 'D',
+                    Some(Cell::Wall) => '#',
+                    Some(Cell::Open) => '.',
+                    Some(Cell::Oxygen) => 'O',
+                    None => ' ',
+                });
+            }
+            res.push(line);
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE_MAZE: &str = " ##\n#D.##\n#.#..#\n#.O.#\n ###\n";
+
+#[test]
+fn test_fake_droid() {
+    let mut droid = FakeDroid::new(EXAMPLE_MAZE);
+    assert_eq!(droid.try_move(Direction::Up), Ok(Status::Wall));
+    assert_eq!(droid.try_move(Direction::Down), Ok(Status::Moved));
+    assert_eq!(droid.try_move(Direction::Down), Ok(Status::Moved));
+    assert_eq!(droid.try_move(Direction::Right), Ok(Status::Oxygen));
+}
+
+#[test]
+fn test_explore() {
+    let map = Map::explore(&mut FakeDroid::new(EXAMPLE_MAZE)).unwrap();
+    assert_eq!(map.oxygen(), Some(Point::new(1, -2)));
+    assert_eq!(map.shortest_path(), Some(3));
+    assert_eq!(map.fill_time(), Some(4));
+    assert_eq!(map.render(), vec![" ##   ", "#D.## ", "#.#..#", "#.O.# ", " ###  "]);
+}
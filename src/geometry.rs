@@ -0,0 +1,107 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Points and directions on a grid, shared between days.
+
+/// Type to represent a point. Probably could have gotten away with just
+/// (isize, isize) since I'm not doing anything more complicated than `add`,
+/// but having that as a function _is_ useful...
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Point {
+    pub x: isize,
+    pub y: isize,
+}
+
+impl Point {
+    /// Returns a new point with specified x and y coordinates.
+    pub fn new(x: isize, y: isize) -> Self {
+        Point { x, y }
+    }
+
+    /// Adds two points together. Not sure what this is called mathematically,
+    /// but it's just a new point with each point being the sum of the two
+    /// added points. Useful for "moving" a point by any amount with just one
+    /// operation... which is exactly what I use it for.
+    pub fn add(&self, other: &Point) -> Self {
+        Point {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+
+    /// Distance from the origin, moving only along the grid.
+    pub fn manhattan(&self) -> isize {
+        self.x.abs() + self.y.abs()
+    }
+}
+
+/// A heading on the grid. Up is towards positive y, like in day 3.
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    /// The point one step in this direction from the origin.
+    pub fn offset(self) -> Point {
+        match self {
+            Direction::Up => Point::new(0, 1),
+            Direction::Right => Point::new(1, 0),
+            Direction::Down => Point::new(0, -1),
+            Direction::Left => Point::new(-1, 0),
+        }
+    }
+
+    /// Turns 90 degrees left (counterclockwise).
+    pub fn turn_left(self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    /// Turns 90 degrees right (clockwise).
+    pub fn turn_right(self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    pub fn reverse(self) -> Self {
+        self.turn_left().turn_left()
+    }
+}
+
+#[test]
+fn test_point() {
+    assert_eq!(Point::new(1, 2).add(&Point::new(-3, 4)), Point::new(-2, 6));
+    assert_eq!(Point::new(-3, 4).manhattan(), 7);
+}
+
+#[test]
+fn test_direction() {
+    assert_eq!(Direction::Up.turn_left(), Direction::Left);
+    assert_eq!(Direction::Up.turn_right().turn_right(), Direction::Down);
+    assert_eq!(Direction::Left.reverse(), Direction::Right);
+    assert_eq!(Direction::Down.offset(), Point::new(0, -1));
+}
@@ -0,0 +1,297 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The day 11 hull painting robot. The IntCode program is the robot's brain:
+//! it reads the color of the panel under the robot, then outputs a color to
+//! paint it and which way to turn before moving forward a panel.
+
+use crate::geometry::{Direction, Point};
+use crate::intcode::{InterpreterError, Machine, State};
+use num_enum::TryFromPrimitive;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Hash, TryFromPrimitive)]
+#[repr(i64)]
+/// The color of a hull panel. Everything starts out black.
+pub enum Color {
+    Black = 0,
+    White = 1,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+/// Things that can go wrong running the robot.
+pub enum HullError {
+    /// The IntCode program itself failed.
+    Interpreter(InterpreterError),
+    /// The program asked for a color that doesn't exist.
+    InvalidColor(i64),
+    /// The program asked for a turn that isn't 0 (left) or 1 (right).
+    InvalidTurn(i64),
+    /// The program output a color without a turn to go with it.
+    PartialOutput(i64),
+}
+
+impl fmt::Display for HullError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let err: String = match self {
+            HullError::Interpreter(err) => err.to_string(),
+            HullError::InvalidColor(color) => format!("Invalid color: {}", color),
+            HullError::InvalidTurn(turn) => format!("Invalid turn: {}", turn),
+            HullError::PartialOutput(color) => format!("Color {} without a turn", color),
+        };
+        write!(f, "Hull robot error: {}", err)
+    }
+}
+
+impl Error for HullError {}
+
+impl From<InterpreterError> for HullError {
+    fn from(err: InterpreterError) -> Self {
+        HullError::Interpreter(err)
+    }
+}
+
+/// Where the robot is, and which way it's facing.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Robot {
+    pub position: Point,
+    pub heading: Direction,
+}
+
+impl Robot {
+    /// A robot at the origin, facing up.
+    pub fn new() -> Self {
+        Robot {
+            position: Point::new(0, 0),
+            heading: Direction::Up,
+        }
+    }
+
+    /// Turns left for 0 or right for 1, then moves forward a panel.
+    pub fn turn_and_move(&mut self, turn: i64) -> Result<(), HullError> {
+        self.heading = match turn {
+            0 => self.heading.turn_left(),
+            1 => self.heading.turn_right(),
+            _ => return Err(HullError::InvalidTurn(turn)),
+        };
+        self.position = self.position.add(&self.heading.offset());
+        Ok(())
+    }
+}
+
+impl Default for Robot {
+    fn default() -> Self {
+        Robot::new()
+    }
+}
+
+/// The hull panels. Only panels that aren't black, or that have been painted,
+/// are stored.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Hull {
+    panels: HashMap<Point, Color>,
+    painted: HashSet<Point>,
+}
+
+impl Hull {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn color(&self, point: &Point) -> Color {
+        self.panels.get(point).copied().unwrap_or(Color::Black)
+    }
+
+    pub fn paint(&mut self, point: Point, color: Color) {
+        self.panels.insert(point, color);
+        self.painted.insert(point);
+    }
+
+    /// How many panels have been painted at least once, whatever color they
+    /// ended up.
+    pub fn painted_count(&self) -> usize {
+        self.painted.len()
+    }
+
+    /// The panels as rows of colors, top to bottom, just covering the white
+    /// panels.
+    fn rows(&self) -> Vec<Vec<Color>> {
+        let white: Vec<&Point> = self
+            .panels
+            .iter()
+            .filter(|(_, &c)| c == Color::White)
+            .map(|(p, _)| p)
+            .collect();
+        let (min_x, max_x) = match (white.iter().map(|p| p.x).min(), white.iter().map(|p| p.x).max()) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return Vec::new(),
+        };
+        let min_y = white.iter().map(|p| p.y).min().unwrap_or(0);
+        let max_y = white.iter().map(|p| p.y).max().unwrap_or(0);
+        (min_y..=max_y)
+            .rev()
+            .map(|y| (min_x..=max_x).map(|x| self.color(&Point::new(x, y))).collect())
+            .collect()
+    }
+
+    /// Draws the white panels as `#`, which is enough to read the
+    /// registration identifier.
+    pub fn render(&self) -> String {
+        let mut res = String::new();
+        for row in self.rows() {
+            let line: String = row
+                .iter()
+                .map(|&c| if c == Color::White { '#' } else { ' ' })
+                .collect();
+            res.push_str(line.trim_end());
+            res.push('\n');
+        }
+        res
+    }
+
+    /// Writes the hull as a plain PBM image, with each panel `scale` pixels
+    /// square.
+    pub fn write_pbm<W: Write>(&self, out: &mut W, scale: usize) -> io::Result<()> {
+        // PBM is 1 for black, the opposite of the panel colors
+        self.write_image(out, scale, "P1", None, |c| 1 - c as i64)
+    }
+
+    /// Writes the hull as a plain PGM image, with each panel `scale` pixels
+    /// square.
+    pub fn write_pgm<W: Write>(&self, out: &mut W, scale: usize) -> io::Result<()> {
+        self.write_image(out, scale, "P2", Some(255), |c| c as i64 * 255)
+    }
+
+    fn write_image<W: Write, F: Fn(Color) -> i64>(
+        &self,
+        out: &mut W,
+        scale: usize,
+        magic: &str,
+        maxval: Option<i64>,
+        pixel: F,
+    ) -> io::Result<()> {
+        let rows = self.rows();
+        let width = rows.first().map_or(0, |row| row.len());
+        writeln!(out, "{}", magic)?;
+        writeln!(out, "{} {}", width * scale, rows.len() * scale)?;
+        if let Some(maxval) = maxval {
+            writeln!(out, "{}", maxval)?;
+        }
+        for row in rows {
+            let line: Vec<String> = row
+                .iter()
+                .flat_map(|&c| std::iter::repeat_n(pixel(c).to_string(), scale))
+                .collect();
+            for _ in 0..scale {
+                writeln!(out, "{}", line.join(" "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs the robot's brain, painting the hull.
+pub struct Simulator {
+    machine: Machine,
+    robot: Robot,
+    hull: Hull,
+}
+
+impl Simulator {
+    /// Sets up a robot on a hull where the starting panel is `start`. That
+    /// doesn't count as painting it.
+    pub fn new(program: Vec<i64>, start: Color) -> Self {
+        let mut hull = Hull::new();
+        hull.panels.insert(Point::new(0, 0), start);
+        Simulator {
+            machine: Machine::new(program),
+            robot: Robot::new(),
+            hull,
+        }
+    }
+
+    pub fn robot(&self) -> &Robot {
+        &self.robot
+    }
+
+    pub fn hull(&self) -> &Hull {
+        &self.hull
+    }
+
+    /// Shows the brain what's under the robot, then paints and moves as it
+    /// says. Returns `false` once the brain has halted.
+    pub fn step(&mut self) -> Result<bool, HullError> {
+        self.machine.push_input(self.hull.color(&self.robot.position) as i64);
+        let state = self.machine.run()?;
+        let output = self.machine.take_output();
+        let mut pairs = output.chunks_exact(2);
+        for pair in &mut pairs {
+            let color = Color::try_from(pair[0]).map_err(|_| HullError::InvalidColor(pair[0]))?;
+            self.hull.paint(self.robot.position, color);
+            self.robot.turn_and_move(pair[1])?;
+        }
+        if let Some(&color) = pairs.remainder().first() {
+            return Err(HullError::PartialOutput(color));
+        }
+        Ok(state != State::Halted)
+    }
+
+    /// Keeps painting until the brain halts.
+    pub fn run(&mut self) -> Result<&Hull, HullError> {
+        while self.step()? {}
+        Ok(&self.hull)
+    }
+}
+
+#[cfg(test)]
+fn scripted_brain(moves: &[(i64, i64)]) -> Vec<i64> {
+    // Reads the camera each time, but ignores it
+    let mut program: Vec<i64> = moves
+        .iter()
+        .flat_map(|&(color, turn)| vec![3, 100, 104, color, 104, turn])
+        .collect();
+    program.push(99);
+    program
+}
+
+#[test]
+fn test_example_robot() {
+    let moves = [(1, 0), (0, 0), (1, 0), (1, 0), (0, 1), (1, 0), (1, 0)];
+    let mut sim = Simulator::new(scripted_brain(&moves), Color::Black);
+    assert_eq!(sim.run().map(Hull::painted_count), Ok(6));
+    assert_eq!(sim.robot().position, Point::new(0, 1));
+    assert_eq!(sim.robot().heading, Direction::Left);
+    assert_eq!(sim.hull().render(), "  #\n  #\n##\n");
+}
+
+#[test]
+fn test_images() {
+    let mut hull = Hull::new();
+    hull.paint(Point::new(0, 0), Color::White);
+    hull.paint(Point::new(1, -1), Color::White);
+    let mut pbm = Vec::new();
+    hull.write_pbm(&mut pbm, 1).unwrap();
+    assert_eq!(String::from_utf8(pbm).unwrap(), "P1\n2 2\n0 1\n1 0\n");
+    let mut pgm = Vec::new();
+    hull.write_pgm(&mut pgm, 2).unwrap();
+    assert_eq!(
+        String::from_utf8(pgm).unwrap(),
+        "P2\n4 4\n255\n255 255 0 0\n255 255 0 0\n0 0 255 255\n0 0 255 255\n"
+    );
+}
@@ -0,0 +1,301 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A small IntCode assembler. One statement a line, in the same syntax the
+//! disassembler writes, plus labels and comments:
+//!
+//! ```text
+//! loop: in [x]          ; read a value
+//!       out [x]
+//!       jt 1, loop
+//! x:    data 0
+//! ```
+//!
+//! Operands are `5` for immediate, `[5]` for position, and `[rb+5]` for
+//! relative. A label can be used anywhere a number can, optionally with an
+//! offset like `x+1`.
+
+use super::instruction::ParameterMode;
+use super::table::{encode_word, InstructionTable};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// An assembled program, along with where everything ended up.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Assembly {
+    pub program: Vec<i64>,
+    /// The address of each label.
+    pub labels: BTreeMap<String, usize>,
+    /// The source line (counting from 1) each statement came from, by
+    /// address.
+    pub lines: BTreeMap<usize, usize>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// What went wrong with a line.
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    WrongOperandCount {
+        mnemonic: String,
+        expected: usize,
+        found: usize,
+    },
+    BadOperand(String),
+    UndefinedLabel(String),
+    DuplicateLabel(String),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// An assembler error, and the line (counting from 1) it was on.
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let err: String = match &self.kind {
+            AsmErrorKind::UnknownMnemonic(name) => format!("Unknown mnemonic `{}`", name),
+            AsmErrorKind::WrongOperandCount {
+                mnemonic,
+                expected,
+                found,
+            } => format!("`{}` takes {} operands, not {}", mnemonic, expected, found),
+            AsmErrorKind::BadOperand(text) => format!("Bad operand `{}`", text),
+            AsmErrorKind::UndefinedLabel(name) => format!("Undefined label `{}`", name),
+            AsmErrorKind::DuplicateLabel(name) => format!("Label `{}` defined twice", name),
+        };
+        write!(f, "Assembler error on line {}: {}", self.line, err)
+    }
+}
+
+impl Error for AsmError {}
+
+/// A number, or a label plus an offset.
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Expr {
+    Number(i64),
+    Label(String, i64),
+}
+
+#[derive(Debug)]
+enum Statement {
+    Instruction {
+        opcode: i64,
+        operands: Vec<(ParameterMode, Expr)>,
+    },
+    Data(Vec<Expr>),
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+fn parse_expr(text: &str) -> Option<Expr> {
+    let text = text.trim();
+    if let Ok(number) = text.parse() {
+        return Some(Expr::Number(number));
+    }
+    let (name, offset) = match text.find(['+', '-']) {
+        Some(split) => {
+            let offset = text[split + 1..].trim().parse::<i64>().ok()?;
+            let sign = if text[split..].starts_with('-') { -1 } else { 1 };
+            (text[..split].trim(), sign * offset)
+        }
+        None => (text, 0),
+    };
+    if is_identifier(name) {
+        Some(Expr::Label(name.to_string(), offset))
+    } else {
+        None
+    }
+}
+
+fn parse_operand(text: &str) -> Option<(ParameterMode, Expr)> {
+    let text = text.trim();
+    let inner = match text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        Some(inner) => inner.trim(),
+        None => return Some((ParameterMode::Immediate, parse_expr(text)?)),
+    };
+    // Careful not to take a label like `rbx` for the relative base
+    match inner.strip_prefix("rb").map(str::trim_start) {
+        Some("") => Some((ParameterMode::Relative, Expr::Number(0))),
+        Some(rest) if rest.starts_with('+') => Some((ParameterMode::Relative, parse_expr(&rest[1..])?)),
+        Some(rest) if rest.starts_with('-') => match parse_expr(&rest[1..])? {
+            Expr::Number(n) => Some((ParameterMode::Relative, Expr::Number(-n))),
+            Expr::Label(..) => None,
+        },
+        _ => Some((ParameterMode::Position, parse_expr(inner)?)),
+    }
+}
+
+fn split_operands(text: &str) -> Vec<&str> {
+    if text.trim().is_empty() {
+        Vec::new()
+    } else {
+        text.split(',').collect()
+    }
+}
+
+/// Assembles a program, using the table for mnemonics so custom instructions
+/// can be assembled too.
+pub fn assemble(source: &str, table: &InstructionTable) -> Result<Assembly, AsmError> {
+    let mut assembly = Assembly::default();
+    let mut statements = Vec::new();
+    let mut address = 0;
+    // First pass: work out where everything goes
+    for (index, line) in source.lines().enumerate() {
+        let number = index + 1;
+        let error = |kind| AsmError { line: number, kind };
+        let mut text = line.split(';').next().unwrap_or("").trim();
+        while let Some(colon) = text.find(':') {
+            let label = text[..colon].trim();
+            if !is_identifier(label) {
+                break;
+            }
+            if assembly.labels.insert(label.to_string(), address).is_some() {
+                return Err(error(AsmErrorKind::DuplicateLabel(label.to_string())));
+            }
+            text = text[colon + 1..].trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+        let (mnemonic, rest) = match text.find(char::is_whitespace) {
+            Some(split) => (&text[..split], &text[split..]),
+            None => (text, ""),
+        };
+        let operands = split_operands(rest);
+        let statement = if mnemonic == "data" {
+            let values = operands
+                .iter()
+                .map(|&op| parse_expr(op).ok_or_else(|| error(AsmErrorKind::BadOperand(op.trim().to_string()))))
+                .collect::<Result<Vec<_>, _>>()?;
+            Statement::Data(values)
+        } else {
+            let info = table
+                .by_mnemonic(mnemonic)
+                .ok_or_else(|| error(AsmErrorKind::UnknownMnemonic(mnemonic.to_string())))?;
+            if operands.len() != info.params {
+                return Err(error(AsmErrorKind::WrongOperandCount {
+                    mnemonic: mnemonic.to_string(),
+                    expected: info.params,
+                    found: operands.len(),
+                }));
+            }
+            let operands = operands
+                .iter()
+                .map(|&op| parse_operand(op).ok_or_else(|| error(AsmErrorKind::BadOperand(op.trim().to_string()))))
+                .collect::<Result<Vec<_>, _>>()?;
+            Statement::Instruction {
+                opcode: info.opcode,
+                operands,
+            }
+        };
+        assembly.lines.insert(address, number);
+        address += match &statement {
+            Statement::Instruction { operands, .. } => operands.len() + 1,
+            Statement::Data(values) => values.len(),
+        };
+        statements.push((number, statement));
+    }
+    // Second pass: now that every label is known, write it all out
+    let mut program = Vec::with_capacity(address);
+    for (number, statement) in statements {
+        let resolve = |expr: &Expr| match expr {
+            Expr::Number(n) => Ok(*n),
+            Expr::Label(name, offset) => assembly
+                .labels
+                .get(name)
+                .map(|&addr| addr as i64 + offset)
+                .ok_or(AsmError {
+                    line: number,
+                    kind: AsmErrorKind::UndefinedLabel(name.clone()),
+                }),
+        };
+        match statement {
+            Statement::Instruction { opcode, operands } => {
+                let modes: Vec<ParameterMode> = operands.iter().map(|(mode, _)| *mode).collect();
+                program.push(encode_word(opcode, &modes));
+                for (_, expr) in &operands {
+                    let value = resolve(expr)?;
+                    program.push(value);
+                }
+            }
+            Statement::Data(values) => {
+                for expr in &values {
+                    let value = resolve(expr)?;
+                    program.push(value);
+                }
+            }
+        }
+    }
+    assembly.program = program;
+    Ok(assembly)
+}
+
+#[test]
+fn test_assemble() {
+    let table = InstructionTable::new();
+    let source = "
+        ; echoes input until it gets a zero
+        loop: in [x]
+              out [x]
+              jt [x], loop
+              hlt
+        x:    data 0
+        rbx:  mul [rb-1], [rb], [rbx+1]
+    ";
+    let assembly = assemble(source, &table).unwrap();
+    assert_eq!(
+        assembly.program,
+        vec![3, 8, 4, 8, 1005, 8, 0, 99, 0, 2202, -1, 0, 10]
+    );
+    assert_eq!(assembly.labels["x"], 8);
+    assert_eq!(assembly.lines[&2], 4);
+}
+
+#[test]
+fn test_assemble_errors() {
+    let table = InstructionTable::new();
+    let err = |source| assemble(source, &table).unwrap_err();
+    assert_eq!(err("hlt\nfoo 1").kind, AsmErrorKind::UnknownMnemonic("foo".to_string()));
+    assert_eq!(err("hlt\nfoo 1").line, 2);
+    assert_eq!(
+        err("add 1, 2").kind,
+        AsmErrorKind::WrongOperandCount {
+            mnemonic: "add".to_string(),
+            expected: 3,
+            found: 2
+        }
+    );
+    assert_eq!(err("out [1").kind, AsmErrorKind::BadOperand("[1".to_string()));
+    assert_eq!(err("jt 1, nowhere").kind, AsmErrorKind::UndefinedLabel("nowhere".to_string()));
+    assert_eq!(err("a: hlt\na: hlt").kind, AsmErrorKind::DuplicateLabel("a".to_string()));
+}
+
+#[test]
+fn test_disassembly_round_trip() {
+    use super::disasm::disassemble;
+    let table = InstructionTable::new();
+    let program = vec![1002, 4, 3, 4, 33, 109, -2, 21101, 1, 2, 3, 99, 7];
+    let source: Vec<String> = disassemble(&program, &table).iter().map(|item| item.to_string()).collect();
+    assert_eq!(assemble(&source.join("\n"), &table).unwrap().program, program);
+}
@@ -0,0 +1,517 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Benchmarks: some synthetic workloads, the different ways there are of
+//! running them, and baselines to compare against. `cargo bench` runs them
+//! all (see `benches/intcode.rs`).
+//!
+//! Speed is in steps per second, where the steps are always those of the
+//! original program on a plain machine. That way a variant that gets the
+//! same work done in fewer instructions, like the optimized one, shows up as
+//! faster.
+//!
+//! Baselines are saved as text, one result a line:
+//!
+//! ```text
+//! # intcode benchmarks, steps per second
+//! tight_loop dense 81234567
+//! ```
+
+use super::memory::{DenseMemory, HashMemory, PagedMemory};
+use super::optimize::{optimize, Options};
+use super::table::InstructionTable;
+use super::{intcode_interpreter, InterpreterError, Machine, State};
+use crate::intcode;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::{self, prelude::*, BufReader, BufWriter};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Where a workload leaves its answer.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Answer {
+    /// The last thing it outputs.
+    Output,
+    /// What's in a cell once it halts.
+    Cell(usize),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+/// Why a variant couldn't run a workload.
+pub enum BenchError {
+    Interpreter(InterpreterError),
+    /// The variant can't run the workload at all, see `Variant::supports`.
+    Unsupported { workload: String, variant: String },
+    WrongAnswer {
+        workload: String,
+        variant: String,
+        expected: i64,
+        actual: i64,
+    },
+}
+
+impl fmt::Display for BenchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let err: String = match self {
+            BenchError::Interpreter(err) => format!("{}", err),
+            BenchError::Unsupported { workload, variant } => format!("{} can't run {}", variant, workload),
+            BenchError::WrongAnswer {
+                workload,
+                variant,
+                expected,
+                actual,
+            } => format!(
+                "{} got {} for {}, but the answer is {}",
+                variant, actual, workload, expected
+            ),
+        };
+        write!(f, "Benchmark error: {}", err)
+    }
+}
+
+impl Error for BenchError {}
+
+impl From<InterpreterError> for BenchError {
+    fn from(err: InterpreterError) -> Self {
+        BenchError::Interpreter(err)
+    }
+}
+
+/// A program to benchmark, with its input and the answer it should get.
+#[derive(Debug, Clone)]
+pub struct Workload {
+    pub name: &'static str,
+    pub program: Vec<i64>,
+    pub input: Vec<i64>,
+    pub answer: Answer,
+    pub expected: i64,
+    /// How many steps it takes on a plain machine.
+    pub steps: u64,
+    /// The optimized program, if the optimizer could do anything.
+    pub optimized: Option<Vec<i64>>,
+    /// Whether `intcode_interpreter` can run it, so no I/O, relative mode or
+    /// memory past the end of the program.
+    pub simple: bool,
+}
+
+impl Workload {
+    fn new(name: &'static str, program: Vec<i64>, input: Vec<i64>, answer: Answer, expected: i64) -> Self {
+        let mut machine = Machine::new(program.clone());
+        machine.extend_input(input.iter().copied());
+        assert_eq!(machine.run(), Ok(State::Halted), "{} doesn't halt", name);
+        let optimized = optimize(&program, &Options { separate_stack: true });
+        // Easiest to just try it, since it errors out on anything it can't do
+        let simple = match answer {
+            Answer::Cell(address) => {
+                let mut memory = program.clone();
+                intcode_interpreter(&mut memory).is_ok() && memory[address] == expected
+            }
+            Answer::Output => false,
+        };
+        Workload {
+            name,
+            simple,
+            input,
+            answer,
+            expected,
+            steps: machine.steps(),
+            optimized: optimized.skipped.is_none().then_some(optimized.program),
+            program,
+        }
+    }
+}
+
+/// A loop counting down from `n` and adding up as it goes, through a chain
+/// of jumps.
+fn tight_loop(n: i64) -> Workload {
+    let program = intcode! {
+        top: add [i], [minus_one] -> [i];
+        add [sum], [i] -> [sum];
+        jt [i], hop;
+        halt;
+        hop: jt 1, top;
+        i: data n;
+        sum: data 0;
+        minus_one: data (-1)
+    };
+    Workload::new("tight_loop", program, Vec::new(), Answer::Cell(16), n * (n - 1) / 2)
+}
+
+/// Fills `n` cells past the end of the program through the relative base,
+/// then walks back down adding them up.
+fn heavy_memory(n: i64) -> Workload {
+    let program = intcode! {
+        arb 1000;
+        fill: add [i], 0 -> [rb];
+        arb 1;
+        add [i], 1 -> [i];
+        lt [i], n -> [t];
+        jt [t], fill;
+        sum: arb (-1);
+        add [total], [rb] -> [total];
+        add [i], (-1) -> [i];
+        jt [i], sum;
+        out [total];
+        halt;
+        i: data 0;
+        t: data 0;
+        total: data 0
+    };
+    Workload::new("heavy_memory", program, Vec::new(), Answer::Output, n * (n - 1) / 2)
+}
+
+/// Reads `n` inputs, outputting the running total after each.
+fn heavy_io(n: i64) -> Workload {
+    let program = intcode! {
+        top: in -> [x];
+        add [x], [total] -> [total];
+        out [total];
+        add [count], (-1) -> [count];
+        jt [count], top;
+        halt;
+        x: data 0;
+        total: data 0;
+        count: data n
+    };
+    Workload::new("heavy_io", program, (1..=n).collect(), Answer::Output, n * (n + 1) / 2)
+}
+
+/// Fills a table by adding 1 to the destination of its own store each time
+/// round, so the optimizer can't touch it.
+fn self_modifying(n: i64) -> Workload {
+    let mut program = intcode! {
+        top: add [i], 0 -> [table];
+        add [(top + 3)], 1 -> [(top + 3)];
+        add [i], 1 -> [i];
+        lt [i], n -> [t];
+        jt [t], top;
+        halt;
+        i: data 0;
+        t: data 0;
+        table: data 0
+    };
+    let table = program.len() - 1;
+    program.resize(table + n as usize, 0);
+    Workload::new(
+        "self_modifying",
+        program,
+        Vec::new(),
+        Answer::Cell(table + n as usize - 1),
+        n - 1,
+    )
+}
+
+/// All the workloads, with `scale` setting how much work they do. At 1 they
+/// take a few thousand steps each, `cargo bench` uses 100.
+pub fn workloads(scale: i64) -> Vec<Workload> {
+    vec![
+        tight_loop(scale * 1000),
+        heavy_memory(scale * 200),
+        heavy_io(scale * 200),
+        self_modifying(scale * 200),
+    ]
+}
+
+/// A way of running a workload.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Variant {
+    /// `intcode_interpreter`, for the workloads it can run.
+    Simple,
+    Dense,
+    Paged,
+    Hash,
+    /// The optimized program on a plain machine.
+    Optimized,
+}
+
+impl Variant {
+    pub fn all() -> [Variant; 5] {
+        [
+            Variant::Simple,
+            Variant::Dense,
+            Variant::Paged,
+            Variant::Hash,
+            Variant::Optimized,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::Simple => "simple",
+            Variant::Dense => "dense",
+            Variant::Paged => "paged",
+            Variant::Hash => "hash",
+            Variant::Optimized => "optimized",
+        }
+    }
+
+    pub fn supports(&self, workload: &Workload) -> bool {
+        match self {
+            Variant::Simple => workload.simple,
+            Variant::Optimized => workload.optimized.is_some(),
+            _ => true,
+        }
+    }
+
+    /// Runs a workload once, giving back its answer.
+    pub fn run(&self, workload: &Workload) -> Result<i64, BenchError> {
+        let unsupported = || BenchError::Unsupported {
+            workload: workload.name.to_string(),
+            variant: self.name().to_string(),
+        };
+        if !self.supports(workload) {
+            return Err(unsupported());
+        }
+        let table = InstructionTable::standard;
+        let mut machine = match self {
+            Variant::Simple => {
+                let mut memory = workload.program.clone();
+                intcode_interpreter(&mut memory)?;
+                // No I/O here, so the answer had better be in a cell
+                return match workload.answer {
+                    Answer::Cell(address) => Ok(memory[address]),
+                    Answer::Output => Err(unsupported()),
+                };
+            }
+            Variant::Dense => Machine::with_memory(DenseMemory::from(workload.program.clone()), table()),
+            Variant::Paged => Machine::with_memory(PagedMemory::from(workload.program.clone()), table()),
+            Variant::Hash => Machine::with_memory(HashMemory::from(workload.program.clone()), table()),
+            Variant::Optimized => Machine::new(workload.optimized.clone().unwrap_or_default()),
+        };
+        machine.extend_input(workload.input.iter().copied());
+        machine.run()?;
+        Ok(match workload.answer {
+            Answer::Output => machine.last_output().unwrap_or(0),
+            Answer::Cell(address) => machine.peek(address),
+        })
+    }
+}
+
+/// How fast a variant ran a workload.
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    pub workload: String,
+    pub variant: String,
+    pub steps: u64,
+    pub elapsed: Duration,
+}
+
+impl Measurement {
+    pub fn steps_per_second(&self) -> f64 {
+        self.steps as f64 / self.elapsed.as_secs_f64().max(1e-9)
+    }
+}
+
+/// Runs a workload over and over for at least `time`, checking the answer
+/// each time.
+pub fn measure(workload: &Workload, variant: Variant, time: Duration) -> Result<Measurement, BenchError> {
+    let start = Instant::now();
+    let mut runs = 0;
+    while runs == 0 || start.elapsed() < time {
+        let answer = variant.run(workload)?;
+        if answer != workload.expected {
+            return Err(BenchError::WrongAnswer {
+                workload: workload.name.to_string(),
+                variant: variant.name().to_string(),
+                expected: workload.expected,
+                actual: answer,
+            });
+        }
+        runs += 1;
+    }
+    Ok(Measurement {
+        workload: workload.name.to_string(),
+        variant: variant.name().to_string(),
+        steps: workload.steps * runs,
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Steps per second for each workload and variant.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Baseline {
+    pub results: BTreeMap<(String, String), f64>,
+}
+
+impl Baseline {
+    pub fn from_measurements(measurements: &[Measurement]) -> Self {
+        let results = measurements
+            .iter()
+            .map(|m| ((m.workload.clone(), m.variant.clone()), m.steps_per_second().round()))
+            .collect();
+        Baseline { results }
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut baseline = Baseline::default();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[..] {
+                [workload, variant, speed] => match speed.parse() {
+                    Ok(speed) => {
+                        baseline
+                            .results
+                            .insert((workload.to_string(), variant.to_string()), speed);
+                    }
+                    Err(_) => return Err(bad_line(index)),
+                },
+                _ => return Err(bad_line(index)),
+            }
+        }
+        Ok(baseline)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "# intcode benchmarks, steps per second")?;
+        for ((workload, variant), speed) in &self.results {
+            writeln!(writer, "{} {} {}", workload, variant, speed)?;
+        }
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Baseline::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// A table of the measurements against the baseline, and whether any
+    /// got slower by more than `tolerance` (0.1 being 10%).
+    pub fn compare(&self, measurements: &[Measurement], tolerance: f64) -> (String, bool) {
+        let mut out = String::new();
+        let mut regressed = false;
+        writeln!(
+            out,
+            "{:<16} {:<10} {:>12} {:>12} {:>8}",
+            "workload", "variant", "steps/s", "baseline", "change"
+        )
+        .unwrap();
+        for m in measurements {
+            let speed = m.steps_per_second();
+            let key = (m.workload.clone(), m.variant.clone());
+            let (baseline, change) = match self.results.get(&key) {
+                Some(&baseline) => {
+                    let change = speed / baseline - 1.0;
+                    let note = if change < -tolerance {
+                        regressed = true;
+                        "  REGRESSION"
+                    } else {
+                        ""
+                    };
+                    (format!("{:.0}", baseline), format!("{:+.1}%{}", change * 100.0, note))
+                }
+                None => ("-".to_string(), "new".to_string()),
+            };
+            writeln!(
+                out,
+                "{:<16} {:<10} {:>12.0} {:>12} {:>8}",
+                m.workload, m.variant, speed, baseline, change
+            )
+            .unwrap();
+        }
+        (out, regressed)
+    }
+}
+
+fn bad_line(index: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Can't read line {} of the baseline", index + 1),
+    )
+}
+
+#[test]
+fn test_workloads() {
+    for workload in workloads(1) {
+        for variant in Variant::all() {
+            if variant.supports(&workload) {
+                assert_eq!(
+                    variant.run(&workload),
+                    Ok(workload.expected),
+                    "{} {}",
+                    workload.name,
+                    variant.name()
+                );
+            }
+        }
+    }
+    let supported = |name: &str| {
+        let workload = workloads(1).into_iter().find(|w| w.name == name).unwrap();
+        Variant::all()
+            .iter()
+            .filter(|variant| variant.supports(&workload))
+            .map(|variant| variant.name())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        supported("tight_loop"),
+        vec!["simple", "dense", "paged", "hash", "optimized"]
+    );
+    assert_eq!(supported("heavy_io"), vec!["dense", "paged", "hash", "optimized"]);
+    assert_eq!(supported("self_modifying"), vec!["simple", "dense", "paged", "hash"]);
+
+    let mut heavy_io = workloads(1).into_iter().find(|w| w.name == "heavy_io").unwrap();
+    assert_eq!(
+        Variant::Simple.run(&heavy_io).unwrap_err().to_string(),
+        "Benchmark error: simple can't run heavy_io"
+    );
+    heavy_io.expected += 1;
+    assert_eq!(
+        measure(&heavy_io, Variant::Dense, Duration::from_millis(0)).unwrap_err(),
+        BenchError::WrongAnswer {
+            workload: "heavy_io".to_string(),
+            variant: "dense".to_string(),
+            expected: heavy_io.expected,
+            actual: heavy_io.expected - 1,
+        }
+    );
+}
+
+#[test]
+fn test_baseline() {
+    let measurement = |variant: &str, steps| Measurement {
+        workload: "tight_loop".to_string(),
+        variant: variant.to_string(),
+        steps,
+        elapsed: Duration::from_secs(1),
+    };
+    let baseline = Baseline::from_measurements(&[measurement("dense", 1000), measurement("hash", 500)]);
+    let mut text = Vec::new();
+    baseline.write(&mut text).unwrap();
+    assert_eq!(
+        String::from_utf8(text.clone()).unwrap(),
+        "# intcode benchmarks, steps per second\ntight_loop dense 1000\ntight_loop hash 500\n"
+    );
+    assert_eq!(Baseline::read(&text[..]).unwrap(), baseline);
+    let (table, regressed) = baseline.compare(&[measurement("dense", 1050), measurement("paged", 10)], 0.1);
+    assert!(!regressed);
+    assert!(table.contains("+5.0%"));
+    assert!(table.contains("new"));
+    let (table, regressed) = baseline.compare(&[measurement("hash", 400)], 0.1);
+    assert!(regressed);
+    assert!(table.contains("-20.0%  REGRESSION"));
+}
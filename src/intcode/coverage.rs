@@ -0,0 +1,173 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Code coverage, for checking which parts of a program some test inputs
+//! actually exercised. Turn it on with `Machine::enable_coverage`, run, and
+//! then take the `Coverage` back out to merge with other runs or report on.
+
+use super::disasm::{decode_at, Item};
+use super::table::InstructionTable;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// How often each way out of a branch went. For jumps (opcodes 5 and 6),
+/// taken means it jumped. For comparisons (7 and 8), taken means the
+/// comparison was true.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Branch {
+    pub taken: u64,
+    pub not_taken: u64,
+}
+
+impl Branch {
+    /// How many of the two outcomes have happened at all.
+    pub fn outcomes(&self) -> usize {
+        (self.taken > 0) as usize + (self.not_taken > 0) as usize
+    }
+}
+
+/// What got executed, over one or more runs.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Coverage {
+    /// How many times the instruction at each address ran.
+    executed: BTreeMap<usize, u64>,
+    branches: BTreeMap<usize, Branch>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Coverage::default()
+    }
+
+    /// Notes that the instruction at `address` ran, and which way it went if
+    /// it's a branch.
+    pub fn record(&mut self, address: usize, branch: Option<bool>) {
+        *self.executed.entry(address).or_insert(0) += 1;
+        if let Some(taken) = branch {
+            let counts = self.branches.entry(address).or_default();
+            if taken {
+                counts.taken += 1;
+            } else {
+                counts.not_taken += 1;
+            }
+        }
+    }
+
+    /// How many times the instruction at `address` ran.
+    pub fn count(&self, address: usize) -> u64 {
+        self.executed.get(&address).copied().unwrap_or(0)
+    }
+
+    pub fn branch(&self, address: usize) -> Option<Branch> {
+        self.branches.get(&address).copied()
+    }
+
+    /// Adds in the coverage from another run.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (&address, &count) in &other.executed {
+            *self.executed.entry(address).or_insert(0) += count;
+        }
+        for (&address, branch) in &other.branches {
+            let counts = self.branches.entry(address).or_default();
+            counts.taken += branch.taken;
+            counts.not_taken += branch.not_taken;
+        }
+    }
+
+    /// Disassembles memory, marking each line with how many times it ran, or
+    /// `-` if it never did, and showing which ways branches went. Decoding
+    /// starts over at any address that ran, in case code and data are mixed
+    /// up in a way a plain disassembly would get wrong. Finishes with a
+    /// summary.
+    pub fn report(&self, memory: &[i64], table: &InstructionTable) -> String {
+        let mut out = String::new();
+        let (mut instructions, mut covered) = (0, 0);
+        let (mut outcomes, mut outcomes_covered) = (0, 0);
+        let mut address = 0;
+        while let Some(mut item) = decode_at(memory, address, table) {
+            // Don't let an instruction swallow the start of one that ran
+            let next_ran = self.executed.range(address + 1..address + item.width()).next();
+            if next_ran.is_some() {
+                item = Item::Data { address, value: memory[address] };
+            }
+            let count = self.count(address);
+            let mut branch_note = String::new();
+            if let Item::Instruction { mnemonic, .. } = &item {
+                instructions += 1;
+                covered += (count > 0) as usize;
+                let names = match mnemonic.as_str() {
+                    "jt" | "jf" => Some(("jumped", "fell through")),
+                    "lt" | "eq" => Some(("true", "false")),
+                    _ => None,
+                };
+                if let Some((taken, not_taken)) = names {
+                    let branch = self.branch(address).unwrap_or_default();
+                    outcomes += 2;
+                    outcomes_covered += branch.outcomes();
+                    branch_note = format!("  [{} {}, {} {}]", taken, branch.taken, not_taken, branch.not_taken);
+                }
+            }
+            let count = if count > 0 { count.to_string() } else { "-".to_string() };
+            writeln!(out, "{:>7} {:>5}: {}{}", count, address, item, branch_note).unwrap();
+            address += item.width();
+        }
+        writeln!(
+            out,
+            "{} of {} instructions run, {} of {} branch outcomes seen",
+            covered, instructions, outcomes_covered, outcomes
+        )
+        .unwrap();
+        out
+    }
+}
+
+#[test]
+fn test_merge() {
+    let mut first = Coverage::new();
+    first.record(0, None);
+    first.record(2, Some(true));
+    let mut second = Coverage::new();
+    second.record(2, Some(false));
+    second.record(2, Some(false));
+    first.merge(&second);
+    assert_eq!((first.count(0), first.count(1), first.count(2)), (1, 0, 3));
+    assert_eq!(first.branch(2), Some(Branch { taken: 1, not_taken: 2 }));
+    assert_eq!(first.branch(2).unwrap().outcomes(), 2);
+}
+
+#[test]
+fn test_report() {
+    use super::Machine;
+    // Outputs whether the input is less than 8
+    let program = vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
+    let mut coverage = Coverage::new();
+    for input in [5, 6] {
+        let mut machine = Machine::new(program.clone());
+        machine.enable_coverage();
+        machine.run_with_input(vec![input]).unwrap();
+        coverage.merge(machine.coverage().unwrap());
+    }
+    assert_eq!(
+        coverage.report(&program, &InstructionTable::new()),
+        "      2     0: in [9]
+      2     2: lt [9], [10], [9]  [true 2, false 0]
+      2     6: out [9]
+      2     8: hlt
+      -     9: data -1
+      -    10: data 8
+4 of 4 instructions run, 1 of 2 branch outcomes seen
+"
+    );
+}
@@ -0,0 +1,685 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A Debug Adapter Protocol server, so editors can debug IntCode programs.
+//!
+//! `launch` takes these arguments:
+//!
+//! - `program`: the path of the program. If it ends in `.asm` it's assembled
+//!   first, which means breakpoints can go on source lines and labels.
+//! - `input`: the path of an input script, optional. It's the same format as
+//!   a program, so comma or line separated values with `#` comments, and it's
+//!   all queued up as input before the program starts.
+//! - `stopOnEntry`: whether to stop before the first instruction.
+//!
+//! Function breakpoints take a label or an address (`12` or `0xc`), and
+//! instruction breakpoints take an address. There's just the one thread, with
+//! a single frame. Its scopes are the registers, and memory split up into
+//! windows of 16 words. Memory can be huge, so the windows are indexed
+//! variables, and a client gets at most 1024 of them at a time, paging
+//! through the rest with `start` and `count`. Everything the program outputs is sent as an output
+//! event, one value a line. Running out of input stops it with a `pause`.
+
+use super::asm::{assemble, Assembly};
+use super::debug::{Debugger, Stop};
+use super::disasm::decode_in;
+use super::table::InstructionTable;
+use super::{Machine, Program};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, prelude::*};
+
+/// The size of each memory window in the variables view.
+const WINDOW: usize = 16;
+/// The most windows sent back for one request.
+const MAX_WINDOWS: usize = 1024;
+/// Variables references for windows count up from here. Below it are the
+/// scopes.
+const FIRST_WINDOW: usize = 100;
+const REGISTERS: usize = 1;
+const MEMORY: usize = 2;
+/// There's only the one.
+const THREAD: i64 = 1;
+/// The longest message a client can send, so a bad `Content-Length` can't
+/// ask for all the memory there is.
+const MAX_MESSAGE: usize = 1 << 20;
+
+/// A program that's been launched.
+struct Launched {
+    debugger: Debugger,
+    path: String,
+    assembly: Option<Assembly>,
+}
+
+/// Serves one debugging session.
+#[derive(Default)]
+pub struct DapServer {
+    seq: i64,
+    launched: Option<Launched>,
+    stop_on_entry: bool,
+    /// Breakpoints from each kind of request, since each replaces its own.
+    line_breakpoints: Vec<usize>,
+    function_breakpoints: Vec<usize>,
+    instruction_breakpoints: Vec<usize>,
+    /// Events to send after the current response.
+    events: Vec<Value>,
+    done: bool,
+}
+
+impl DapServer {
+    pub fn new() -> Self {
+        DapServer::default()
+    }
+
+    /// Handles requests until the client disconnects or goes away.
+    pub fn serve<R: BufRead, W: Write>(&mut self, mut reader: R, mut writer: W) -> io::Result<()> {
+        while let Some(request) = read_message(&mut reader)? {
+            let command = request["command"].as_str().unwrap_or("").to_string();
+            let mut response = json!({
+                "type": "response",
+                "request_seq": request["seq"],
+                "command": command,
+            });
+            match self.handle(&command, &request["arguments"]) {
+                Ok(body) => {
+                    response["success"] = json!(true);
+                    response["body"] = body;
+                }
+                Err(message) => {
+                    response["success"] = json!(false);
+                    response["message"] = json!(message);
+                }
+            }
+            self.send(&mut writer, response)?;
+            for event in std::mem::take(&mut self.events) {
+                self.send(&mut writer, event)?;
+            }
+            if self.done {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn send<W: Write>(&mut self, writer: &mut W, mut message: Value) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        let body = message.to_string();
+        write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        writer.flush()
+    }
+
+    fn event(&mut self, event: &str, body: Value) {
+        self.events
+            .push(json!({ "type": "event", "event": event, "body": body }));
+    }
+
+    fn launched(&mut self) -> Result<&mut Launched, String> {
+        self.launched
+            .as_mut()
+            .ok_or_else(|| "Nothing's been launched".to_string())
+    }
+
+    fn handle(&mut self, command: &str, args: &Value) -> Result<Value, String> {
+        match command {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsFunctionBreakpoints": true,
+                "supportsInstructionBreakpoints": true,
+                "supportsTerminateRequest": true,
+            })),
+            "launch" => {
+                self.launch(args)?;
+                // Breakpoints need the program, so configuration waits until now
+                self.event("initialized", json!({}));
+                Ok(Value::Null)
+            }
+            "setBreakpoints" => self.set_line_breakpoints(args),
+            "setFunctionBreakpoints" => {
+                let mut breakpoints = Vec::new();
+                let mut addresses = Vec::new();
+                for breakpoint in args["breakpoints"].as_array().into_iter().flatten() {
+                    let name = breakpoint["name"].as_str().unwrap_or("");
+                    let label = self
+                        .launched()?
+                        .assembly
+                        .as_ref()
+                        .and_then(|a| a.labels.get(name).copied());
+                    breakpoints.push(self.resolved(label.or_else(|| parse_address(name)), &mut addresses));
+                }
+                self.function_breakpoints = addresses;
+                self.update_breakpoints()?;
+                Ok(json!({ "breakpoints": breakpoints }))
+            }
+            "setInstructionBreakpoints" => {
+                let mut breakpoints = Vec::new();
+                let mut addresses = Vec::new();
+                for breakpoint in args["breakpoints"].as_array().into_iter().flatten() {
+                    let address = breakpoint["instructionReference"]
+                        .as_str()
+                        .and_then(parse_address)
+                        .map(|address| address as i64 + breakpoint["offset"].as_i64().unwrap_or(0))
+                        .filter(|&address| address >= 0);
+                    breakpoints.push(self.resolved(address.map(|a| a as usize), &mut addresses));
+                }
+                self.instruction_breakpoints = addresses;
+                self.update_breakpoints()?;
+                Ok(json!({ "breakpoints": breakpoints }))
+            }
+            "configurationDone" => {
+                if self.stop_on_entry {
+                    self.stopped("entry", None);
+                } else {
+                    let stop = self.launched()?.debugger.resume();
+                    self.report(stop);
+                }
+                Ok(Value::Null)
+            }
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD, "name": "intcode" }] })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => {
+                let windows = self.launched()?.debugger.machine().memory().len().div_ceil(WINDOW);
+                Ok(json!({
+                    "scopes": [
+                        { "name": "Registers", "variablesReference": REGISTERS, "expensive": false },
+                        {
+                            "name": "Memory",
+                            "variablesReference": MEMORY,
+                            "indexedVariables": windows,
+                            "expensive": windows > MAX_WINDOWS,
+                        },
+                    ]
+                }))
+            }
+            "variables" => self.variables(args["variablesReference"].as_u64().unwrap_or(0) as usize, args),
+            "continue" => {
+                let stop = self.launched()?.debugger.resume();
+                self.report(stop);
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            // It's all one instruction at a time, with no calls to step into
+            "next" | "stepIn" | "stepOut" => {
+                let stop = self.launched()?.debugger.step();
+                self.report(stop);
+                Ok(Value::Null)
+            }
+            // Nothing runs between requests, so it's already paused
+            "pause" => {
+                self.launched()?;
+                self.stopped("pause", None);
+                Ok(Value::Null)
+            }
+            "terminate" => {
+                self.event("terminated", json!({}));
+                Ok(Value::Null)
+            }
+            "disconnect" => {
+                self.done = true;
+                Ok(Value::Null)
+            }
+            _ => Err(format!("Unsupported request `{}`", command)),
+        }
+    }
+
+    fn launch(&mut self, args: &Value) -> Result<(), String> {
+        let path = args["program"].as_str().ok_or("No program to launch")?;
+        let text = fs::read_to_string(path).map_err(|err| format!("Can't read {}: {}", path, err))?;
+        let (program, assembly) = if path.ends_with(".asm") {
+            let assembly = assemble(&text, &InstructionTable::new()).map_err(|err| err.to_string())?;
+            (assembly.program.clone(), Some(assembly))
+        } else {
+            let program: Program = text.parse().map_err(|err: super::ProgramError| err.to_string())?;
+            (program.into_vec(), None)
+        };
+        let mut machine = Machine::new(program);
+        if let Some(input) = args["input"].as_str() {
+            let input = Program::from_file(input).map_err(|err| err.to_string())?;
+            machine.extend_input(input.into_vec());
+        }
+        self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+        self.launched = Some(Launched {
+            debugger: Debugger::new(machine),
+            path: path.to_string(),
+            assembly,
+        });
+        Ok(())
+    }
+
+    /// Breakpoints on source lines, which only work for assembly. Each goes on
+    /// the first statement at or after its line.
+    fn set_line_breakpoints(&mut self, args: &Value) -> Result<Value, String> {
+        let launched = self.launched()?;
+        let assembly = launched
+            .assembly
+            .as_ref()
+            .filter(|_| args["source"]["path"].as_str() == Some(&launched.path));
+        let mut breakpoints = Vec::new();
+        let mut addresses = Vec::new();
+        for breakpoint in args["breakpoints"].as_array().into_iter().flatten() {
+            let line = breakpoint["line"].as_u64().unwrap_or(0) as usize;
+            let found = assembly.and_then(|assembly| {
+                assembly
+                    .lines
+                    .iter()
+                    .filter(|&(_, &l)| l >= line)
+                    .min_by_key(|&(&address, &l)| (l, address))
+                    .map(|(&address, &l)| (address, l))
+            });
+            match found {
+                Some((address, line)) => {
+                    addresses.push(address);
+                    breakpoints.push(json!({
+                        "verified": true,
+                        "line": line,
+                        "instructionReference": address.to_string(),
+                    }));
+                }
+                None => breakpoints.push(json!({ "verified": false, "message": "No code here" })),
+            }
+        }
+        self.line_breakpoints = addresses;
+        self.update_breakpoints()?;
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    /// The breakpoint in a response for an address, if it makes sense.
+    fn resolved(&self, address: Option<usize>, addresses: &mut Vec<usize>) -> Value {
+        match address {
+            Some(address) => {
+                addresses.push(address);
+                let mut breakpoint = json!({ "verified": true, "instructionReference": address.to_string() });
+                if let Some(line) = self.line_of(address) {
+                    breakpoint["line"] = json!(line);
+                }
+                breakpoint
+            }
+            None => json!({ "verified": false, "message": "Not a label or an address" }),
+        }
+    }
+
+    fn update_breakpoints(&mut self) -> Result<(), String> {
+        let all: Vec<usize> = [
+            &self.line_breakpoints,
+            &self.function_breakpoints,
+            &self.instruction_breakpoints,
+        ]
+        .iter()
+        .flat_map(|addresses| addresses.iter().copied())
+        .collect();
+        let debugger = &mut self.launched()?.debugger;
+        debugger.clear_breakpoints();
+        for address in all {
+            debugger.add_breakpoint(address);
+        }
+        Ok(())
+    }
+
+    /// The assembly source line for an address, if there is one.
+    fn line_of(&self, address: usize) -> Option<usize> {
+        let assembly = self.launched.as_ref()?.assembly.as_ref()?;
+        assembly.lines.get(&address).copied()
+    }
+
+    /// Sends on the output and says why the machine stopped.
+    fn report(&mut self, stop: Stop) {
+        let output = match self.launched.as_mut() {
+            Some(launched) => launched.debugger.machine_mut().take_output(),
+            None => Vec::new(),
+        };
+        for value in output {
+            self.event(
+                "output",
+                json!({ "category": "stdout", "output": format!("{}\n", value) }),
+            );
+        }
+        match stop {
+            Stop::Stepped => self.stopped("step", None),
+            Stop::Breakpoint(_) => self.stopped("breakpoint", None),
+            Stop::WaitingForInput => self.stopped("pause", Some("Waiting for input".to_string())),
+            Stop::Halted => {
+                self.event("exited", json!({ "exitCode": 0 }));
+                self.event("terminated", json!({}));
+            }
+            Stop::Error(err) => self.stopped("exception", Some(err.to_string())),
+        }
+    }
+
+    fn stopped(&mut self, reason: &str, text: Option<String>) {
+        let mut body = json!({ "reason": reason, "threadId": THREAD, "allThreadsStopped": true });
+        if let Some(text) = text {
+            body["text"] = json!(text);
+        }
+        self.event("stopped", body);
+    }
+
+    fn stack_trace(&mut self) -> Result<Value, String> {
+        let launched = self.launched()?;
+        let machine = launched.debugger.machine();
+        let ip = machine.ip();
+        let name = decode_in(machine.memory(), ip, machine.table())
+            .map_or_else(|| format!("{}", ip), |item| format!("{}: {}", ip, item));
+        let mut frame = json!({
+            "id": 1,
+            "name": name,
+            "line": 0,
+            "column": 0,
+            "instructionPointerReference": ip.to_string(),
+        });
+        let path = launched.path.clone();
+        if let Some(line) = self.line_of(ip) {
+            frame["line"] = json!(line);
+            frame["column"] = json!(1);
+            frame["source"] = json!({ "path": path });
+        }
+        Ok(json!({ "stackFrames": [frame], "totalFrames": 1 }))
+    }
+
+    /// The variables for a reference. For the memory scope, `start` and
+    /// `count` in the arguments pick which windows.
+    fn variables(&mut self, reference: usize, args: &Value) -> Result<Value, String> {
+        let launched = self.launched()?;
+        let machine = launched.debugger.machine();
+        let variable = |name: String, value: String| json!({ "name": name, "value": value, "variablesReference": 0 });
+        let variables: Vec<Value> = match reference {
+            REGISTERS => vec![
+                variable("ip".to_string(), machine.ip().to_string()),
+                variable("rb".to_string(), machine.relative_base().to_string()),
+                variable("steps".to_string(), machine.steps().to_string()),
+                variable("input".to_string(), format!("{} queued", machine.input_len())),
+                variable("state".to_string(), format!("{:?}", machine.state())),
+            ],
+            MEMORY => {
+                let windows = machine.memory().len().div_ceil(WINDOW);
+                let first = args["start"].as_u64().map_or(0, |start| start.min(windows as u64) as usize);
+                let count = args["count"]
+                    .as_u64()
+                    .filter(|&count| count > 0)
+                    .map_or(MAX_WINDOWS, |count| count.min(MAX_WINDOWS as u64) as usize);
+                (first..windows.min(first + count))
+                    .map(|window| {
+                        let start = window * WINDOW;
+                        json!({
+                            "name": format!("{}-{}", start, start + WINDOW - 1),
+                            "value": "",
+                            "variablesReference": FIRST_WINDOW + window,
+                            "indexedVariables": WINDOW,
+                        })
+                    })
+                    .collect()
+            }
+            window if window >= FIRST_WINDOW => {
+                let start = (window - FIRST_WINDOW)
+                    .checked_mul(WINDOW)
+                    .filter(|&start| start < machine.memory().len())
+                    .ok_or_else(|| format!("No memory window for reference {}", reference))?;
+                let labels = launched.assembly.as_ref().map(|assembly| &assembly.labels);
+                (start..start + WINDOW)
+                    .map(|address| {
+                        let label = labels
+                            .and_then(|labels| labels.iter().find(|&(_, &a)| a == address))
+                            .map_or(String::new(), |(name, _)| format!(" {}", name));
+                        variable(format!("[{}]{}", address, label), machine.peek(address).to_string())
+                    })
+                    .collect()
+            }
+            _ => return Err(format!("No variables for reference {}", reference)),
+        };
+        Ok(json!({ "variables": variables }))
+    }
+}
+
+/// Reads a message, or `None` at the end of the stream.
+fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.unwrap();
+    if length > MAX_MESSAGE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("A message of {} bytes is too long", length),
+        ));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// An address as decimal, or hex starting with `0x`.
+fn parse_address(text: &str) -> Option<usize> {
+    match text.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Runs some requests through a server, giving back everything it sent.
+#[cfg(test)]
+fn converse(requests: &[Value]) -> Vec<Value> {
+    let mut input = Vec::new();
+    for (seq, request) in requests.iter().enumerate() {
+        let mut request = request.clone();
+        request["type"] = json!("request");
+        request["seq"] = json!(seq + 1);
+        let body = request.to_string();
+        write!(input, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    }
+    let mut output = Vec::new();
+    DapServer::new().serve(&input[..], &mut output).unwrap();
+    let mut reader = &output[..];
+    let mut messages = Vec::new();
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        messages.push(message);
+    }
+    messages
+}
+
+/// Writes a file for a test to launch, giving back its path.
+#[cfg(test)]
+fn scratch_file(name: &str, contents: &str) -> String {
+    let path = std::env::temp_dir().join(format!("aoc_2019_dap_{}_{}", std::process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+/// Just the events and failed responses, in short: the event name and
+/// reason or output, or the failed command.
+#[cfg(test)]
+fn summarize(messages: &[Value]) -> Vec<String> {
+    messages
+        .iter()
+        .filter_map(|message| match message["type"].as_str() {
+            Some("event") => {
+                let body = &message["body"];
+                let detail = body["reason"]
+                    .as_str()
+                    .or_else(|| body["output"].as_str())
+                    .unwrap_or("");
+                Some(format!("{} {}", message["event"].as_str().unwrap(), detail.trim_end()))
+            }
+            _ if message["success"] == json!(false) => Some(format!("failed {}", message["command"])),
+            _ => None,
+        })
+        .map(|summary| summary.trim_end().to_string())
+        .collect()
+}
+
+#[test]
+fn test_assembly_session() {
+    let program = scratch_file(
+        "double.asm",
+        "; Doubles each input until it gets a zero
+loop: in [n]
+      jf [n], end
+      mul [n], 2, [n]
+      out [n]
+      jt 1, loop
+end:  hlt
+n:    data 0
+",
+    );
+    let input = scratch_file("double.txt", "# Some numbers\n5, 21\n0\n");
+    let messages = converse(&[
+        json!({ "command": "initialize", "arguments": { "adapterID": "intcode" } }),
+        json!({ "command": "launch", "arguments": { "program": program, "input": input } }),
+        json!({ "command": "setBreakpoints", "arguments": { "source": { "path": program }, "breakpoints": [{ "line": 5 }, { "line": 1 }] } }),
+        json!({ "command": "setFunctionBreakpoints", "arguments": { "breakpoints": [{ "name": "end" }, { "name": "nowhere" }] } }),
+        json!({ "command": "configurationDone" }),
+        json!({ "command": "stackTrace", "arguments": { "threadId": 1 } }),
+        json!({ "command": "next", "arguments": { "threadId": 1 } }),
+        json!({ "command": "variables", "arguments": { "variablesReference": REGISTERS } }),
+        json!({ "command": "variables", "arguments": { "variablesReference": FIRST_WINDOW } }),
+        json!({ "command": "setBreakpoints", "arguments": { "source": { "path": program }, "breakpoints": [] } }),
+        json!({ "command": "continue", "arguments": { "threadId": 1 } }),
+        json!({ "command": "continue", "arguments": { "threadId": 1 } }),
+        json!({ "command": "evaluate", "arguments": { "expression": "ip" } }),
+        json!({ "command": "disconnect" }),
+    ]);
+    assert_eq!(
+        summarize(&messages),
+        vec![
+            "initialized",
+            "stopped breakpoint",
+            "output 10",
+            "stopped step",
+            "output 42",
+            "stopped breakpoint",
+            "exited",
+            "terminated",
+            "failed \"evaluate\"",
+        ]
+    );
+    let response = |command: &str, index: usize| {
+        messages
+            .iter()
+            .filter(|message| message["type"] == json!("response") && message["command"] == json!(command))
+            .nth(index)
+            .unwrap()["body"]
+            .clone()
+    };
+    // Line 5 is the out, and line 1 is a comment so it's the in on line 2
+    let breakpoints = response("setBreakpoints", 0);
+    assert_eq!(breakpoints["breakpoints"][0]["instructionReference"], json!("9"));
+    assert_eq!(breakpoints["breakpoints"][1]["line"], json!(2));
+    let breakpoints = response("setFunctionBreakpoints", 0);
+    assert_eq!(breakpoints["breakpoints"][0]["instructionReference"], json!("14"));
+    assert_eq!(breakpoints["breakpoints"][1]["verified"], json!(false));
+    let frame = &response("stackTrace", 0)["stackFrames"][0];
+    assert_eq!(frame["name"], json!("9: out [15]"));
+    assert_eq!(frame["line"], json!(5));
+    let registers = response("variables", 0);
+    assert_eq!(registers["variables"][0]["value"], json!("11"));
+    assert_eq!(registers["variables"][4]["value"], json!("Running"));
+    let memory = response("variables", 1);
+    assert_eq!(memory["variables"][15]["name"], json!("[15] n"));
+    assert_eq!(memory["variables"][15]["value"], json!("10"));
+    fs::remove_file(program).unwrap();
+    fs::remove_file(input).unwrap();
+}
+
+#[test]
+fn test_plain_program() {
+    // Outputs its input, then wants more
+    let program = scratch_file("echo.txt", "3,5,4,5,99,0");
+    let messages = converse(&[
+        json!({ "command": "initialize" }),
+        json!({ "command": "launch", "arguments": { "program": program, "stopOnEntry": true } }),
+        json!({ "command": "setInstructionBreakpoints", "arguments": { "breakpoints": [{ "instructionReference": "0x2" }] } }),
+        json!({ "command": "configurationDone" }),
+        json!({ "command": "continue" }),
+        json!({ "command": "variables", "arguments": { "variablesReference": FIRST_WINDOW + 1 } }),
+        json!({ "command": "variables", "arguments": { "variablesReference": usize::MAX } }),
+        json!({ "command": "variables", "arguments": { "variablesReference": MEMORY } }),
+    ]);
+    assert_eq!(
+        summarize(&messages),
+        vec![
+            "initialized",
+            "stopped entry",
+            "stopped pause",
+            "failed \"variables\"",
+            "failed \"variables\""
+        ]
+    );
+    // There's only the one window
+    assert_eq!(
+        messages[messages.len() - 3]["message"],
+        json!(format!("No memory window for reference {}", FIRST_WINDOW + 1))
+    );
+    let last = messages.last().unwrap();
+    assert_eq!(last["body"]["variables"][0]["name"], json!("0-15"));
+    let waiting = &messages[messages.len() - 4];
+    assert_eq!(waiting["body"]["text"], json!("Waiting for input"));
+    fs::remove_file(program).unwrap();
+}
+
+#[test]
+fn test_huge_memory() {
+    // Writes a million words out, then halts
+    let program = scratch_file("huge.txt", "1101,1,1,1000000,99");
+    let messages = converse(&[
+        json!({ "command": "initialize" }),
+        json!({ "command": "launch", "arguments": { "program": program } }),
+        json!({ "command": "configurationDone" }),
+        json!({ "command": "scopes", "arguments": { "frameId": 1 } }),
+        json!({ "command": "variables", "arguments": { "variablesReference": MEMORY } }),
+        json!({ "command": "variables", "arguments": { "variablesReference": MEMORY, "start": 62499, "count": 5 } }),
+        json!({ "command": "variables", "arguments": { "variablesReference": MEMORY, "start": 70000 } }),
+    ]);
+    let bodies: Vec<&Value> = messages
+        .iter()
+        .filter(|message| message["type"] == json!("response"))
+        .map(|message| &message["body"])
+        .collect();
+    let memory = &bodies[bodies.len() - 4]["scopes"][1];
+    assert_eq!(memory["indexedVariables"], json!(62501));
+    assert_eq!(memory["expensive"], json!(true));
+    // Only a page at a time, even when asked for everything
+    assert_eq!(bodies[bodies.len() - 3]["variables"].as_array().unwrap().len(), MAX_WINDOWS);
+    let last_page: Vec<&Value> = bodies[bodies.len() - 2]["variables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|variable| &variable["name"])
+        .collect();
+    assert_eq!(last_page, vec!["999984-999999", "1000000-1000015"]);
+    assert_eq!(bodies[bodies.len() - 1]["variables"], json!([]));
+    fs::remove_file(program).unwrap();
+}
+
+#[test]
+fn test_message_too_long() {
+    let mut reader = &b"Content-Length: 1000000000000\r\n\r\n{}"[..];
+    let err = read_message(&mut reader).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
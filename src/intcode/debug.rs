@@ -0,0 +1,117 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The stepping and pausing that debugger front ends need, on top of a
+//! machine: breakpoints, and a reason for every stop.
+
+use super::{InterpreterError, Machine, State};
+use std::collections::BTreeSet;
+
+/// Why the machine stopped.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Stop {
+    /// Ran the one instruction it was asked to.
+    Stepped,
+    /// About to run the instruction at a breakpoint.
+    Breakpoint(usize),
+    WaitingForInput,
+    Halted,
+    Error(InterpreterError),
+}
+
+/// A machine being debugged.
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    machine: Machine,
+    breakpoints: BTreeSet<usize>,
+}
+
+impl Debugger {
+    pub fn new(machine: Machine) -> Self {
+        Debugger {
+            machine,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut Machine {
+        &mut self.machine
+    }
+
+    /// Returns whether it's a new breakpoint.
+    pub fn add_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.insert(address)
+    }
+
+    /// Returns whether there was a breakpoint there.
+    pub fn remove_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Runs a single instruction.
+    pub fn step(&mut self) -> Stop {
+        match self.machine.step() {
+            Ok(State::Running) => Stop::Stepped,
+            Ok(State::WaitingForInput) => Stop::WaitingForInput,
+            Ok(State::Halted) => Stop::Halted,
+            Err(err) => Stop::Error(err),
+        }
+    }
+
+    /// Runs until the next breakpoint, or until the machine can't go on. A
+    /// breakpoint where it's already stopped doesn't count, so resuming from
+    /// one carries on past it.
+    pub fn resume(&mut self) -> Stop {
+        loop {
+            match self.step() {
+                Stop::Stepped => {}
+                stop => return stop,
+            }
+            let ip = self.machine.ip();
+            if self.breakpoints.contains(&ip) {
+                return Stop::Breakpoint(ip);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_breakpoints() {
+    // Counts down from 3, outputting each number
+    let program = vec![1101, 3, 0, 13, 4, 13, 1001, 13, -1, 13, 1005, 13, 4, 0];
+    let mut debugger = Debugger::new(Machine::new(program));
+    assert!(debugger.add_breakpoint(4));
+    assert!(!debugger.add_breakpoint(4));
+    assert_eq!(debugger.resume(), Stop::Breakpoint(4));
+    assert_eq!(debugger.resume(), Stop::Breakpoint(4));
+    assert_eq!(debugger.machine_mut().take_output(), vec![3]);
+    assert_eq!(debugger.step(), Stop::Stepped);
+    assert_eq!(debugger.machine().ip(), 6);
+    assert!(debugger.remove_breakpoint(4));
+    assert_eq!(debugger.resume(), Stop::Error(InterpreterError::InvalidInstruction { address: 13, code: 0 }));
+    assert_eq!(debugger.machine_mut().take_output(), vec![2, 1]);
+}
@@ -0,0 +1,223 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Compares two memory images, like the end states of two runs, to see
+//! exactly which cells came out different. Changed cells are grouped into
+//! runs of neighbouring addresses, and each is marked as code or data by
+//! tracing what's reachable in the program the runs started from.
+
+use super::disasm::{trace, Item};
+use super::table::InstructionTable;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// What a cell was in the original program.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CellKind {
+    /// Part of the reachable instruction at `instruction`, either the
+    /// instruction word itself or one of its operands.
+    Code {
+        instruction: usize,
+    },
+    Data,
+}
+
+/// A cell that's different.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Change {
+    pub address: usize,
+    pub before: i64,
+    pub after: i64,
+    pub kind: CellKind,
+}
+
+/// A run of changed cells with neighbouring addresses, `start` to `end`
+/// inclusive.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ChangedRange {
+    pub start: usize,
+    pub end: usize,
+    pub changes: Vec<Change>,
+}
+
+/// Everything that's different between two images.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct MemoryDiff {
+    pub ranges: Vec<ChangedRange>,
+}
+
+/// Which instruction each cell of code belongs to, for the instructions
+/// reachable from address 0.
+pub fn code_cells(program: &[i64], table: &InstructionTable) -> BTreeMap<usize, usize> {
+    let mut cells = BTreeMap::new();
+    for item in trace(program, table, &[0]) {
+        if let Item::Instruction { address, .. } = item {
+            for cell in address..address + item.width() {
+                cells.insert(cell, address);
+            }
+        }
+    }
+    cells
+}
+
+impl MemoryDiff {
+    /// Compares `before` with `after`, with `program` being the image both
+    /// started out as, for telling code from data. Images can be different
+    /// lengths, with anything past the end of one counting as 0, same as a
+    /// machine reads it.
+    pub fn new(before: &[i64], after: &[i64], program: &[i64], table: &InstructionTable) -> Self {
+        let code = code_cells(program, table);
+        let mut diff = MemoryDiff::default();
+        for address in 0..before.len().max(after.len()) {
+            let value = |image: &[i64]| image.get(address).copied().unwrap_or(0);
+            let (before, after) = (value(before), value(after));
+            if before == after {
+                continue;
+            }
+            let kind = match code.get(&address) {
+                Some(&instruction) => CellKind::Code { instruction },
+                None => CellKind::Data,
+            };
+            let change = Change {
+                address,
+                before,
+                after,
+                kind,
+            };
+            match diff.ranges.last_mut() {
+                Some(range) if range.end + 1 == address => {
+                    range.end = address;
+                    range.changes.push(change);
+                }
+                _ => diff.ranges.push(ChangedRange {
+                    start: address,
+                    end: address,
+                    changes: vec![change],
+                }),
+            }
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// How many cells are different.
+    pub fn changed(&self) -> usize {
+        self.ranges.iter().map(|range| range.changes.len()).sum()
+    }
+
+    /// The changes side by side, before on the left and after on the right,
+    /// a range at a time.
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        for range in &self.ranges {
+            writeln!(out, "Cells {}-{}:", range.start, range.end).unwrap();
+            for change in &range.changes {
+                let kind = match change.kind {
+                    CellKind::Code { instruction } => format!("code at {}", instruction),
+                    CellKind::Data => "data".to_string(),
+                };
+                writeln!(
+                    out,
+                    "{:>7}  {:<12} {:>12} | {}",
+                    change.address, kind, change.before, change.after
+                )
+                .unwrap();
+            }
+        }
+        writeln!(out, "{} cells changed in {} ranges", self.changed(), self.ranges.len()).unwrap();
+        out
+    }
+
+    pub fn to_json(&self) -> Value {
+        let ranges: Vec<Value> = self
+            .ranges
+            .iter()
+            .map(|range| {
+                let cells: Vec<Value> = range
+                    .changes
+                    .iter()
+                    .map(|change| {
+                        let mut cell = json!({
+                            "address": change.address,
+                            "before": change.before,
+                            "after": change.after,
+                        });
+                        match change.kind {
+                            CellKind::Code { instruction } => {
+                                cell["kind"] = json!("code");
+                                cell["instruction"] = json!(instruction);
+                            }
+                            CellKind::Data => cell["kind"] = json!("data"),
+                        }
+                        cell
+                    })
+                    .collect();
+                json!({ "start": range.start, "end": range.end, "cells": cells })
+            })
+            .collect();
+        json!({ "changed": self.changed(), "ranges": ranges })
+    }
+}
+
+#[test]
+fn test_noun_verb_runs() {
+    use super::Machine;
+    // Day 2's first example, with two different nouns and verbs
+    let program = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+    let run = |noun, verb| {
+        let mut machine = Machine::new(program.clone());
+        machine.poke(1, noun);
+        machine.poke(2, verb);
+        machine.run().unwrap();
+        machine.into_memory()
+    };
+    let diff = MemoryDiff::new(&run(9, 10), &run(10, 11), &program, &InstructionTable::new());
+    assert_eq!(diff.changed(), 4);
+    assert_eq!(
+        diff.report(),
+        "Cells 0-3:
+      0  code at 0            3500 | 4500
+      1  code at 0               9 | 10
+      2  code at 0              10 | 11
+      3  code at 0              70 | 90
+4 cells changed in 1 ranges
+"
+    );
+}
+
+#[test]
+fn test_ranges_and_json() {
+    let table = InstructionTable::new();
+    let program = vec![1101, 1, 2, 7, 99, 0, 0, 0];
+    let before = vec![1101, 1, 2, 7, 99, 0, 0, 3];
+    let after = vec![1101, 1, 2, 7, 99, 5, 0, 4, 0, 6];
+    let diff = MemoryDiff::new(&before, &after, &program, &table);
+    let spans: Vec<(usize, usize)> = diff.ranges.iter().map(|range| (range.start, range.end)).collect();
+    assert_eq!(spans, vec![(5, 5), (7, 7), (9, 9)]);
+    assert_eq!(diff.ranges[2].changes[0].kind, CellKind::Data);
+    assert_eq!(
+        diff.to_json()["ranges"][1],
+        json!({ "start": 7, "end": 7, "cells": [{ "address": 7, "kind": "data", "before": 3, "after": 4 }] })
+    );
+    assert_eq!(
+        code_cells(&program, &table).keys().copied().collect::<Vec<_>>(),
+        vec![0, 1, 2, 3, 4]
+    );
+    assert!(MemoryDiff::new(&before, &before, &program, &table).is_empty());
+}
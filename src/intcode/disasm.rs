@@ -0,0 +1,214 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Turns IntCode memory back into something readable, in the same syntax the
+//! assembler takes: `5` is immediate, `[5]` is position, and `[rb+5]` is
+//! relative.
+
+use super::instruction::ParameterMode;
+use super::memory::Memory;
+use super::table::{InstructionTable, Operand};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// One line of a disassembly.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Item {
+    Instruction {
+        address: usize,
+        mnemonic: String,
+        operands: Vec<Operand>,
+    },
+    /// Anything that doesn't decode as an instruction.
+    Data { address: usize, value: i64 },
+}
+
+impl Item {
+    pub fn address(&self) -> usize {
+        match self {
+            Item::Instruction { address, .. } | Item::Data { address, .. } => *address,
+        }
+    }
+
+    /// How many words of memory the item covers.
+    pub fn width(&self) -> usize {
+        match self {
+            Item::Instruction { operands, .. } => operands.len() + 1,
+            Item::Data { .. } => 1,
+        }
+    }
+}
+
+/// Formats an operand the way the assembler reads it.
+pub fn format_operand(operand: &Operand) -> String {
+    match operand.mode {
+        ParameterMode::Immediate => operand.value.to_string(),
+        ParameterMode::Position => format!("[{}]", operand.value),
+        ParameterMode::Relative if operand.value < 0 => format!("[rb{}]", operand.value),
+        ParameterMode::Relative => format!("[rb+{}]", operand.value),
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Item::Instruction { mnemonic, operands, .. } => {
+                let operands: Vec<String> = operands.iter().map(format_operand).collect();
+                if operands.is_empty() {
+                    write!(f, "{}", mnemonic)
+                } else {
+                    write!(f, "{} {}", mnemonic, operands.join(", "))
+                }
+            }
+            Item::Data { value, .. } => write!(f, "data {}", value),
+        }
+    }
+}
+
+/// Decodes the item at an address, if there's anything there.
+pub fn decode_at(memory: &[i64], address: usize, table: &InstructionTable) -> Option<Item> {
+    decode_with(memory.len(), |addr| memory[addr], address, table)
+}
+
+/// Like `decode_at`, but straight from a machine's memory, without copying
+/// it all out first.
+pub fn decode_in(memory: &dyn Memory, address: usize, table: &InstructionTable) -> Option<Item> {
+    decode_with(memory.len(), |addr| memory.get(addr), address, table)
+}
+
+/// Does the decoding for both, with `get` only ever asked for addresses
+/// below `len`.
+fn decode_with<F: Fn(usize) -> i64>(len: usize, get: F, address: usize, table: &InstructionTable) -> Option<Item> {
+    if address >= len {
+        return None;
+    }
+    let word = get(address);
+    match table.decode(word) {
+        Ok((info, modes)) if address + info.params < len => {
+            let operands = modes
+                .iter()
+                .enumerate()
+                .map(|(i, &mode)| Operand {
+                    address: address + i + 1,
+                    value: get(address + i + 1),
+                    mode,
+                })
+                .collect();
+            Some(Item::Instruction {
+                address,
+                mnemonic: info.mnemonic.clone(),
+                operands,
+            })
+        }
+        _ => Some(Item::Data { address, value: word }),
+    }
+}
+
+/// Disassembles memory from start to end. There's no telling code from data
+/// here, so anything that happens to look like an instruction is shown as one.
+pub fn disassemble(memory: &[i64], table: &InstructionTable) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut address = 0;
+    while let Some(item) = decode_at(memory, address, table) {
+        address += item.width();
+        items.push(item);
+    }
+    items
+}
+
+/// Whether an item is a jump that's always taken, so never falls through.
+pub fn always_jumps(item: &Item) -> bool {
+    match item {
+        Item::Instruction { mnemonic, operands, .. } if operands.len() == 2 => {
+            let condition = operands[0];
+            condition.mode == ParameterMode::Immediate
+                && match mnemonic.as_str() {
+                    "jt" => condition.value != 0,
+                    "jf" => condition.value == 0,
+                    _ => false,
+                }
+        }
+        _ => false,
+    }
+}
+
+/// Finds the instructions that can be reached by running from the entry
+/// points, in address order. Jumps with an immediate target are followed, and
+/// everything but a halt or a jump that's always taken falls through to the
+/// next instruction. Jumps to computed targets can't be followed, so code only
+/// reached that way is missed unless it's also an entry point.
+pub fn trace(memory: &[i64], table: &InstructionTable, entries: &[usize]) -> Vec<Item> {
+    let mut found = BTreeMap::new();
+    let mut pending = entries.to_vec();
+    while let Some(address) = pending.pop() {
+        if found.contains_key(&address) {
+            continue;
+        }
+        let item = match decode_at(memory, address, table) {
+            Some(item @ Item::Instruction { .. }) => item,
+            _ => continue,
+        };
+        if let Item::Instruction { mnemonic, operands, .. } = &item {
+            if mnemonic == "jt" || mnemonic == "jf" {
+                let target = operands[1];
+                if target.mode == ParameterMode::Immediate && target.value >= 0 {
+                    pending.push(target.value as usize);
+                }
+            }
+            if mnemonic != "hlt" && !always_jumps(&item) {
+                pending.push(address + item.width());
+            }
+        }
+        found.insert(address, item);
+    }
+    found.into_values().collect()
+}
+
+/// A disassembly as text, one item a line, with addresses.
+pub fn listing(memory: &[i64], table: &InstructionTable) -> String {
+    disassemble(memory, table)
+        .iter()
+        .map(|item| format!("{:>5}: {}\n", item.address(), item))
+        .collect()
+}
+
+#[test]
+fn test_disassemble() {
+    let table = InstructionTable::new();
+    let memory = vec![1002, 4, 3, 4, 33, 109, -2, 21101, 1, 2, 3, 99, 7];
+    assert_eq!(
+        listing(&memory, &table),
+        "    0: mul [4], 3, [4]\n    4: data 33\n    5: arb -2\n    7: add 1, 2, [rb+3]\n   11: hlt\n   12: data 7\n"
+    );
+    // Not enough room left for the parameters
+    assert_eq!(
+        disassemble(&[1, 2], &table),
+        vec![Item::Data { address: 0, value: 1 }, Item::Data { address: 1, value: 2 }]
+    );
+    // Decoding from memory directly gives the same thing
+    let paged = super::memory::PagedMemory::from(memory.clone());
+    for address in 0..=memory.len() {
+        assert_eq!(decode_in(&paged, address, &table), decode_at(&memory, address, &table));
+    }
+}
+
+#[test]
+fn test_trace() {
+    let table = InstructionTable::new();
+    // Jumps over some data to an output, then halts before more data
+    let memory = vec![1105, 1, 4, 7, 104, 5, 99, 1];
+    let addresses: Vec<usize> = trace(&memory, &table, &[0]).iter().map(Item::address).collect();
+    assert_eq!(addresses, vec![0, 4, 6]);
+}
@@ -0,0 +1,212 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The `intcode!` macro, for writing small programs inline, mostly in tests.
+
+/// Writes an IntCode program inline, as a `Vec<i64>`.
+///
+/// Statements are separated by `;`. Instructions that write somewhere put
+/// it after `->`:
+///
+/// ```
+/// use aoc_2019::intcode;
+/// let program = intcode! {
+///     start: in -> [x];
+///     mul [x], 2 -> [x];
+///     out [x];
+///     jt [x], start;
+///     halt;
+///     x: data 0
+/// };
+/// assert_eq!(program, vec![3, 12, 1002, 12, 2, 12, 4, 12, 1005, 12, 0, 99, 0]);
+/// ```
+///
+/// Each operand has to be a single token, so:
+///
+/// - `5` or `x` (a label) is immediate, and `(-5)` or `(x + 1)` for
+///   anything longer;
+/// - `[5]` or `[x]` is position mode;
+/// - `[rb]`, `[rb + 5]` or `[rb - 5]` is relative mode.
+///
+/// The mnemonics are `add`, `mul`, `in`, `out`, `jt`, `jf`, `lt`, `eq`,
+/// `arb` and `halt` (or `hlt`), plus `data` for raw values. Labels are
+/// identifiers followed by `:`, and can be used before they're defined. Using
+/// an instruction with the wrong operands fails to compile.
+#[macro_export]
+macro_rules! intcode {
+    // Labels
+    (@munch $pos:tt [$($words:tt)*] [$($labels:tt)*] $label:ident : $($rest:tt)*) => {
+        $crate::intcode!(@munch $pos [$($words)*] [$($labels)* ($label, $pos)] $($rest)*)
+    };
+    // Instructions
+    (@munch $pos:tt $words:tt $labels:tt add $a:tt , $b:tt -> $c:tt ; $($rest:tt)*) => {
+        $crate::intcode!(@emit $pos $words $labels 1 [$a $b $c] $($rest)*)
+    };
+    (@munch $pos:tt $words:tt $labels:tt mul $a:tt , $b:tt -> $c:tt ; $($rest:tt)*) => {
+        $crate::intcode!(@emit $pos $words $labels 2 [$a $b $c] $($rest)*)
+    };
+    (@munch $pos:tt $words:tt $labels:tt in -> $a:tt ; $($rest:tt)*) => {
+        $crate::intcode!(@emit $pos $words $labels 3 [$a] $($rest)*)
+    };
+    (@munch $pos:tt $words:tt $labels:tt out $a:tt ; $($rest:tt)*) => {
+        $crate::intcode!(@emit $pos $words $labels 4 [$a] $($rest)*)
+    };
+    (@munch $pos:tt $words:tt $labels:tt jt $a:tt , $b:tt ; $($rest:tt)*) => {
+        $crate::intcode!(@emit $pos $words $labels 5 [$a $b] $($rest)*)
+    };
+    (@munch $pos:tt $words:tt $labels:tt jf $a:tt , $b:tt ; $($rest:tt)*) => {
+        $crate::intcode!(@emit $pos $words $labels 6 [$a $b] $($rest)*)
+    };
+    (@munch $pos:tt $words:tt $labels:tt lt $a:tt , $b:tt -> $c:tt ; $($rest:tt)*) => {
+        $crate::intcode!(@emit $pos $words $labels 7 [$a $b $c] $($rest)*)
+    };
+    (@munch $pos:tt $words:tt $labels:tt eq $a:tt , $b:tt -> $c:tt ; $($rest:tt)*) => {
+        $crate::intcode!(@emit $pos $words $labels 8 [$a $b $c] $($rest)*)
+    };
+    (@munch $pos:tt $words:tt $labels:tt arb $a:tt ; $($rest:tt)*) => {
+        $crate::intcode!(@emit $pos $words $labels 9 [$a] $($rest)*)
+    };
+    (@munch $pos:tt $words:tt $labels:tt halt ; $($rest:tt)*) => {
+        $crate::intcode!(@emit $pos $words $labels 99 [] $($rest)*)
+    };
+    (@munch $pos:tt $words:tt $labels:tt hlt ; $($rest:tt)*) => {
+        $crate::intcode!(@emit $pos $words $labels 99 [] $($rest)*)
+    };
+    (@munch $pos:tt [$($words:tt)*] $labels:tt data $($value:tt),+ ; $($rest:tt)*) => {
+        $crate::intcode!(
+            @munch ($pos $(+ $crate::intcode!(@one $value))+)
+            [$($words)* $(($crate::intcode!(@value $value)))+]
+            $labels $($rest)*
+        )
+    };
+    // Empty statements, like after a trailing `;`
+    (@munch $pos:tt $words:tt $labels:tt ; $($rest:tt)*) => {
+        $crate::intcode!(@munch $pos $words $labels $($rest)*)
+    };
+    // Done, so bind the labels and build the program
+    (@munch $pos:tt [$($words:tt)*] [$(($label:ident, $address:tt))*]) => {{
+        $(
+            #[allow(unused_variables, non_snake_case)]
+            let $label: i64 = $address;
+        )*
+        ::std::vec::Vec::<i64>::from([$($words),*])
+    }};
+    // Anything else is a mistake
+    (@munch $pos:tt $words:tt $labels:tt add $($rest:tt)*) => {
+        compile_error!("`add` takes two operands and a destination, like `add a, b -> c`")
+    };
+    (@munch $pos:tt $words:tt $labels:tt mul $($rest:tt)*) => {
+        compile_error!("`mul` takes two operands and a destination, like `mul a, b -> c`")
+    };
+    (@munch $pos:tt $words:tt $labels:tt in $($rest:tt)*) => {
+        compile_error!("`in` takes just a destination, like `in -> c`")
+    };
+    (@munch $pos:tt $words:tt $labels:tt out $($rest:tt)*) => {
+        compile_error!("`out` takes one operand, like `out a`")
+    };
+    (@munch $pos:tt $words:tt $labels:tt jt $($rest:tt)*) => {
+        compile_error!("`jt` takes a condition and a target, like `jt a, b`")
+    };
+    (@munch $pos:tt $words:tt $labels:tt jf $($rest:tt)*) => {
+        compile_error!("`jf` takes a condition and a target, like `jf a, b`")
+    };
+    (@munch $pos:tt $words:tt $labels:tt lt $($rest:tt)*) => {
+        compile_error!("`lt` takes two operands and a destination, like `lt a, b -> c`")
+    };
+    (@munch $pos:tt $words:tt $labels:tt eq $($rest:tt)*) => {
+        compile_error!("`eq` takes two operands and a destination, like `eq a, b -> c`")
+    };
+    (@munch $pos:tt $words:tt $labels:tt arb $($rest:tt)*) => {
+        compile_error!("`arb` takes one operand, like `arb a`")
+    };
+    (@munch $pos:tt $words:tt $labels:tt halt $($rest:tt)*) => {
+        compile_error!("`halt` doesn't take any operands")
+    };
+    (@munch $pos:tt $words:tt $labels:tt hlt $($rest:tt)*) => {
+        compile_error!("`hlt` doesn't take any operands")
+    };
+    (@munch $pos:tt $words:tt $labels:tt $other:tt $($rest:tt)*) => {
+        compile_error!(concat!("Not an IntCode statement: `", stringify!($other), "`"))
+    };
+    // Writes out an instruction word and its operands
+    (@emit $pos:tt [$($words:tt)*] $labels:tt $opcode:tt [$($operand:tt)*] $($rest:tt)*) => {
+        $crate::intcode!(
+            @munch ($pos + 1 $(+ $crate::intcode!(@one $operand))*)
+            [
+                $($words)*
+                ($crate::intcode!(@word $opcode 100 $($operand)*))
+                $(($crate::intcode!(@value $operand)))*
+            ]
+            $labels $($rest)*
+        )
+    };
+    (@word $opcode:tt $scale:tt) => { $opcode };
+    (@word $opcode:tt $scale:tt $operand:tt $($rest:tt)*) => {
+        $crate::intcode!(@mode $operand) * $scale + $crate::intcode!(@word $opcode ($scale * 10) $($rest)*)
+    };
+    (@mode [rb $($offset:tt)*]) => { 2 };
+    (@mode [$($address:tt)*]) => { 0 };
+    (@mode $value:tt) => { 1 };
+    (@value [rb]) => { 0i64 };
+    (@value [rb + $offset:tt]) => { ($offset) as i64 };
+    (@value [rb - $offset:tt]) => { -(($offset) as i64) };
+    (@value [$address:tt]) => { ($address) as i64 };
+    (@value $value:tt) => { ($value) as i64 };
+    (@one $token:tt) => { 1 };
+    // The way in. The extra `;` saves needing one after the last statement
+    ($($program:tt)*) => {
+        $crate::intcode!(@munch (0) [] [] $($program)* ;)
+    };
+}
+
+#[test]
+fn test_intcode_macro() {
+    assert_eq!(intcode! { mul [4], [4] -> [5]; halt; data 0 }, vec![2, 4, 4, 5, 99, 0]);
+    assert_eq!(intcode! {}, Vec::<i64>::new());
+    assert_eq!(
+        intcode! {
+            arb 5;
+            add [rb], [rb - 1] -> [rb + 2];
+            jf (-3), [end];
+            end: hlt;
+        },
+        vec![109, 5, 22201, 0, -1, 2, 106, -3, 9, 99]
+    );
+    // Labels can be used before they're defined, and with offsets
+    assert_eq!(
+        intcode! {
+            jt 1, skip;
+            data 7, 8;
+            skip: out [(skip - 1)];
+            out (skip + 1);
+        },
+        vec![1105, 1, 5, 7, 8, 4, 4, 104, 6]
+    );
+}
+
+#[test]
+fn test_intcode_macro_runs() {
+    use super::Machine;
+    let program = intcode! {
+        // Counts down from the input to 1
+        in -> [n];
+        top: out [n];
+        add [n], (-1) -> [n];
+        jt [n], top;
+        halt;
+        n: data 0
+    };
+    assert_eq!(Machine::new(program).run_with_input(vec![3]), Ok(vec![3, 2, 1]));
+}
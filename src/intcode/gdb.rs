@@ -0,0 +1,547 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A stub for GDB's remote serial protocol, so a debugger front end can
+//! attach to a machine with `target remote`.
+//!
+//! GDB thinks in bytes, so each word of memory shows up as 8 little endian
+//! bytes: word `n` is at address `n * 8`. There are two 64 bit registers, `ip`
+//! (number 0, the pc) and `rb` (number 1, the relative base). Breakpoints and
+//! single stepping work as usual. Input and output go through `monitor`
+//! commands:
+//!
+//! - `monitor input 1 2 3` queues up some input;
+//! - `monitor output` shows (and takes) what's been output so far.
+//!
+//! A machine waiting for input stops with `SIGTTIN`, and a halted one exits
+//! with status 0. There's no interrupting a running machine, so a `continue`
+//! only comes back once it gets somewhere it'd stop anyway.
+
+use super::debug::{Debugger, Stop};
+use super::InterpreterError;
+use std::convert::{TryFrom, TryInto};
+use std::fmt::Write as _;
+use std::io::{self, prelude::*, BufReader};
+use std::net::TcpListener;
+
+/// Describes the registers, for front ends that ask.
+const TARGET_XML: &str = concat!(
+    r#"<?xml version="1.0"?><!DOCTYPE target SYSTEM "gdb-target.dtd">"#,
+    r#"<target version="1.0"><feature name="org.intcode.core">"#,
+    r#"<reg name="ip" bitsize="64" type="code_ptr" regnum="0"/>"#,
+    r#"<reg name="rb" bitsize="64" type="int64" regnum="1"/>"#,
+    "</feature></target>"
+);
+
+/// Most bytes of memory sent in one reply.
+const MAX_READ: usize = 0x800;
+/// How many words past the end of memory a client can write, so one packet
+/// can't make the machine grow its memory without limit.
+const MAX_WRITE_GROWTH: usize = 0x10000;
+
+/// What to do after handling a packet.
+enum Reply {
+    Send(String),
+    /// Send the reply, then hang up.
+    SendAndClose(String),
+    Close,
+}
+
+/// Serves one machine to a GDB client.
+#[derive(Debug, Clone)]
+pub struct GdbStub {
+    debugger: Debugger,
+    /// The reply to the last thing that stopped the machine.
+    last_stop: String,
+    no_ack: bool,
+}
+
+impl GdbStub {
+    pub fn new(debugger: Debugger) -> Self {
+        GdbStub {
+            debugger,
+            last_stop: "S05".to_string(),
+            no_ack: false,
+        }
+    }
+
+    pub fn debugger(&self) -> &Debugger {
+        &self.debugger
+    }
+
+    pub fn into_debugger(self) -> Debugger {
+        self.debugger
+    }
+
+    /// Waits for a client on `listener`, then serves it until it detaches
+    /// or hangs up.
+    pub fn accept(&mut self, listener: &TcpListener) -> io::Result<()> {
+        let (stream, _) = listener.accept()?;
+        stream.set_nodelay(true)?;
+        self.serve(stream)
+    }
+
+    /// Serves a client over some already open connection, until it detaches
+    /// or hangs up.
+    pub fn serve<S: Read + Write>(&mut self, stream: S) -> io::Result<()> {
+        let mut stream = BufReader::new(stream);
+        self.no_ack = false;
+        while let Some(packet) = self.read_packet(&mut stream)? {
+            match self.handle(&packet) {
+                Reply::Send(reply) => self.write_packet(&mut stream, &reply)?,
+                Reply::SendAndClose(reply) => {
+                    self.write_packet(&mut stream, &reply)?;
+                    break;
+                }
+                Reply::Close => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the next packet, acknowledging it. Returns `None` if the client
+    /// hung up.
+    fn read_packet<S: Read + Write>(&self, stream: &mut BufReader<S>) -> io::Result<Option<String>> {
+        loop {
+            // Skip acks, and interrupts, which there's nothing to do about
+            match read_byte(stream)? {
+                Some(b'$') => {}
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+            let mut body = Vec::new();
+            if stream.read_until(b'#', &mut body)? == 0 || body.pop() != Some(b'#') {
+                return Ok(None);
+            }
+            let mut checksum = [0; 2];
+            stream.read_exact(&mut checksum)?;
+            let valid = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|checksum| u8::from_str_radix(checksum, 16).ok())
+                == Some(checksum_of(&body));
+            if !self.no_ack {
+                stream.get_mut().write_all(if valid { b"+" } else { b"-" })?;
+                stream.get_mut().flush()?;
+            }
+            if valid {
+                return Ok(Some(String::from_utf8_lossy(&body).into_owned()));
+            }
+        }
+    }
+
+    /// Sends a packet, sending it again until the client acknowledges it.
+    fn write_packet<S: Read + Write>(&self, stream: &mut BufReader<S>, body: &str) -> io::Result<()> {
+        let packet = format!("${}#{:02x}", body, checksum_of(body.as_bytes()));
+        loop {
+            stream.get_mut().write_all(packet.as_bytes())?;
+            stream.get_mut().flush()?;
+            if self.no_ack || read_byte(stream)? != Some(b'-') {
+                return Ok(());
+            }
+        }
+    }
+
+    fn handle(&mut self, packet: &str) -> Reply {
+        let (command, args) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+        let reply = match command {
+            "?" => self.last_stop.clone(),
+            "g" => {
+                let machine = self.debugger.machine();
+                to_hex(&register_bytes(machine.ip() as i64)) + &to_hex(&register_bytes(machine.relative_base()))
+            }
+            "G" => match from_hex(args).as_deref() {
+                // The ip's the only one that can fail, so it goes first
+                Some(bytes) if bytes.len() == 16 && self.set_register(0, &bytes[..8]) => {
+                    self.set_register(1, &bytes[8..]);
+                    "OK".to_string()
+                }
+                _ => "E01".to_string(),
+            },
+            "p" => match usize::from_str_radix(args, 16) {
+                Ok(0) => to_hex(&register_bytes(self.debugger.machine().ip() as i64)),
+                Ok(1) => to_hex(&register_bytes(self.debugger.machine().relative_base())),
+                _ => "E01".to_string(),
+            },
+            "P" => {
+                let parsed = args.split_once('=').and_then(|(register, value)| {
+                    Some((usize::from_str_radix(register, 16).ok()?, from_hex(value)?))
+                });
+                match parsed {
+                    Some((register, value))
+                        if register < 2 && value.len() == 8 && self.set_register(register, &value) =>
+                    {
+                        "OK".to_string()
+                    }
+                    _ => "E01".to_string(),
+                }
+            }
+            "m" => match parse_range(args) {
+                Some((address, length)) => to_hex(&self.read_memory(address, length.min(MAX_READ))),
+                None => "E01".to_string(),
+            },
+            "M" => {
+                let parsed = args
+                    .split_once(':')
+                    .and_then(|(range, data)| Some((parse_range(range)?, from_hex(data)?)));
+                match parsed {
+                    Some(((address, length), data)) if data.len() == length && self.write_memory(address, &data) => {
+                        "OK".to_string()
+                    }
+                    _ => "E01".to_string(),
+                }
+            }
+            "Z" | "z" => self.breakpoint(command == "Z", args),
+            "s" | "c" => {
+                if !args.is_empty() {
+                    match usize::from_str_radix(args, 16) {
+                        Ok(address) if address.is_multiple_of(8) => self.debugger.machine_mut().set_ip(address / 8),
+                        _ => return Reply::Send("E01".to_string()),
+                    }
+                }
+                let stop = if command == "s" {
+                    self.debugger.step()
+                } else {
+                    self.debugger.resume()
+                };
+                self.last_stop = stop_reply(&stop);
+                self.last_stop.clone()
+            }
+            "q" | "Q" | "v" => self.query(packet),
+            "H" => "OK".to_string(),
+            "D" => return Reply::SendAndClose("OK".to_string()),
+            "k" => return Reply::Close,
+            // Empty means not supported
+            _ => String::new(),
+        };
+        Reply::Send(reply)
+    }
+
+    /// The general queries and settings.
+    fn query(&mut self, packet: &str) -> String {
+        let (name, args) = packet.split_once([':', ',']).unwrap_or((packet, ""));
+        match name {
+            "qSupported" => format!(
+                "PacketSize={:x};qXfer:features:read+;swbreak+;QStartNoAckMode+",
+                MAX_READ * 2 + 4
+            ),
+            "QStartNoAckMode" => {
+                // Takes effect after this reply is acknowledged
+                self.no_ack = true;
+                "OK".to_string()
+            }
+            "qXfer" => match args.strip_prefix("features:read:target.xml:").and_then(parse_range) {
+                Some((offset, length)) => {
+                    let rest = TARGET_XML.get(offset.min(TARGET_XML.len())..).unwrap_or("");
+                    if rest.len() > length {
+                        format!("m{}", &rest[..length])
+                    } else {
+                        format!("l{}", rest)
+                    }
+                }
+                None => "E01".to_string(),
+            },
+            "qAttached" => "1".to_string(),
+            "qC" => "QC1".to_string(),
+            "qfThreadInfo" => "m1".to_string(),
+            "qsThreadInfo" => "l".to_string(),
+            "qRcmd" => match from_hex(args).and_then(|command| String::from_utf8(command).ok()) {
+                Some(command) => self.monitor(&command),
+                None => "E01".to_string(),
+            },
+            _ => String::new(),
+        }
+    }
+
+    /// Runs a `monitor` command.
+    fn monitor(&mut self, command: &str) -> String {
+        let mut words = command.split_whitespace();
+        let text = match words.next() {
+            Some("input") => {
+                let values: Result<Vec<i64>, _> = words.map(str::parse).collect();
+                match values {
+                    Ok(values) => {
+                        self.debugger.machine_mut().extend_input(values);
+                        return "OK".to_string();
+                    }
+                    Err(_) => "Input has to be numbers\n".to_string(),
+                }
+            }
+            Some("output") => {
+                let output = self.debugger.machine_mut().take_output();
+                let output: Vec<String> = output.iter().map(i64::to_string).collect();
+                format!("{}\n", output.join(" "))
+            }
+            _ => "Commands are `input VALUES...` and `output`\n".to_string(),
+        };
+        to_hex(text.as_bytes())
+    }
+
+    /// Handles `Z` and `z`. Software and hardware breakpoints are the same
+    /// thing here, and there aren't any watchpoints.
+    fn breakpoint(&mut self, insert: bool, args: &str) -> String {
+        let mut fields = args.split(',');
+        let kind = fields.next();
+        let address = fields.next().and_then(|address| usize::from_str_radix(address, 16).ok());
+        match (kind, address) {
+            (Some("0"), Some(address)) | (Some("1"), Some(address)) if address.is_multiple_of(8) => {
+                if insert {
+                    self.debugger.add_breakpoint(address / 8);
+                } else {
+                    self.debugger.remove_breakpoint(address / 8);
+                }
+                "OK".to_string()
+            }
+            (Some("0"), _) | (Some("1"), _) => "E01".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// Sets a register, or gives back false if it can't hold the value, like
+    /// a negative ip.
+    fn set_register(&mut self, register: usize, bytes: &[u8]) -> bool {
+        let value = i64::from_le_bytes(bytes.try_into().unwrap());
+        let machine = self.debugger.machine_mut();
+        match register {
+            0 => match usize::try_from(value) {
+                Ok(ip) => machine.set_ip(ip),
+                Err(_) => return false,
+            },
+            _ => machine.set_relative_base(value),
+        }
+        true
+    }
+
+    fn read_memory(&self, address: usize, length: usize) -> Vec<u8> {
+        let machine = self.debugger.machine();
+        (address..address.saturating_add(length))
+            .map(|byte| machine.peek(byte / 8).to_le_bytes()[byte % 8])
+            .collect()
+    }
+
+    /// Writes some bytes, or gives back false without writing anything if
+    /// they'd go too far past the end of memory.
+    fn write_memory(&mut self, address: usize, data: &[u8]) -> bool {
+        let machine = self.debugger.machine_mut();
+        let limit = machine.memory().len().saturating_add(MAX_WRITE_GROWTH);
+        match address.checked_add(data.len()) {
+            Some(end) if end.div_ceil(8) <= limit => {}
+            _ => return false,
+        }
+        for (byte, &value) in (address..).zip(data) {
+            let mut word = machine.peek(byte / 8).to_le_bytes();
+            word[byte % 8] = value;
+            machine.poke(byte / 8, i64::from_le_bytes(word));
+        }
+        true
+    }
+}
+
+/// The stop reply packet for why the machine stopped.
+fn stop_reply(stop: &Stop) -> String {
+    let signal = match stop {
+        Stop::Stepped | Stop::Breakpoint(_) => 5, // SIGTRAP
+        Stop::WaitingForInput => 21,              // SIGTTIN
+        Stop::Halted => return "W00".to_string(),
+        Stop::Error(InterpreterError::StepLimitExceeded { .. }) => 24, // SIGXCPU
+        Stop::Error(InterpreterError::EndOfMemory { .. }) | Stop::Error(InterpreterError::NegativeAddress { .. }) => {
+            11 // SIGSEGV
+        }
+        Stop::Error(_) => 4, // SIGILL
+    };
+    format!("S{:02x}", signal)
+}
+
+fn read_byte<R: BufRead>(reader: &mut R) -> io::Result<Option<u8>> {
+    let byte = reader.fill_buf()?.first().copied();
+    if byte.is_some() {
+        reader.consume(1);
+    }
+    Ok(byte)
+}
+
+fn checksum_of(body: &[u8]) -> u8 {
+    body.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn register_bytes(value: i64) -> [u8; 8] {
+    value.to_le_bytes()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(hex, "{:02x}", byte).unwrap();
+    }
+    hex
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Parses `address,length`, both in hex.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let (address, length) = range.split_once(',')?;
+    Some((usize::from_str_radix(address, 16).ok()?, usize::from_str_radix(length, 16).ok()?))
+}
+
+/// Plays the client's side of a conversation, sending each packet and
+/// acknowledging each reply, and gives back the replies.
+#[cfg(test)]
+fn converse<S: Read + Write>(mut stream: S, packets: &[&str]) -> Vec<String> {
+    let mut replies = Vec::new();
+    for packet in packets {
+        write!(stream, "${}#{:02x}", packet, checksum_of(packet.as_bytes())).unwrap();
+        let mut ack = [0];
+        stream.read_exact(&mut ack).unwrap();
+        assert_eq!(&ack, b"+");
+        if *packet == "k" {
+            break;
+        }
+        let mut reply = Vec::new();
+        let mut byte = [0];
+        loop {
+            stream.read_exact(&mut byte).unwrap();
+            reply.push(byte[0]);
+            if byte[0] == b'#' {
+                break;
+            }
+        }
+        let mut checksum = [0; 2];
+        stream.read_exact(&mut checksum).unwrap();
+        let body = &reply[1..reply.len() - 1];
+        assert_eq!(reply[0], b'$');
+        assert_eq!(std::str::from_utf8(&checksum).unwrap(), format!("{:02x}", checksum_of(body)));
+        stream.write_all(b"+").unwrap();
+        replies.push(String::from_utf8(body.to_vec()).unwrap());
+    }
+    replies
+}
+
+#[test]
+fn test_session() {
+    use super::{Machine, State};
+    use std::net::TcpStream;
+    use std::thread;
+    // Doubles each input until it gets a zero
+    let program = vec![3, 15, 1006, 15, 14, 1002, 15, 2, 15, 4, 15, 1105, 1, 0, 99, 0];
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let mut stub = GdbStub::new(Debugger::new(Machine::new(program)));
+        stub.accept(&listener).unwrap();
+        stub
+    });
+    let replies = converse(
+        {
+            let stream = TcpStream::connect(address).unwrap();
+            stream.set_nodelay(true).unwrap();
+            stream
+        },
+        &[
+            "qSupported:swbreak+",
+            "qXfer:features:read:target.xml:0,15",
+            "?",
+            "g",
+            "m0,10",
+            "c",
+            "qRcmd,696e70757420352034", // input 5 4
+            "Z0,48,1",
+            "c",
+            "p1",
+            "s",
+            "p0",
+            "M38,8:0300000000000000",
+            "z0,48,1",
+            "c",
+            "qRcmd,6f7574707574", // output
+            "qRcmd,696e7075742030", // input 0
+            "c",
+            "?",
+            "D",
+        ],
+    );
+    let stub = server.join().unwrap();
+    let output = to_hex(b"10 12\n");
+    assert_eq!(
+        replies,
+        vec![
+            "PacketSize=1004;qXfer:features:read+;swbreak+;QStartNoAckMode+",
+            "m<?xml version=\"1.0\"?>",
+            "S05",
+            "00000000000000000000000000000000",
+            // The first two words, 3 and 15
+            "03000000000000000f00000000000000",
+            "S15",
+            "OK",
+            "OK",
+            // At the out, having doubled the 5
+            "S05",
+            "0000000000000000",
+            "S05",
+            "0b00000000000000",
+            // Now it triples instead
+            "OK",
+            "OK",
+            "S15",
+            &output,
+            "OK",
+            "W00",
+            "W00",
+            "OK",
+        ]
+    );
+    assert_eq!(stub.debugger().machine().state(), State::Halted);
+}
+
+#[test]
+fn test_negative_ip() {
+    use super::Machine;
+    let mut stub = GdbStub::new(Debugger::new(Machine::new(vec![99])));
+    let reply = |stub: &mut GdbStub, packet: &str| match stub.handle(packet) {
+        Reply::Send(reply) => reply,
+        _ => panic!("{} should get a reply", packet),
+    };
+    let minus_one = to_hex(&register_bytes(-1));
+    assert_eq!(reply(&mut stub, &format!("P0={}", minus_one)), "E01");
+    assert_eq!(reply(&mut stub, &format!("G{}{}", minus_one, to_hex(&register_bytes(5)))), "E01");
+    // Neither register changed
+    assert_eq!(reply(&mut stub, "g"), "00000000000000000000000000000000");
+    assert_eq!(reply(&mut stub, &format!("P1={}", minus_one)), "OK");
+    assert_eq!(reply(&mut stub, "p1"), minus_one);
+}
+
+#[test]
+fn test_write_bounds() {
+    use super::Machine;
+    let mut stub = GdbStub::new(Debugger::new(Machine::new(vec![99])));
+    let reply = |stub: &mut GdbStub, packet: &str| match stub.handle(packet) {
+        Reply::Send(reply) => reply,
+        _ => panic!("{} should get a reply", packet),
+    };
+    // Some way past the end is fine, but not miles past it, or right at the
+    // end of the address space
+    assert_eq!(reply(&mut stub, "M400,1:2a"), "OK");
+    assert_eq!(reply(&mut stub, "m400,1"), "2a");
+    assert_eq!(reply(&mut stub, "M40000000,1:2a"), "E01");
+    assert_eq!(reply(&mut stub, "Mffffffffffffffff,2:2a2a"), "E01");
+    assert_eq!(stub.debugger().machine().memory().len(), 0x81);
+}
@@ -0,0 +1,354 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Images: a program with some metadata about it, so it can be passed around
+//! without having to explain where it came from or how to run it. The
+//! metadata goes in `#!` lines before the program:
+//!
+//! ```text
+//! #!intcode
+//! #!name: day 2
+//! #!entry: 0
+//! #!io: numeric
+//! #!patch: 1=12, 2=2
+//! #!hash: fnv1a64:2b1f0ac37c5fe6b8
+//! 1,0,0,3,1,1,2,3,...
+//! ```
+//!
+//! Only `#!intcode` is required. Since they're comments, anything that only
+//! knows plain programs still loads images, just without the patches.
+//! `Program`'s own loader applies the patches too, but refuses images with an
+//! entry point other than 0, since a plain program has to start there. The
+//! hash is `Program::hash` of the program as written, before patching, and
+//! loading fails if it doesn't match.
+
+use super::program::parse_words;
+use super::{Machine, Program, ProgramError};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// How far past the end of the program a patch can go. Images come from
+/// other people, and one with a patch at some huge address shouldn't be able
+/// to ask for all the memory there is.
+pub const MAX_PATCH_GROWTH: usize = 1 << 16;
+
+/// How a program expects to talk to the outside world.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum IoMode {
+    /// Plain numbers.
+    #[default]
+    Numeric,
+    /// Characters as their ASCII codes, like day 17 onwards.
+    Ascii,
+}
+
+impl fmt::Display for IoMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IoMode::Numeric => write!(f, "numeric"),
+            IoMode::Ascii => write!(f, "ascii"),
+        }
+    }
+}
+
+impl FromStr for IoMode {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "numeric" => Ok(IoMode::Numeric),
+            "ascii" => Ok(IoMode::Ascii),
+            _ => Err(format!("Unknown I/O mode `{}`", text)),
+        }
+    }
+}
+
+/// A value to put in a cell before running, like day 13's free play flag
+/// (`0=2`).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Patch {
+    pub address: usize,
+    pub value: i64,
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.address, self.value)
+    }
+}
+
+impl FromStr for Patch {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let bad = || format!("`{}` isn't a patch like `1=12`", text);
+        let mut parts = text.splitn(2, '=');
+        let address = parts.next().unwrap_or("").trim().parse().map_err(|_| bad())?;
+        let value = parts.next().ok_or_else(bad)?.trim().parse().map_err(|_| bad())?;
+        Ok(Patch { address, value })
+    }
+}
+
+/// Everything an image says about its program.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Metadata {
+    pub name: Option<String>,
+    /// Where execution should start.
+    pub entry: usize,
+    pub io: IoMode,
+    pub patches: Vec<Patch>,
+    /// The hash the program should have, if there was one.
+    pub hash: Option<u64>,
+}
+
+/// A program and its metadata. The program is kept as it was written, with
+/// the patches applied by `patched`.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Image {
+    pub metadata: Metadata,
+    pub program: Program,
+}
+
+impl Image {
+    /// An image of `program` with no metadata yet, besides its hash.
+    pub fn new(program: Program) -> Self {
+        let metadata = Metadata {
+            hash: Some(program.hash()),
+            ..Metadata::default()
+        };
+        Image { metadata, program }
+    }
+
+    /// Whether some text is an image rather than a plain program, which is
+    /// whether there's a `#!intcode` somewhere in the comments before the
+    /// program starts.
+    pub fn is_image(text: &str) -> bool {
+        text.lines()
+            .map(str::trim)
+            .take_while(|line| line.is_empty() || line.starts_with('#'))
+            .any(|line| line == "#!intcode")
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ProgramError> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+        text.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        write!(file, "{}", self)?;
+        file.flush()
+    }
+
+    /// The program with the patches applied. Patches past the end of it grow
+    /// it, like writing there would.
+    pub fn patched(&self) -> Program {
+        let mut words = self.program.to_vec();
+        for patch in &self.metadata.patches {
+            if patch.address >= words.len() {
+                words.resize(patch.address + 1, 0);
+            }
+            words[patch.address] = patch.value;
+        }
+        Program::from(words)
+    }
+
+    /// A machine all ready to run the patched program from its entry point.
+    pub fn machine(&self) -> Machine {
+        let mut machine = Machine::new(self.patched().into_vec());
+        machine.set_ip(self.metadata.entry);
+        machine
+    }
+}
+
+impl FromStr for Image {
+    type Err = ProgramError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut metadata = Metadata::default();
+        // The line each patch was on, for checking them against the program
+        let mut patch_lines = Vec::new();
+        let mut header = false;
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let bad = |message: String| ProgramError::BadMetadata {
+                line: index + 1,
+                message,
+            };
+            if line == "#!intcode" {
+                header = true;
+                continue;
+            }
+            let directive = match line.strip_prefix("#!") {
+                Some(directive) => directive,
+                None => continue,
+            };
+            if !header {
+                return Err(bad("Metadata before `#!intcode`".to_string()));
+            }
+            let mut parts = directive.splitn(2, ':');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts
+                .next()
+                .map(str::trim)
+                .ok_or_else(|| bad(format!("No value for `{}`", key)))?;
+            match key {
+                "name" => metadata.name = Some(value.to_string()),
+                "entry" => metadata.entry = value.parse().map_err(|_| bad(format!("Bad entry point `{}`", value)))?,
+                "io" => metadata.io = value.parse().map_err(bad)?,
+                "patch" => {
+                    for patch in value.split(',') {
+                        metadata.patches.push(patch.parse().map_err(bad)?);
+                        patch_lines.push(index + 1);
+                    }
+                }
+                "hash" => {
+                    let hash = value
+                        .strip_prefix("fnv1a64:")
+                        .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+                        .ok_or_else(|| bad(format!("Bad hash `{}`", value)))?;
+                    metadata.hash = Some(hash);
+                }
+                _ => return Err(bad(format!("Unknown metadata `{}`", key))),
+            }
+        }
+        if !header {
+            return Err(ProgramError::BadMetadata {
+                line: 1,
+                message: "Missing `#!intcode`".to_string(),
+            });
+        }
+        let program = parse_words(text)?;
+        let limit = program.len().saturating_add(MAX_PATCH_GROWTH);
+        for (patch, &line) in metadata.patches.iter().zip(&patch_lines) {
+            if patch.address > limit {
+                return Err(ProgramError::BadMetadata {
+                    line,
+                    message: format!("Patch address {} is too far past the end of the program", patch.address),
+                });
+            }
+        }
+        if let Some(expected) = metadata.hash {
+            let actual = program.hash();
+            if actual != expected {
+                return Err(ProgramError::HashMismatch { expected, actual });
+            }
+        }
+        Ok(Image { metadata, program })
+    }
+}
+
+/// Writes the image out with all its metadata, and a fresh hash.
+impl fmt::Display for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "#!intcode")?;
+        if let Some(name) = &self.metadata.name {
+            writeln!(f, "#!name: {}", name)?;
+        }
+        writeln!(f, "#!entry: {}", self.metadata.entry)?;
+        writeln!(f, "#!io: {}", self.metadata.io)?;
+        if !self.metadata.patches.is_empty() {
+            let patches: Vec<String> = self.metadata.patches.iter().map(Patch::to_string).collect();
+            writeln!(f, "#!patch: {}", patches.join(", "))?;
+        }
+        writeln!(f, "#!hash: fnv1a64:{:016x}", self.program.hash())?;
+        writeln!(f, "{}", self.program)
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let mut image = Image::new(Program::from(vec![1, 0, 0, 3, 1, 1, 2, 3, 99]));
+    image.metadata.name = Some("day 2".to_string());
+    image.metadata.patches = vec![Patch { address: 1, value: 12 }, Patch { address: 2, value: 2 }];
+    let text = image.to_string();
+    assert_eq!(
+        text,
+        format!(
+            "#!intcode\n#!name: day 2\n#!entry: 0\n#!io: numeric\n#!patch: 1=12, 2=2\n#!hash: fnv1a64:{:016x}\n\
+             1,0,0,3,1,1,2,3,99\n",
+            image.program.hash()
+        )
+    );
+    assert_eq!(text.parse::<Image>().unwrap(), image);
+    // The plain loader applies the patches, and ignores the rest
+    assert_eq!(
+        text.parse::<Program>().unwrap().into_vec(),
+        vec![1, 12, 2, 3, 1, 1, 2, 3, 99]
+    );
+    // FNV-1a's own test vector, for the empty string
+    assert_eq!(Program::default().hash(), 0xcbf2_9ce4_8422_2325);
+}
+
+#[test]
+fn test_metadata() {
+    let image: Image = "#!intcode\n#!entry: 2\n#!io: ascii\n#!patch: 6=104\n99,0,104,72,99\n"
+        .parse()
+        .unwrap();
+    assert_eq!(image.metadata.io, IoMode::Ascii);
+    assert_eq!(image.metadata.hash, None);
+    assert_eq!(image.patched().into_vec(), vec![99, 0, 104, 72, 99, 0, 104]);
+    let mut machine = image.machine();
+    machine.run().unwrap();
+    assert_eq!(machine.take_output(), vec![72]);
+
+    let err = |text: &str| text.parse::<Image>().unwrap_err().to_string();
+    assert_eq!(
+        err("#!intcode\n#!hash: fnv1a64:0000000000000001\n99\n"),
+        format!(
+            "Program error: The program's hash is {:016x}, but its metadata says 0000000000000001",
+            Program::from(vec![99]).hash()
+        )
+    );
+    assert_eq!(
+        err("#!intcode\n#!patch: 1=x\n99\n"),
+        "Program error: `1=x` isn't a patch like `1=12` at line 2"
+    );
+    assert_eq!(
+        err("#!intcode\n#!colour: red\n99\n"),
+        "Program error: Unknown metadata `colour` at line 2"
+    );
+    assert_eq!(err("99\n"), "Program error: Missing `#!intcode` at line 1");
+    // Patches can grow the program some, but not without limit
+    let far = format!("#!intcode\n#!patch: {}=1\n99\n", MAX_PATCH_GROWTH + 1);
+    assert_eq!(far.parse::<Image>().unwrap().patched().len(), MAX_PATCH_GROWTH + 2);
+    assert_eq!(
+        err("#!intcode\n#!patch: 1=2, 18446744073709551615=1\n99\n"),
+        "Program error: Patch address 18446744073709551615 is too far past the end of the program at line 2"
+    );
+    assert!("#!intcode\n#!name: x\n#!patch: 100000=1\n99\n".parse::<Program>().is_err());
+}
+
+#[test]
+fn test_plain_loader() {
+    // Other comments can come before the `#!intcode`
+    let text = "# Day 2, with the 1202 alarm\n#!intcode\n#!patch: 1=12\n1,0,0,3,99\n";
+    assert!(Image::is_image(text));
+    assert_eq!(text.parse::<Program>().unwrap().into_vec(), vec![1, 12, 0, 3, 99]);
+    // But not after it
+    assert!(!Image::is_image("1,0,0,3,99\n#!intcode\n"));
+    // A plain program can't start anywhere but 0
+    assert_eq!(
+        "#!intcode\n#!entry: 2\n99,0,99\n"
+            .parse::<Program>()
+            .unwrap_err()
+            .to_string(),
+        "Program error: The image starts at 2, but a plain program can only start at 0, so load it as an image"
+    );
+}
@@ -13,11 +13,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::disasm::format_operand;
+use super::table::{encode_word, Operand};
 use std::convert::TryFrom;
 use num_enum::TryFromPrimitive;
 use std::fmt;
 
-#[derive(Debug, PartialOrd, Ord, PartialEq, Eq)]
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy)]
 #[repr(i8)]
 /// An IntCode instruction.
 pub enum Instruction {
@@ -25,50 +27,239 @@ pub enum Instruction {
     Add(ParameterMode, ParameterMode, ParameterMode),
     /// opcode 2
     Multiply(ParameterMode, ParameterMode, ParameterMode),
+    /// opcode 3
+    Input(ParameterMode),
+    /// opcode 4
+    Output(ParameterMode),
+    /// opcode 5
+    JumpIfTrue(ParameterMode, ParameterMode),
+    /// opcode 6
+    JumpIfFalse(ParameterMode, ParameterMode),
+    /// opcode 7
+    LessThan(ParameterMode, ParameterMode, ParameterMode),
+    /// opcode 8
+    Equals(ParameterMode, ParameterMode, ParameterMode),
+    /// opcode 9
+    AdjustRelativeBase(ParameterMode),
     /// opcode 99
     Halt,
 }
 
+impl Instruction {
+    /// Builds an instruction out of an opcode and a mode for each of its
+    /// parameters. Returns `None` for an unknown opcode or the wrong number of
+    /// modes.
+    pub fn from_parts(opcode: i64, modes: &[ParameterMode]) -> Option<Self> {
+        use Instruction::*;
+        Some(match (opcode, modes) {
+            (1, &[m1, m2, m3]) => Add(m1, m2, m3),
+            (2, &[m1, m2, m3]) => Multiply(m1, m2, m3),
+            (3, &[m1]) => Input(m1),
+            (4, &[m1]) => Output(m1),
+            (5, &[m1, m2]) => JumpIfTrue(m1, m2),
+            (6, &[m1, m2]) => JumpIfFalse(m1, m2),
+            (7, &[m1, m2, m3]) => LessThan(m1, m2, m3),
+            (8, &[m1, m2, m3]) => Equals(m1, m2, m3),
+            (9, &[m1]) => AdjustRelativeBase(m1),
+            (99, &[]) => Halt,
+            _ => return None,
+        })
+    }
+
+    pub fn opcode(&self) -> i64 {
+        match *self {
+            Instruction::Add(..) => 1,
+            Instruction::Multiply(..) => 2,
+            Instruction::Input(..) => 3,
+            Instruction::Output(..) => 4,
+            Instruction::JumpIfTrue(..) => 5,
+            Instruction::JumpIfFalse(..) => 6,
+            Instruction::LessThan(..) => 7,
+            Instruction::Equals(..) => 8,
+            Instruction::AdjustRelativeBase(..) => 9,
+            Instruction::Halt => 99,
+        }
+    }
+
+    /// The mode of each parameter, in order.
+    pub fn modes(&self) -> Vec<ParameterMode> {
+        match *self {
+            Instruction::Add(m1, m2, m3)
+            | Instruction::Multiply(m1, m2, m3)
+            | Instruction::LessThan(m1, m2, m3)
+            | Instruction::Equals(m1, m2, m3) => vec![m1, m2, m3],
+            Instruction::JumpIfTrue(m1, m2) | Instruction::JumpIfFalse(m1, m2) => vec![m1, m2],
+            Instruction::Input(m1) | Instruction::Output(m1) | Instruction::AdjustRelativeBase(m1) => vec![m1],
+            Instruction::Halt => vec![],
+        }
+    }
+
+    /// How many parameters follow the instruction word.
+    pub fn operand_count(&self) -> usize {
+        match *self {
+            Instruction::Add(..)
+            | Instruction::Multiply(..)
+            | Instruction::LessThan(..)
+            | Instruction::Equals(..) => 3,
+            Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..) => 2,
+            Instruction::Input(..) | Instruction::Output(..) | Instruction::AdjustRelativeBase(..) => 1,
+            Instruction::Halt => 0,
+        }
+    }
+
+    /// The instruction word, with the opcode and parameter modes.
+    pub fn encode(&self) -> i64 {
+        encode_word(self.opcode(), &self.modes())
+    }
+
+    /// Pairs the instruction with the values of its parameters, for
+    /// displaying the whole thing.
+    pub fn with_operands(self, operands: &[i64]) -> WithOperands {
+        WithOperands {
+            instruction: self,
+            operands: operands.to_vec(),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Instruction::Add(..) => "Add",
+            Instruction::Multiply(..) => "Multiply",
+            Instruction::Input(..) => "Input",
+            Instruction::Output(..) => "Output",
+            Instruction::JumpIfTrue(..) => "JumpIfTrue",
+            Instruction::JumpIfFalse(..) => "JumpIfFalse",
+            Instruction::LessThan(..) => "LessThan",
+            Instruction::Equals(..) => "Equals",
+            Instruction::AdjustRelativeBase(..) => "AdjustRelativeBase",
+            Instruction::Halt => "Halt",
+        }
+    }
+}
+
+/// Shows the instruction and its parameter modes, like
+/// `Multiply(2) [Position(0), Immediate(1), Position(0)]`.
 impl fmt::Display for Instruction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match *self {
-                Instruction::Add(_,_,_) => "Add(1)",
-                Instruction::Multiply(_,_,_) => "Multiply(2)",
-                Instruction::Halt => "Halt(99)",
+        write!(f, "{}({})", self.name(), self.opcode())?;
+        let modes: Vec<String> = self.modes().iter().map(ParameterMode::to_string).collect();
+        if !modes.is_empty() {
+            write!(f, " [{}]", modes.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// An instruction along with its parameters.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct WithOperands {
+    pub instruction: Instruction,
+    pub operands: Vec<i64>,
+}
+
+/// Shows the parameters the same way the disassembler does, like
+/// `Multiply(2) [4], 3, [4]`.
+impl fmt::Display for WithOperands {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let instruction = self.instruction;
+        write!(f, "{}({})", instruction.name(), instruction.opcode())?;
+        let operands: Vec<String> = instruction
+            .modes()
+            .into_iter()
+            .zip(self.operands.iter())
+            .map(|(mode, &value)| format_operand(&Operand { address: 0, value, mode }))
+            .collect();
+        if !operands.is_empty() {
+            write!(f, " {}", operands.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy)]
+/// Why an instruction word couldn't be decoded.
+pub enum DecodeError {
+    /// The last two digits aren't an opcode we know.
+    UnknownOpcode,
+    /// A parameter (counting from 1) has a mode digit that isn't a mode.
+    InvalidParameterMode { param: usize, mode: i64 },
+    /// There's a nonzero digit where the mode of a parameter would be, past
+    /// the ones the instruction has.
+    UnexpectedDigit { param: usize, digit: i64 },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DecodeError::UnknownOpcode => write!(f, "Unknown opcode"),
+            DecodeError::InvalidParameterMode { param, mode } => {
+                write!(f, "Parameter {} has invalid mode {}", param, mode)
             }
-        )
+            DecodeError::UnexpectedDigit { param, digit } => {
+                write!(f, "Unexpected digit {} for parameter {}, which isn't there", digit, param)
+            }
+        }
     }
 }
 
+/// Gets the mode of a parameter (counting from 1) out of an instruction word.
+pub fn parameter_mode(value: isize, param: usize) -> Result<ParameterMode, DecodeError> {
+    let mode = (value / 10isize.pow(param as u32 + 1)) % 10;
+    ParameterMode::try_from(mode as i8).map_err(|_| DecodeError::InvalidParameterMode {
+        param,
+        mode: mode as i64,
+    })
+}
+
+/// Checks an instruction word with `params` parameters doesn't have any
+/// digits past their modes, besides leading zeros.
+pub fn check_unexpected_digits(word: i64, params: usize) -> Result<(), DecodeError> {
+    let mut digits = word / 10i64.pow(params as u32 + 2);
+    let mut param = params + 1;
+    while digits != 0 {
+        if digits % 10 != 0 {
+            return Err(DecodeError::UnexpectedDigit {
+                param,
+                digit: digits % 10,
+            });
+        }
+        digits /= 10;
+        param += 1;
+    }
+    Ok(())
+}
+
 impl TryFrom<isize> for Instruction {
-    type Error = &'static str;
+    type Error = DecodeError;
     fn try_from(value: isize)-> Result<Self, Self::Error> {
-        let opcode = value % 100;
-        let modes = value / 100;
-        let m1 = modes % 10;
-        let m2 = (modes % 100) / 10;
-        let m3 = (modes % 1000) / 100;
-        let m1 = ParameterMode::try_from(m1 as i8).map_err(|_| "Unknown parameter mode")?;
-        let m2 = ParameterMode::try_from(m2 as i8).map_err(|_| "Unknown parameter mode")?;
-        let m3 = ParameterMode::try_from(m3 as i8).map_err(|_| "Unknown parameter mode")?;
-        match opcode {
-            1 => Ok(Instruction::Add(m1,m2,m3)),
-            2 => Ok(Instruction::Multiply(m1,m2,m3)),
+        let m = |param| parameter_mode(value, param);
+        let inst = match value % 100 {
+            1 => Ok(Instruction::Add(m(1)?,m(2)?,m(3)?)),
+            2 => Ok(Instruction::Multiply(m(1)?,m(2)?,m(3)?)),
+            3 => Ok(Instruction::Input(m(1)?)),
+            4 => Ok(Instruction::Output(m(1)?)),
+            5 => Ok(Instruction::JumpIfTrue(m(1)?,m(2)?)),
+            6 => Ok(Instruction::JumpIfFalse(m(1)?,m(2)?)),
+            7 => Ok(Instruction::LessThan(m(1)?,m(2)?,m(3)?)),
+            8 => Ok(Instruction::Equals(m(1)?,m(2)?,m(3)?)),
+            9 => Ok(Instruction::AdjustRelativeBase(m(1)?)),
             99 => Ok(Instruction::Halt),
-            _ => Err("Unknown opcode")
-        }
+            _ => Err(DecodeError::UnknownOpcode)
+        }?;
+        // Same as `InstructionTable::decode`, so they agree on what runs
+        check_unexpected_digits(value as i64, inst.operand_count())?;
+        Ok(inst)
     }
 }
 
-#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, TryFromPrimitive)]
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy, TryFromPrimitive)]
 #[repr(i8)]
 /// An intcode parameter mode.
 pub enum ParameterMode {
     Position = 0,
     Immediate = 1,
+    /// Like position, but offset by the relative base.
+    Relative = 2,
 }
 
 impl fmt::Display for ParameterMode {
@@ -79,7 +270,96 @@ impl fmt::Display for ParameterMode {
             match *self {
                 ParameterMode::Position => "Position(0)",
                 ParameterMode::Immediate => "Immediate(1)",
+                ParameterMode::Relative => "Relative(2)",
             }
         )
     }
 }
+
+#[test]
+fn test_decode_errors() {
+    assert_eq!(Instruction::try_from(42), Err(DecodeError::UnknownOpcode));
+    assert_eq!(
+        Instruction::try_from(3101),
+        Err(DecodeError::InvalidParameterMode { param: 2, mode: 3 })
+    );
+    // Halt doesn't have any parameters, so there shouldn't be any modes
+    assert_eq!(
+        Instruction::try_from(30099),
+        Err(DecodeError::UnexpectedDigit { param: 3, digit: 3 })
+    );
+    assert_eq!(
+        Instruction::try_from(1_000_099).unwrap_err().to_string(),
+        "Unexpected digit 1 for parameter 5, which isn't there"
+    );
+    assert_eq!(Instruction::try_from(99), Ok(Instruction::Halt));
+}
+
+/// Every standard instruction, with every combination of modes.
+#[cfg(test)]
+fn all_instructions() -> Vec<Instruction> {
+    use ParameterMode::*;
+    let modes = [Position, Immediate, Relative];
+    let mut all = Vec::new();
+    let counts = [(1, 3), (2, 3), (3, 1), (4, 1), (5, 2), (6, 2), (7, 3), (8, 3), (9, 1), (99, 0)];
+    for &(opcode, count) in &counts {
+        for combination in 0..3usize.pow(count as u32) {
+            let chosen: Vec<ParameterMode> = (0..count).map(|i| modes[combination / 3usize.pow(i as u32) % 3]).collect();
+            all.push(Instruction::from_parts(opcode, &chosen).unwrap());
+        }
+    }
+    all
+}
+
+#[test]
+fn test_encode_round_trip() {
+    let all = all_instructions();
+    assert_eq!(all.len(), 4 * 27 + 2 * 9 + 3 * 3 + 1);
+    for instruction in all {
+        let word = instruction.encode();
+        assert_eq!(Instruction::try_from(word as isize), Ok(instruction), "{}", word);
+        assert_eq!(instruction.modes().len(), instruction.operand_count());
+        assert_eq!(Instruction::from_parts(instruction.opcode(), &instruction.modes()), Some(instruction));
+    }
+    assert_eq!(Instruction::from_parts(1, &[ParameterMode::Position]), None);
+    assert_eq!(Instruction::from_parts(42, &[]), None);
+}
+
+#[test]
+fn test_table_agrees() {
+    use super::table::InstructionTable;
+    let table = InstructionTable::new();
+    for instruction in all_instructions() {
+        let (info, modes) = table.decode(instruction.encode()).unwrap();
+        assert_eq!(info.opcode, instruction.opcode());
+        assert_eq!(info.params, instruction.operand_count());
+        assert_eq!(*modes, instruction.modes()[..]);
+    }
+}
+
+#[test]
+fn test_assembly_round_trip() {
+    use super::asm::assemble;
+    use super::disasm::disassemble;
+    use super::table::InstructionTable;
+    let table = InstructionTable::new();
+    // Every instruction, with some parameters, one after another
+    let mut program = Vec::new();
+    for (i, instruction) in all_instructions().into_iter().enumerate() {
+        program.push(instruction.encode());
+        program.extend((0..instruction.operand_count()).map(|n| (i * 3 + n) as i64 - 50));
+    }
+    let source: Vec<String> = disassemble(&program, &table).iter().map(|item| item.to_string()).collect();
+    assert_eq!(source.len(), all_instructions().len());
+    assert_eq!(assemble(&source.join("\n"), &table).unwrap().program, program);
+}
+
+#[test]
+fn test_display() {
+    use ParameterMode::*;
+    let instruction = Instruction::Multiply(Position, Immediate, Relative);
+    assert_eq!(instruction.to_string(), "Multiply(2) [Position(0), Immediate(1), Relative(2)]");
+    assert_eq!(instruction.with_operands(&[4, 3, -1]).to_string(), "Multiply(2) [4], 3, [rb-1]");
+    assert_eq!(Instruction::Halt.to_string(), "Halt(99)");
+    assert_eq!(Instruction::Halt.with_operands(&[]).to_string(), "Halt(99)");
+}
@@ -0,0 +1,747 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use super::instruction::{Instruction, ParameterMode};
+use super::coverage::Coverage;
+use super::memory::{DenseMemory, Memory};
+use super::session::{InputEvent, Session};
+use super::taint::{Labels, Taint};
+use super::table::{Flow, Handler, InstructionTable, Operand};
+use super::InterpreterError;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// What a machine is up to after stepping or running it.
+pub enum State {
+    /// Ready to execute the next instruction.
+    Running,
+    /// Stopped on an input instruction with nothing in the input queue. Push
+    /// some input and run again to carry on from the same instruction.
+    WaitingForInput,
+    /// Hit a halt instruction. Nothing more will happen.
+    Halted,
+}
+
+/// A resumable IntCode machine. Unlike `intcode_interpreter`, this knows the
+/// whole instruction set, does I/O through a pair of queues, and grows memory
+/// as needed, since memory past the end of the program is supposed to start
+/// out as 0.
+///
+/// Memory can be any backend from `memory`. It's kept behind a box rather than
+/// a type parameter so custom instruction handlers work on every machine.
+#[derive(Debug, Clone)]
+pub struct Machine {
+    memory: Box<dyn Memory>,
+    ip: usize,
+    relative_base: i64,
+    input: VecDeque<i64>,
+    output: VecDeque<i64>,
+    state: State,
+    table: Arc<InstructionTable>,
+    steps: u64,
+    step_limit: Option<u64>,
+    coverage: Option<Coverage>,
+    recording: Option<Session>,
+    taint: Option<Taint>,
+}
+
+impl Machine {
+    /// Returns a new machine that will start running the program at address 0.
+    pub fn new(program: Vec<i64>) -> Self {
+        Machine::with_table(program, InstructionTable::standard())
+    }
+
+    /// Like `new`, but with custom instructions from a table on top of the
+    /// standard ones.
+    pub fn with_table(program: Vec<i64>, table: Arc<InstructionTable>) -> Self {
+        Machine::with_memory(DenseMemory::from(program), table)
+    }
+
+    /// Like `with_table`, but with the program already loaded into some
+    /// other kind of memory.
+    ///
+    /// ```
+    /// use aoc_2019::intcode::memory::PagedMemory;
+    /// use aoc_2019::intcode::table::InstructionTable;
+    /// use aoc_2019::intcode::Machine;
+    /// let memory = PagedMemory::from(vec![1101, 1, 1, 1_000_000_000, 99]);
+    /// let mut machine = Machine::with_memory(memory, InstructionTable::standard());
+    /// machine.run().unwrap();
+    /// assert_eq!(machine.peek(1_000_000_000), 2);
+    /// ```
+    pub fn with_memory<M: Memory + 'static>(memory: M, table: Arc<InstructionTable>) -> Self {
+        Machine {
+            memory: Box::new(memory),
+            ip: 0,
+            relative_base: 0,
+            input: VecDeque::new(),
+            output: VecDeque::new(),
+            state: State::Running,
+            table,
+            steps: 0,
+            step_limit: None,
+            coverage: None,
+            recording: None,
+            taint: None,
+        }
+    }
+
+    pub fn table(&self) -> &InstructionTable {
+        &self.table
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// Moves execution somewhere else. The machine carries on from there
+    /// the next time it's stepped or run.
+    pub fn set_ip(&mut self, ip: usize) {
+        self.ip = ip;
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    pub fn set_relative_base(&mut self, relative_base: i64) {
+        self.relative_base = relative_base;
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// How many instructions have been executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Makes running fail with `StepLimitExceeded` once this many
+    /// instructions have been executed in total, for programs that might
+    /// never halt.
+    pub fn set_step_limit(&mut self, limit: Option<u64>) {
+        self.step_limit = limit;
+    }
+
+    pub fn memory(&self) -> &dyn Memory {
+        &*self.memory
+    }
+
+    /// Gives back the memory as a `Vec`, for looking at the results of a
+    /// program.
+    pub fn into_memory(self) -> Vec<i64> {
+        self.memory.to_vec()
+    }
+
+    /// Reads a value from memory. Anything past the end reads as 0.
+    pub fn peek(&self, addr: usize) -> i64 {
+        self.memory.get(addr)
+    }
+
+    /// Writes a value to memory, growing it if needed. Useful for patching a
+    /// program before running it.
+    pub fn poke(&mut self, addr: usize, value: i64) {
+        self.memory.set(addr, value);
+    }
+
+    pub fn push_input(&mut self, value: i64) {
+        self.input.push_back(value);
+    }
+
+    pub fn extend_input<I: IntoIterator<Item = i64>>(&mut self, values: I) {
+        self.input.extend(values);
+    }
+
+    /// How many values are queued up for input.
+    pub fn input_len(&self) -> usize {
+        self.input.len()
+    }
+
+    /// Takes the oldest value out of the input queue. Meant for custom
+    /// instructions that want to do their own input, which then gets
+    /// recorded like any other.
+    pub fn pop_input(&mut self) -> Option<i64> {
+        let value = self.input.pop_front()?;
+        if let Some(session) = &mut self.recording {
+            session.inputs.push(InputEvent {
+                step: self.steps,
+                value,
+            });
+        }
+        Some(value)
+    }
+
+    /// Starts recording every input value as it's consumed, so the run can
+    /// be replayed later.
+    pub fn start_recording(&mut self) {
+        if self.recording.is_none() {
+            self.recording = Some(Session::new());
+        }
+    }
+
+    /// Takes what's been recorded so far, which also stops recording.
+    pub fn take_recording(&mut self) -> Option<Session> {
+        self.recording.take()
+    }
+
+    pub fn push_output(&mut self, value: i64) {
+        self.output.push_back(value);
+    }
+
+    /// Takes the oldest value out of the output queue.
+    pub fn pop_output(&mut self) -> Option<i64> {
+        self.output.pop_front()
+    }
+
+    /// The newest value in the output queue, leaving it there.
+    pub fn last_output(&self) -> Option<i64> {
+        self.output.back().copied()
+    }
+
+    /// Takes everything out of the output queue.
+    pub fn take_output(&mut self) -> Vec<i64> {
+        self.output.drain(..).collect()
+    }
+
+    /// Starts keeping track of which instructions run, and which ways
+    /// branches go.
+    pub fn enable_coverage(&mut self) {
+        if self.coverage.is_none() {
+            self.coverage = Some(Coverage::new());
+        }
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    /// Takes the coverage so far, which also stops collecting it.
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    /// Starts tracking which values depend on input. See `taint` for how.
+    pub fn enable_taint(&mut self) {
+        if self.taint.is_none() {
+            self.taint = Some(Taint::new());
+        }
+    }
+
+    /// Makes a cell a source of taint, like a patched noun or verb, turning on
+    /// taint tracking if it isn't already.
+    pub fn taint_cell(&mut self, address: usize) {
+        self.taint.get_or_insert_with(Taint::new).mark(address);
+    }
+
+    pub fn taint(&self) -> Option<&Taint> {
+        self.taint.as_ref()
+    }
+
+    /// Takes the taint state so far, which also stops tracking it.
+    pub fn take_taint(&mut self) -> Option<Taint> {
+        self.taint.take()
+    }
+
+    /// Decodes a parameter of the current instruction.
+    fn operand(&self, parameter: usize, mode: ParameterMode) -> Operand {
+        let address = self.ip + parameter;
+        Operand {
+            address,
+            value: self.peek(address),
+            mode,
+        }
+    }
+
+    fn load(&self, parameter: usize, mode: ParameterMode) -> Result<i64, InterpreterError> {
+        self.read(self.operand(parameter, mode))
+    }
+
+    fn store(&mut self, parameter: usize, mode: ParameterMode, value: i64) -> Result<(), InterpreterError> {
+        self.write(self.operand(parameter, mode), value)
+    }
+
+    /// Reads the value an operand refers to.
+    pub fn read(&self, operand: Operand) -> Result<i64, InterpreterError> {
+        match operand.mode {
+            ParameterMode::Immediate => Ok(operand.value),
+            _ => Ok(self.peek(self.operand_address(operand)?)),
+        }
+    }
+
+    /// Writes to wherever an operand refers to. Immediate mode operands
+    /// can't be written to.
+    pub fn write(&mut self, operand: Operand, value: i64) -> Result<(), InterpreterError> {
+        if operand.mode == ParameterMode::Immediate {
+            return Err(InterpreterError::WriteToImmediate {
+                ip: self.ip,
+                code: self.peek(self.ip),
+                param: self.param(operand),
+            });
+        }
+        let addr = self.operand_address(operand)?;
+        self.poke(addr, value);
+        Ok(())
+    }
+
+    fn operand_address(&self, operand: Operand) -> Result<usize, InterpreterError> {
+        let target = match operand.mode {
+            ParameterMode::Immediate => return Ok(operand.address),
+            ParameterMode::Position => operand.value,
+            ParameterMode::Relative => self.relative_base + operand.value,
+        };
+        self.check_address(target, self.param(operand))
+    }
+
+    /// Which parameter of the current instruction an operand is, for errors.
+    /// Handlers can make up operands from anywhere, so ones from before the
+    /// instruction count as parameter 0.
+    fn param(&self, operand: Operand) -> usize {
+        operand.address.saturating_sub(self.ip)
+    }
+
+    fn check_address(&self, address: i64, param: usize) -> Result<usize, InterpreterError> {
+        usize::try_from(address).map_err(|_| InterpreterError::NegativeAddress {
+            ip: self.ip,
+            param,
+            address,
+        })
+    }
+
+    /// Runs an instruction with a handler from the table.
+    fn step_custom(&mut self, handler: Handler, modes: &[ParameterMode]) -> Result<(), InterpreterError> {
+        let operands: Vec<Operand> = modes
+            .iter()
+            .enumerate()
+            .map(|(i, &mode)| self.operand(i + 1, mode))
+            .collect();
+        let ip = self.ip;
+        let flow = handler(self, &operands)?;
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(ip, None);
+        }
+        match flow {
+            Flow::Next => self.ip += operands.len() + 1,
+            Flow::Jump(target) => self.ip = target,
+            Flow::Halt => self.state = State::Halted,
+        }
+        Ok(())
+    }
+
+    /// Each operand of an instruction about to run, and the address it
+    /// refers to, if it's not immediate and the address is valid.
+    fn resolve_operands(&self, inst: Instruction) -> Vec<(Operand, Option<usize>)> {
+        inst.modes()
+            .iter()
+            .enumerate()
+            .map(|(i, &mode)| {
+                let operand = self.operand(i + 1, mode);
+                let target = match mode {
+                    ParameterMode::Immediate => None,
+                    _ => self.operand_address(operand).ok(),
+                };
+                (operand, target)
+            })
+            .collect()
+    }
+
+    /// Moves taint labels along for an instruction that's just run, given
+    /// its operands from before it ran.
+    fn track_taint(
+        &mut self,
+        ip: usize,
+        inst: Instruction,
+        operands: &[(Operand, Option<usize>)],
+        branch: Option<bool>,
+    ) {
+        let waiting = self.state == State::WaitingForInput;
+        let output = self.last_output().unwrap_or(0);
+        let taint = match &mut self.taint {
+            Some(taint) => taint,
+            None => return,
+        };
+        let labels: Vec<Labels> = operands
+            .iter()
+            .map(|(operand, target)| taint.operand(operand, *target))
+            .collect();
+        match inst {
+            Instruction::Add(..) | Instruction::Multiply(..) | Instruction::LessThan(..) | Instruction::Equals(..) => {
+                if let Some(dest) = operands[2].1 {
+                    // Just the pointer's labels for the destination, since
+                    // the value that was there is gone
+                    let pointer = taint.operand(&operands[2].0, None);
+                    taint.write(dest, &(&labels[0] | &labels[1]) | &pointer);
+                }
+            }
+            Instruction::Input(..) => {
+                if let (false, Some(dest)) = (waiting, operands[0].1) {
+                    taint.input(dest, taint.operand(&operands[0].0, None));
+                }
+            }
+            Instruction::Output(..) => taint.output(ip, output, labels[0].clone()),
+            Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..) => {
+                taint.decision(ip, branch == Some(true), &labels[0] | &labels[1]);
+            }
+            Instruction::AdjustRelativeBase(..) => taint.adjust_relative_base(labels[0].clone()),
+            Instruction::Halt => {}
+        }
+    }
+
+    fn jump(&mut self, parameter: usize, mode: ParameterMode) -> Result<(), InterpreterError> {
+        let target = self.load(parameter, mode)?;
+        self.ip = self.check_address(target, parameter)?;
+        Ok(())
+    }
+
+    /// Executes a single instruction. If the instruction is an input and there
+    /// isn't any, the machine stays on that instruction and reports
+    /// `State::WaitingForInput`.
+    pub fn step(&mut self) -> Result<State, InterpreterError> {
+        if self.state == State::Halted {
+            return Ok(State::Halted);
+        }
+        if let Some(limit) = self.step_limit {
+            if self.steps >= limit {
+                return Err(InterpreterError::StepLimitExceeded {
+                    ip: self.ip,
+                    steps: self.steps,
+                });
+            }
+        }
+        let code = self.peek(self.ip);
+        self.state = State::Running;
+        self.steps += 1;
+        // Everything goes through the table, same as the disassembler, so
+        // they agree on what's an instruction. Only custom instructions need
+        // anything from it once it's decoded, since they need the machine
+        let (opcode, handler, modes) = match self.table.decode(code) {
+            Ok((info, modes)) => (info.opcode, info.handler.clone(), modes),
+            Err(err) => return Err(InterpreterError::from_decode(err, self.ip, code)),
+        };
+        if let Some(handler) = handler {
+            self.step_custom(handler, &modes)?;
+            return Ok(self.state);
+        }
+        let inst = Instruction::from_parts(opcode, &modes)
+            .ok_or(InterpreterError::InvalidInstruction { address: self.ip, code })?;
+        let ip = self.ip;
+        // Which way a branch went, for coverage
+        let mut branch = None;
+        // Where the operands point before anything changes, for taint
+        let tainting = self.taint.as_ref().map(|_| self.resolve_operands(inst));
+        match inst {
+            Instruction::Add(m1, m2, m3) => {
+                let value = self.load(1, m1)? + self.load(2, m2)?;
+                self.store(3, m3, value)?;
+                self.ip += 4;
+            }
+            Instruction::Multiply(m1, m2, m3) => {
+                let value = self.load(1, m1)? * self.load(2, m2)?;
+                self.store(3, m3, value)?;
+                self.ip += 4;
+            }
+            Instruction::Input(m1) => match self.pop_input() {
+                Some(value) => {
+                    self.store(1, m1, value)?;
+                    self.ip += 2;
+                }
+                None => {
+                    // Waiting doesn't count as a step
+                    self.steps -= 1;
+                    self.state = State::WaitingForInput;
+                }
+            },
+            Instruction::Output(m1) => {
+                let value = self.load(1, m1)?;
+                self.output.push_back(value);
+                self.ip += 2;
+            }
+            Instruction::JumpIfTrue(m1, m2) | Instruction::JumpIfFalse(m1, m2) => {
+                let jump_if = matches!(inst, Instruction::JumpIfTrue(..));
+                let taken = (self.load(1, m1)? != 0) == jump_if;
+                if taken {
+                    self.jump(2, m2)?;
+                } else {
+                    self.ip += 3;
+                }
+                branch = Some(taken);
+            }
+            Instruction::LessThan(m1, m2, m3) => {
+                let result = self.load(1, m1)? < self.load(2, m2)?;
+                self.store(3, m3, result as i64)?;
+                self.ip += 4;
+                branch = Some(result);
+            }
+            Instruction::Equals(m1, m2, m3) => {
+                let result = self.load(1, m1)? == self.load(2, m2)?;
+                self.store(3, m3, result as i64)?;
+                self.ip += 4;
+                branch = Some(result);
+            }
+            Instruction::AdjustRelativeBase(m1) => {
+                self.relative_base += self.load(1, m1)?;
+                self.ip += 2;
+            }
+            Instruction::Halt => self.state = State::Halted,
+        }
+        if let Some(operands) = tainting {
+            self.track_taint(ip, inst, &operands, branch);
+        }
+        if let Some(coverage) = &mut self.coverage {
+            if self.state != State::WaitingForInput {
+                coverage.record(ip, branch);
+            }
+        }
+        Ok(self.state)
+    }
+
+    /// Runs until the machine halts or needs input it doesn't have.
+    pub fn run(&mut self) -> Result<State, InterpreterError> {
+        loop {
+            match self.step()? {
+                State::Running => continue,
+                state => return Ok(state),
+            }
+        }
+    }
+
+    /// Runs a program with some input, returning everything it output. Since
+    /// there won't be any more input, wanting more than it got is an error.
+    pub fn run_with_input<I: IntoIterator<Item = i64>>(&mut self, input: I) -> Result<Vec<i64>, InterpreterError> {
+        self.extend_input(input);
+        match self.run()? {
+            State::WaitingForInput => Err(InterpreterError::InputExhausted { ip: self.ip }),
+            _ => Ok(self.take_output()),
+        }
+    }
+}
+
+#[test]
+fn test_io_and_compare() {
+    // Outputs whether the input is equal to 8 (day 5)
+    let program = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+    assert_eq!(Machine::new(program.clone()).run_with_input(vec![8]), Ok(vec![1]));
+    assert_eq!(Machine::new(program).run_with_input(vec![7]), Ok(vec![0]));
+    // Outputs whether the input is less than 8, in immediate mode
+    let program = vec![3, 3, 1107, -1, 8, 3, 4, 3, 99];
+    assert_eq!(Machine::new(program.clone()).run_with_input(vec![5]), Ok(vec![1]));
+    assert_eq!(Machine::new(program).run_with_input(vec![9]), Ok(vec![0]));
+}
+
+#[test]
+fn test_relative_base_and_large_memory() {
+    // A quine (day 9), which needs memory past the end of the program
+    let quine = vec![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99];
+    assert_eq!(Machine::new(quine.clone()).run_with_input(vec![]), Ok(quine));
+    let program = vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0];
+    assert_eq!(Machine::new(program).run_with_input(vec![]), Ok(vec![1219070632396864]));
+}
+
+#[test]
+fn test_resume_after_input() {
+    // Echoes two inputs back
+    let mut machine = Machine::new(vec![3, 9, 4, 9, 3, 9, 4, 9, 99, 0]);
+    assert_eq!(machine.run(), Ok(State::WaitingForInput));
+    assert_eq!(machine.ip(), 0);
+    machine.push_input(5);
+    assert_eq!(machine.run(), Ok(State::WaitingForInput));
+    assert_eq!(machine.pop_output(), Some(5));
+    machine.push_input(6);
+    assert_eq!(machine.run(), Ok(State::Halted));
+    assert_eq!(machine.take_output(), vec![6]);
+    assert_eq!(machine.step(), Ok(State::Halted));
+}
+
+#[test]
+fn test_custom_instructions() {
+    let mut table = InstructionTable::new();
+    // Outputs the sum of its two parameters, twice, like a debug print
+    table
+        .register(42, "dbg", 2, |machine: &mut Machine, ops: &[Operand]| {
+            let value = machine.read(ops[0])? + machine.read(ops[1])?;
+            machine.push_output(value);
+            machine.push_output(value);
+            Ok(Flow::Next)
+        })
+        .unwrap();
+    // Stops the machine if its parameter is zero
+    table
+        .register(50, "hz", 1, |machine: &mut Machine, ops: &[Operand]| {
+            Ok(if machine.read(ops[0])? == 0 { Flow::Halt } else { Flow::Next })
+        })
+        .unwrap();
+    let mut machine = Machine::with_table(vec![1042, 8, 3, 150, 0, 4, 8, 99, 5], Arc::new(table));
+    assert_eq!(machine.run(), Ok(State::Halted));
+    assert_eq!(machine.take_output(), vec![8, 8]);
+    assert_eq!(machine.ip(), 3);
+    // Plain machines don't know about them
+    assert_eq!(
+        Machine::new(vec![1042, 8, 3, 99]).run(),
+        Err(InterpreterError::InvalidInstruction { address: 0, code: 1042 })
+    );
+}
+
+#[test]
+fn test_operands_before_ip() {
+    // Read from and write to the cell at 0, from wherever they are
+    let behind = |mode| Operand {
+        address: 0,
+        value: -1,
+        mode,
+    };
+    let mut table = InstructionTable::new();
+    table
+        .register(60, "rd0", 0, move |machine: &mut Machine, _: &[Operand]| {
+            machine.read(behind(ParameterMode::Position))?;
+            Ok(Flow::Next)
+        })
+        .unwrap();
+    table
+        .register(61, "wr0", 0, move |machine: &mut Machine, _: &[Operand]| {
+            machine.write(behind(ParameterMode::Immediate), 1)?;
+            Ok(Flow::Next)
+        })
+        .unwrap();
+    let mut machine = Machine::with_table(vec![99, 60, 61, 99], Arc::new(table));
+    machine.set_ip(1);
+    assert_eq!(
+        machine.run(),
+        Err(InterpreterError::NegativeAddress {
+            ip: 1,
+            param: 0,
+            address: -1
+        })
+    );
+    machine.set_ip(2);
+    assert_eq!(
+        machine.run(),
+        Err(InterpreterError::WriteToImmediate { ip: 2, code: 61, param: 0 })
+    );
+}
+
+#[test]
+fn test_decoders_agree() {
+    use super::disasm::{disassemble, Item};
+    use super::intcode_interpreter;
+    use std::convert::TryFrom;
+    // Every opcode with every combination of three mode digits, plus some
+    // extra digits, like 30099, and negatives. Things like writing to an
+    // immediate still fail when run, but not at decoding
+    let table = InstructionTable::new();
+    let decode_error = |result: Result<_, InterpreterError>| {
+        matches!(
+            result,
+            Err(InterpreterError::InvalidInstruction { .. })
+                | Err(InterpreterError::InvalidParameterMode { .. })
+                | Err(InterpreterError::UnexpectedDigit { .. })
+        )
+    };
+    for word in (-200..100_000).chain((0..100).map(|opcode| 1_000_000 + opcode)) {
+        let mut machine = Machine::new(vec![word, 0, 0, 0]);
+        let runs = !decode_error(machine.step().map(|_| ()));
+        let disassembles = matches!(disassemble(&[word, 0, 0, 0], &table)[0], Item::Instruction { .. });
+        assert_eq!(runs, disassembles, "{}", word);
+        assert_eq!(runs, table.decode(word).is_ok(), "{}", word);
+        assert_eq!(runs, Instruction::try_from(word as isize).is_ok(), "{}", word);
+        // The simple interpreter decodes with that too. It can loop forever
+        // on what does decode, so only try it on what doesn't
+        if !runs {
+            let simple = intcode_interpreter(&mut vec![word, 0, 0, 0]).map(|_| ());
+            assert!(decode_error(simple), "{}", word);
+        }
+    }
+    assert_eq!(
+        Machine::new(vec![1099]).run(),
+        Err(InterpreterError::UnexpectedDigit {
+            address: 0,
+            code: 1099,
+            param: 2,
+            digit: 1
+        })
+    );
+    assert_eq!(intcode_interpreter(&mut vec![1099]).map(|_| ()), Machine::new(vec![1099]).run().map(|_| ()));
+}
+
+#[test]
+fn test_override_standard() {
+    // A table without output makes it invalid
+    let mut table = InstructionTable::new();
+    table.remove(4);
+    let program = vec![104, 7, 99];
+    assert_eq!(
+        Machine::with_table(program.clone(), Arc::new(table.clone())).run(),
+        Err(InterpreterError::InvalidInstruction { address: 0, code: 104 })
+    );
+    // And then one that outputs twice instead
+    table
+        .register(4, "out", 1, |machine: &mut Machine, ops: &[Operand]| {
+            let value = machine.read(ops[0])?;
+            machine.push_output(value);
+            machine.push_output(value);
+            Ok(Flow::Next)
+        })
+        .unwrap();
+    let mut machine = Machine::with_table(program, Arc::new(table));
+    assert_eq!(machine.run_with_input(vec![]), Ok(vec![7, 7]));
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(
+        Machine::new(vec![3, 0, 99]).run_with_input(vec![]),
+        Err(InterpreterError::InputExhausted { ip: 0 })
+    );
+    assert_eq!(
+        Machine::new(vec![109, -5, 204, 1, 99]).run(),
+        Err(InterpreterError::NegativeAddress { ip: 2, param: 1, address: -4 })
+    );
+    assert_eq!(
+        Machine::new(vec![1105, 1, -1]).run(),
+        Err(InterpreterError::NegativeAddress { ip: 0, param: 2, address: -1 })
+    );
+    assert_eq!(
+        Machine::new(vec![11101, 1, 1, 0, 99]).run(),
+        Err(InterpreterError::WriteToImmediate { ip: 0, code: 11101, param: 3 })
+    );
+    assert_eq!(
+        Machine::new(vec![104, 1, 504, 0]).run(),
+        Err(InterpreterError::InvalidParameterMode { address: 2, code: 504, param: 1, mode: 5 })
+    );
+    // Loops forever
+    let mut machine = Machine::new(vec![1105, 1, 0]);
+    machine.set_step_limit(Some(100));
+    assert_eq!(
+        machine.run(),
+        Err(InterpreterError::StepLimitExceeded { ip: 0, steps: 100 })
+    );
+    assert_eq!(machine.steps(), 100);
+}
+
+#[test]
+fn test_memory_backends() {
+    use super::memory::{HashMemory, PagedMemory};
+    // Writes its input way out past the end, then reads it back
+    let program = vec![3, 1_000_000_000_000, 4, 1_000_000_000_000, 99];
+    fn check<M: Memory + 'static>(memory: M) {
+        let mut machine = Machine::with_memory(memory, InstructionTable::standard());
+        assert_eq!(machine.run_with_input(vec![17]), Ok(vec![17]));
+        assert_eq!(machine.memory().len(), 1_000_000_000_001);
+    }
+    check(PagedMemory::from(program.clone()));
+    check(HashMemory::from(program));
+}
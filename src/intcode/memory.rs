@@ -0,0 +1,248 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Places for a machine to keep its memory. Every address starts out as 0,
+//! so they only need to remember what's been written.
+//!
+//! - `DenseMemory` is a plain `Vec`, which is fastest for normal programs but
+//!   allocates everything up to the highest address written.
+//! - `PagedMemory` splits memory into 4K pages that are only allocated when
+//!   written, and shared copy-on-write between clones, so forking a machine
+//!   only copies the pages that change afterwards.
+//! - `HashMemory` keeps each written address in a `HashMap`, for programs
+//!   that scatter a few values all over the place.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+/// Memory for a machine, addressed by word.
+pub trait Memory: fmt::Debug + Send + Sync {
+    /// Reads a word. Anything never written reads as 0.
+    fn get(&self, addr: usize) -> i64;
+
+    fn set(&mut self, addr: usize, value: i64);
+
+    /// One past the highest address that's been written (or was part of the
+    /// program).
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copies everything up to `len` out into a `Vec`. Careful with programs
+    /// that write to huge addresses.
+    fn to_vec(&self) -> Vec<i64> {
+        (0..self.len()).map(|addr| self.get(addr)).collect()
+    }
+
+    /// Clones into a new box, since `Clone` can't be a supertrait of a trait
+    /// used as a trait object.
+    fn boxed_clone(&self) -> Box<dyn Memory>;
+}
+
+impl Clone for Box<dyn Memory> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+/// Memory as one contiguous `Vec`, grown as needed.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct DenseMemory(Vec<i64>);
+
+impl From<Vec<i64>> for DenseMemory {
+    fn from(program: Vec<i64>) -> Self {
+        DenseMemory(program)
+    }
+}
+
+impl Memory for DenseMemory {
+    fn get(&self, addr: usize) -> i64 {
+        self.0.get(addr).copied().unwrap_or(0)
+    }
+
+    fn set(&mut self, addr: usize, value: i64) {
+        if addr >= self.0.len() {
+            self.0.resize(addr + 1, 0);
+        }
+        self.0[addr] = value;
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn to_vec(&self) -> Vec<i64> {
+        self.0.clone()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Memory> {
+        Box::new(self.clone())
+    }
+}
+
+/// Words per page.
+pub const PAGE_SIZE: usize = 4096;
+
+type Page = [i64; PAGE_SIZE];
+
+/// Memory in pages, which are only allocated once something is written to
+/// them. Clones share pages until one of them writes to a page, which then
+/// gets its own copy.
+#[derive(Debug, Clone, Default)]
+pub struct PagedMemory {
+    pages: BTreeMap<usize, Arc<Page>>,
+    len: usize,
+}
+
+impl PagedMemory {
+    pub fn new() -> Self {
+        PagedMemory::default()
+    }
+
+    /// How many pages have actually been allocated.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Whether a page is shared with another clone.
+    pub fn is_shared(&self, page: usize) -> bool {
+        self.pages.get(&page).is_some_and(|page| Arc::strong_count(page) > 1)
+    }
+}
+
+impl From<Vec<i64>> for PagedMemory {
+    fn from(program: Vec<i64>) -> Self {
+        let mut memory = PagedMemory::new();
+        for (index, chunk) in program.chunks(PAGE_SIZE).enumerate() {
+            let mut page = [0; PAGE_SIZE];
+            page[..chunk.len()].copy_from_slice(chunk);
+            memory.pages.insert(index, Arc::new(page));
+        }
+        memory.len = program.len();
+        memory
+    }
+}
+
+impl Memory for PagedMemory {
+    fn get(&self, addr: usize) -> i64 {
+        self.pages
+            .get(&(addr / PAGE_SIZE))
+            .map_or(0, |page| page[addr % PAGE_SIZE])
+    }
+
+    fn set(&mut self, addr: usize, value: i64) {
+        let page = self
+            .pages
+            .entry(addr / PAGE_SIZE)
+            .or_insert_with(|| Arc::new([0; PAGE_SIZE]));
+        Arc::make_mut(page)[addr % PAGE_SIZE] = value;
+        self.len = self.len.max(addr + 1);
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Memory> {
+        Box::new(self.clone())
+    }
+}
+
+/// Memory as a map from address to value.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct HashMemory {
+    words: HashMap<usize, i64>,
+    len: usize,
+}
+
+impl HashMemory {
+    pub fn new() -> Self {
+        HashMemory::default()
+    }
+}
+
+impl From<Vec<i64>> for HashMemory {
+    fn from(program: Vec<i64>) -> Self {
+        let len = program.len();
+        let words = program
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, value)| value != 0)
+            .collect();
+        HashMemory { words, len }
+    }
+}
+
+impl Memory for HashMemory {
+    fn get(&self, addr: usize) -> i64 {
+        self.words.get(&addr).copied().unwrap_or(0)
+    }
+
+    fn set(&mut self, addr: usize, value: i64) {
+        if value == 0 {
+            self.words.remove(&addr);
+        } else {
+            self.words.insert(addr, value);
+        }
+        self.len = self.len.max(addr + 1);
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Memory> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+fn check_backend(mut memory: Box<dyn Memory>) {
+    assert_eq!(memory.to_vec(), vec![1, 0, 2]);
+    assert_eq!(memory.get(1_000_000), 0);
+    memory.set(5, 7);
+    assert_eq!(memory.to_vec(), vec![1, 0, 2, 0, 0, 7]);
+    let high = 1 << 40;
+    memory.set(high, -1);
+    assert_eq!(memory.get(high), -1);
+    assert_eq!(memory.len(), high + 1);
+    let mut fork = memory.clone();
+    fork.set(0, 9);
+    assert_eq!((memory.get(0), fork.get(0)), (1, 9));
+}
+
+#[test]
+fn test_backends() {
+    assert_eq!(DenseMemory::from(vec![1, 0, 2]).to_vec(), vec![1, 0, 2]);
+    check_backend(Box::new(PagedMemory::from(vec![1, 0, 2])));
+    check_backend(Box::new(HashMemory::from(vec![1, 0, 2])));
+}
+
+#[test]
+fn test_copy_on_write() {
+    let mut memory = PagedMemory::from((0..5000).collect::<Vec<i64>>());
+    assert_eq!(memory.page_count(), 2);
+    let mut fork = memory.clone();
+    assert!(memory.is_shared(0) && memory.is_shared(1));
+    fork.set(4096, -1);
+    assert!(memory.is_shared(0) && !memory.is_shared(1));
+    assert_eq!((memory.get(4096), fork.get(4096)), (4096, -1));
+    memory.set(10, 0);
+    assert!(!fork.is_shared(0));
+    assert_eq!(fork.get(10), 10);
+}
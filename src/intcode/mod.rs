@@ -17,30 +17,135 @@ use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt;
 
+pub mod asm;
+pub mod bench;
+pub mod coverage;
+pub mod dap;
+pub mod debug;
+pub mod diff;
+pub mod disasm;
+pub mod gdb;
+mod dsl;
+pub mod image;
 pub mod instruction;
-use instruction::{Instruction, ParameterMode};
+mod machine;
+pub mod memory;
+pub mod optimize;
+mod program;
+pub mod scheduler;
+pub mod search;
+pub mod session;
+pub mod table;
+pub mod taint;
+pub mod translate;
+use instruction::{DecodeError, Instruction, ParameterMode};
+pub use machine::{Machine, State};
+pub use program::{Program, ProgramError};
 
-#[derive(Debug, PartialOrd, Ord, PartialEq, Eq)]
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone)]
 /// An interpreter error. These indicate unrecoverable failure of the IntCode interpreter.
+/// Parameters are counted from 1, the same as in the instruction word.
 pub enum InterpreterError {
     /// Tried to fetch from an invalid address in memory. This should only be
     /// possible with an address that points outside the memory space.
-    EndOfMemory,
+    EndOfMemory { address: usize },
+    /// A parameter of the instruction at `ip` pointed at (or jumped to) a
+    /// negative address.
+    NegativeAddress { ip: usize, param: usize, address: i64 },
+    /// The instruction at `ip` tried to write to an immediate mode parameter.
+    WriteToImmediate { ip: usize, code: i64, param: usize },
+    /// The instruction at `address` has a mode digit that isn't a mode.
+    InvalidParameterMode {
+        address: usize,
+        code: i64,
+        param: usize,
+        mode: i64,
+    },
+    /// The instruction at `address` has a digit where the mode of a
+    /// parameter it doesn't have would be.
+    UnexpectedDigit {
+        address: usize,
+        code: i64,
+        param: usize,
+        digit: i64,
+    },
     /// Tried to execute an invalid (unknown) instruction. Holds the address at
     /// which the invalid instruction was encountered and the value of the instruction.
     InvalidInstruction {
         address: usize,
         code: i64, // Cannot use Instruction because not being a valid Instruction *is* the error
     },
+    /// The input instruction at `ip` wanted input, and there wasn't going to
+    /// be any more.
+    InputExhausted { ip: usize },
+    /// Ran for as many steps as allowed without halting. `ip` is the
+    /// instruction it would have run next.
+    StepLimitExceeded { ip: usize, steps: u64 },
+}
+
+impl InterpreterError {
+    /// Turns a decoding failure for the instruction word `code` at `address`
+    /// into an interpreter error.
+    pub fn from_decode(err: DecodeError, address: usize, code: i64) -> Self {
+        match err {
+            DecodeError::UnknownOpcode => InterpreterError::InvalidInstruction { address, code },
+            DecodeError::InvalidParameterMode { param, mode } => InterpreterError::InvalidParameterMode {
+                address,
+                code,
+                param,
+                mode,
+            },
+            DecodeError::UnexpectedDigit { param, digit } => InterpreterError::UnexpectedDigit {
+                address,
+                code,
+                param,
+                digit,
+            },
+        }
+    }
 }
 
 impl fmt::Display for InterpreterError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let err: String = match self {
-            InterpreterError::EndOfMemory => "Fetch past end of memory".to_string(),
+            InterpreterError::EndOfMemory { address } => {
+                format!("Fetch past end of memory at address {}", address)
+            }
+            InterpreterError::NegativeAddress { ip, param, address } => format!(
+                "Parameter {} of the instruction at {} refers to negative address {}",
+                param, ip, address
+            ),
+            InterpreterError::WriteToImmediate { ip, code, param } => format!(
+                "Parameter {} of instruction {} at {} writes in immediate mode",
+                param, code, ip
+            ),
+            InterpreterError::InvalidParameterMode {
+                address,
+                code,
+                param,
+                mode,
+            } => format!(
+                "Parameter {} of instruction {} at {} has invalid mode {}",
+                param, code, address, mode
+            ),
+            InterpreterError::UnexpectedDigit {
+                address,
+                code,
+                param,
+                digit,
+            } => format!(
+                "Unexpected digit {} in instruction {} at {}, for parameter {} which it doesn't have",
+                digit, code, address, param
+            ),
             InterpreterError::InvalidInstruction { address, code } => {
                 format!("Invalid instruction: {}({})", code, address)
             }
+            InterpreterError::InputExhausted { ip } => {
+                format!("Ran out of input at the instruction at {}", ip)
+            }
+            InterpreterError::StepLimitExceeded { ip, steps } => {
+                format!("Still running after {} steps, at {}", steps, ip)
+            }
         };
         write!(f, "Interpreter error: {}", err)
     }
@@ -48,22 +153,34 @@ impl fmt::Display for InterpreterError {
 
 impl Error for InterpreterError {}
 
+/// Checks a parameter of the instruction at `ip` refers to an address that
+/// could exist.
+fn to_address(value: i64, ip: usize, param: usize) -> Result<usize, InterpreterError> {
+    usize::try_from(value).map_err(|_| InterpreterError::NegativeAddress {
+        ip,
+        param,
+        address: value,
+    })
+}
+
 /// Fetch the value at an address in IntCode memory.
 ///
 /// # Examples
 ///
-/// ```
+/// ```ignore
 /// let memory = vec![1,2,3];
 ///
-/// assert_eq!(fetch_address(memory, 0), Ok(1));
-/// assert_eq!(fetch_address(memory, 3), Err(InterpreterError::EndOfMemory));
+/// assert_eq!(fetch_address(&memory, 0), Ok(&1));
+/// assert_eq!(fetch_address(&memory, 3), Err(InterpreterError::EndOfMemory { address: 3 }));
 /// ```
-fn fetch_address(memory: &Vec<i64>, addr: usize) -> Result<&i64, InterpreterError> {
-    memory.get(addr).ok_or(InterpreterError::EndOfMemory)
+fn fetch_address(memory: &[i64], addr: usize) -> Result<&i64, InterpreterError> {
+    memory
+        .get(addr)
+        .ok_or(InterpreterError::EndOfMemory { address: addr })
 }
 
 fn fetch_parameter(
-    memory: &Vec<i64>,
+    memory: &[i64],
     inst_addr: usize,
     parameter: usize,
     mode: ParameterMode,
@@ -71,41 +188,47 @@ fn fetch_parameter(
     let dest = fetch_address(memory, inst_addr + parameter)?;
     match mode {
         ParameterMode::Immediate => Ok(dest),
-        ParameterMode::Position => fetch_address(memory, *dest as usize),
+        // There's no way to move the relative base here, so it stays at 0
+        ParameterMode::Position | ParameterMode::Relative => {
+            fetch_address(memory, to_address(*dest, inst_addr, parameter)?)
+        }
     }
 }
 
 fn fetch_parameter_mut(
-    memory: &mut Vec<i64>,
+    memory: &mut [i64],
     inst_addr: usize,
     parameter: usize,
     mode: ParameterMode,
 ) -> Result<&mut i64, InterpreterError> {
     match mode {
-        ParameterMode::Immediate => memory
-            .get_mut(inst_addr + parameter)
-            .ok_or(InterpreterError::EndOfMemory),
-        ParameterMode::Position => {
-            let dest = *fetch_address(memory, inst_addr + parameter)?;
+        ParameterMode::Immediate => Err(InterpreterError::WriteToImmediate {
+            ip: inst_addr,
+            code: *fetch_address(memory, inst_addr)?,
+            param: parameter,
+        }),
+        ParameterMode::Position | ParameterMode::Relative => {
+            let dest = to_address(*fetch_address(memory, inst_addr + parameter)?, inst_addr, parameter)?;
             memory
-                .get_mut(dest as usize)
-                .ok_or(InterpreterError::EndOfMemory)
+                .get_mut(dest)
+                .ok_or(InterpreterError::EndOfMemory { address: dest })
         }
     }
 }
 
+/// Runs a program to completion in place. This only knows about programs that
+/// don't do any I/O (like day 2's), so input, output, and adjusting the relative
+/// base are treated as invalid instructions, and memory can't grow. See
+/// `Machine` for the full thing.
 pub fn intcode_interpreter(memory: &mut Vec<i64>) -> Result<&mut Vec<i64>, InterpreterError> {
     let mut ip = 0;
     let mut halt = false;
     while !halt {
         //println!("New ip: {}", ip);
-        let inst = fetch_address(memory, ip)?;
-        let inst = Instruction::try_from(*inst as isize).map_err(|_| {
-            InterpreterError::InvalidInstruction {
-                address: ip,
-                code: *inst,
-            }
-        })?;
+        let code = *fetch_address(memory, ip)?;
+        let invalid = InterpreterError::InvalidInstruction { address: ip, code };
+        let inst = Instruction::try_from(code as isize)
+            .map_err(|err| InterpreterError::from_decode(err, ip, code))?;
         //println!("instruction at {} is: {}", ip, inst);
         match inst {
             Instruction::Add(m1, m2, m3) => {
@@ -123,6 +246,37 @@ pub fn intcode_interpreter(memory: &mut Vec<i64>) -> Result<&mut Vec<i64>, Inter
                 *dest = lhs * rhs;
                 ip += 4;
             }
+            Instruction::JumpIfTrue(m1, m2) => {
+                if *fetch_parameter(memory, ip, 1, m1)? != 0 {
+                    ip = to_address(*fetch_parameter(memory, ip, 2, m2)?, ip, 2)?;
+                } else {
+                    ip += 3;
+                }
+            }
+            Instruction::JumpIfFalse(m1, m2) => {
+                if *fetch_parameter(memory, ip, 1, m1)? == 0 {
+                    ip = to_address(*fetch_parameter(memory, ip, 2, m2)?, ip, 2)?;
+                } else {
+                    ip += 3;
+                }
+            }
+            Instruction::LessThan(m1, m2, m3) => {
+                let lhs = *fetch_parameter(memory, ip, 1, m1)?;
+                let rhs = *fetch_parameter(memory, ip, 2, m2)?;
+                let dest = fetch_parameter_mut(memory, ip, 3, m3)?;
+                *dest = (lhs < rhs) as i64;
+                ip += 4;
+            }
+            Instruction::Equals(m1, m2, m3) => {
+                let lhs = *fetch_parameter(memory, ip, 1, m1)?;
+                let rhs = *fetch_parameter(memory, ip, 2, m2)?;
+                let dest = fetch_parameter_mut(memory, ip, 3, m3)?;
+                *dest = (lhs == rhs) as i64;
+                ip += 4;
+            }
+            Instruction::Input(_) | Instruction::Output(_) | Instruction::AdjustRelativeBase(_) => {
+                return Err(invalid)
+            }
             Instruction::Halt => halt = true,
         }
     }
@@ -133,40 +287,82 @@ pub fn intcode_interpreter(memory: &mut Vec<i64>) -> Result<&mut Vec<i64>, Inter
 #[test]
 fn test_fetch_address() {
     assert_eq!(
-        fetch_address(&vec![], 0),
-        Err(InterpreterError::EndOfMemory)
+        fetch_address(&[], 0),
+        Err(InterpreterError::EndOfMemory { address: 0 })
     );
-    assert_eq!(fetch_address(&vec![1, 2, 3], 1), Ok(&2));
+    assert_eq!(fetch_address(&[1, 2, 3], 1), Ok(&2));
     assert_eq!(
-        fetch_address(&vec![4, 5, 6], 3),
-        Err(InterpreterError::EndOfMemory)
+        fetch_address(&[4, 5, 6], 3),
+        Err(InterpreterError::EndOfMemory { address: 3 })
     );
 }
 
 #[test]
 fn test_fetch_parameter() {
     assert_eq!(
-        fetch_parameter(&vec![1, 0, 0, 0, 99], 0, 1, ParameterMode::Position),
+        fetch_parameter(&[1, 0, 0, 0, 99], 0, 1, ParameterMode::Position),
         Ok(&1)
     );
     assert_eq!(
-        fetch_parameter(&vec![1, 2, 0, 0, 99], 0, 1, ParameterMode::Position),
+        fetch_parameter(&[1, 2, 0, 0, 99], 0, 1, ParameterMode::Position),
         Ok(&0)
     );
     assert_eq!(
-        fetch_parameter(&vec![1, 2, 42, 0, 99], 0, 1, ParameterMode::Position),
+        fetch_parameter(&[1, 2, 42, 0, 99], 0, 1, ParameterMode::Position),
         Ok(&42)
     );
     assert_eq!(
-        fetch_parameter(&vec![1, 0, 0, 0, 99], 0, 4, ParameterMode::Position),
-        Err(InterpreterError::EndOfMemory)
+        fetch_parameter(&[1, 0, 0, 0, 99], 0, 4, ParameterMode::Position),
+        Err(InterpreterError::EndOfMemory { address: 99 })
     );
     assert_eq!(
-        fetch_parameter(&vec![1, 0, 0, 0, 99], 0, 4, ParameterMode::Immediate),
+        fetch_parameter(&[1, 0, 0, 0, 99], 0, 4, ParameterMode::Immediate),
         Ok(&99)
     );
     assert_eq!(
-        fetch_parameter(&vec![1, 0, 0, 0, 99], 4, 1, ParameterMode::Immediate),
-        Err(InterpreterError::EndOfMemory)
+        fetch_parameter(&[1, 0, 0, 0, 99], 4, 1, ParameterMode::Immediate),
+        Err(InterpreterError::EndOfMemory { address: 5 })
+    );
+}
+
+#[test]
+fn test_interpreter_comparisons() {
+    assert_eq!(
+        *intcode_interpreter(&mut vec![1108, 4, 4, 5, 99, 7]).unwrap(),
+        vec![1108, 4, 4, 5, 99, 1]
+    );
+    assert_eq!(
+        *intcode_interpreter(&mut vec![7, 5, 6, 5, 99, 7, 3]).unwrap(),
+        vec![7, 5, 6, 5, 99, 0, 3]
+    );
+    // Jumps over the bad opcode at 3
+    assert_eq!(
+        *intcode_interpreter(&mut vec![1105, 1, 4, 42, 99]).unwrap(),
+        vec![1105, 1, 4, 42, 99]
+    );
+    assert_eq!(
+        intcode_interpreter(&mut vec![3, 0, 99]),
+        Err(InterpreterError::InvalidInstruction { address: 0, code: 3 })
+    );
+}
+
+#[test]
+fn test_interpreter_errors() {
+    assert_eq!(
+        intcode_interpreter(&mut vec![1, -1, 0, 0, 99]),
+        Err(InterpreterError::NegativeAddress { ip: 0, param: 1, address: -1 })
+    );
+    assert_eq!(
+        intcode_interpreter(&mut vec![10001, 0, 0, 0, 99]),
+        Err(InterpreterError::WriteToImmediate { ip: 0, code: 10001, param: 3 })
+    );
+    let err = intcode_interpreter(&mut vec![1, 0, 0, 0, 3101, 0, 0, 0, 99]).unwrap_err();
+    assert_eq!(
+        err,
+        InterpreterError::InvalidParameterMode { address: 4, code: 3101, param: 2, mode: 3 }
+    );
+    assert_eq!(
+        err.to_string(),
+        "Interpreter error: Parameter 2 of instruction 3101 at 4 has invalid mode 3"
     );
 }
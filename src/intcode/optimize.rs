@@ -0,0 +1,527 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A static optimizer, rewriting a program in place so it does the same I/O
+//! in fewer steps under the plain interpreter. Nothing moves, since any
+//! value in memory might be an address. The rewrites are:
+//!
+//! - folding: a position mode operand that reads a cell nothing ever writes
+//!   becomes an immediate with that cell's value;
+//! - threading: a jump to a jump that's always taken (or never taken) goes
+//!   straight to where that one ends up;
+//! - removal: cells that aren't reachable code and that nothing reads are
+//!   zeroed, and zeros at the end are dropped.
+//!
+//! All of it relies on knowing every instruction that can run and every cell
+//! that can be read or written, so programs that modify their own code, read
+//! it as data, jump to computed addresses, or use relative mode at all are
+//! left alone. Relative mode is usually just a stack past the end of the
+//! program, though, and `Options::separate_stack` says to assume that.
+//!
+//! `differential_test` runs the original and the optimized program side by
+//! side to check they really do behave the same.
+
+use super::instruction::{Instruction, ParameterMode};
+use super::table::InstructionTable;
+use super::{InterpreterError, Machine, State};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Options {
+    /// Assume relative mode only ever touches memory past the end of the
+    /// program, like a stack.
+    pub separate_stack: bool,
+}
+
+/// A change the optimizer made.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Rewrite {
+    /// Parameter `param` (from 1) of the instruction at `address` reads a
+    /// constant, so it's now the immediate `value`.
+    Folded { address: usize, param: usize, value: i64 },
+    /// The jump at `address` went to `from`, and now goes to `to`.
+    Threaded { address: usize, from: usize, to: usize },
+    /// Cells `start` to `end` inclusive weren't needed.
+    Removed { start: usize, end: usize },
+}
+
+impl fmt::Display for Rewrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rewrite::Folded { address, param, value } => {
+                write!(f, "{}: folded parameter {} to {}", address, param, value)
+            }
+            Rewrite::Threaded { address, from, to } => write!(f, "{}: jump to {} now goes to {}", address, from, to),
+            Rewrite::Removed { start, end } => write!(f, "{}-{}: removed", start, end),
+        }
+    }
+}
+
+/// An optimized program and what was done to it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Optimized {
+    pub program: Vec<i64>,
+    pub rewrites: Vec<Rewrite>,
+    /// Why nothing could be done, if that's the case.
+    pub skipped: Option<String>,
+}
+
+/// A decoded instruction and its parameters.
+#[derive(Debug, Clone)]
+struct Decoded {
+    address: usize,
+    inst: Instruction,
+    params: Vec<i64>,
+}
+
+impl Decoded {
+    /// Decodes the same way a plain machine does, so anything it wouldn't
+    /// run doesn't count as code.
+    fn at(memory: &[i64], address: usize) -> Option<Self> {
+        let code = *memory.get(address)?;
+        let table = InstructionTable::standard();
+        let (info, modes) = table.decode(code).ok()?;
+        let inst = Instruction::from_parts(info.opcode, &modes)?;
+        let params = (1..=inst.operand_count())
+            .map(|i| memory.get(address + i).copied().unwrap_or(0))
+            .collect();
+        Some(Decoded { address, inst, params })
+    }
+
+    fn width(&self) -> usize {
+        self.params.len() + 1
+    }
+
+    /// Which parameter (from 1) gets written to, if any.
+    fn written_param(&self) -> Option<usize> {
+        match self.inst {
+            Instruction::Add(..) | Instruction::Multiply(..) | Instruction::LessThan(..) | Instruction::Equals(..) => {
+                Some(3)
+            }
+            Instruction::Input(..) => Some(1),
+            _ => None,
+        }
+    }
+
+    /// For a jump whose condition is an immediate, whether it's taken.
+    fn constant_condition(&self) -> Option<bool> {
+        let modes = self.inst.modes();
+        match self.inst {
+            Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..) if modes[0] == ParameterMode::Immediate => {
+                let jump_if = matches!(self.inst, Instruction::JumpIfTrue(..));
+                Some((self.params[0] != 0) == jump_if)
+            }
+            _ => None,
+        }
+    }
+
+    /// The target of a jump, if it's an immediate. Negative targets fail at
+    /// run time, so don't count.
+    fn target(&self) -> Option<usize> {
+        match self.inst {
+            Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..)
+                if self.inst.modes()[1] == ParameterMode::Immediate =>
+            {
+                usize::try_from(self.params[1]).ok()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What can run, and what it touches.
+struct Analysis {
+    code: BTreeMap<usize, Decoded>,
+    /// Whether some reachable jump goes somewhere only known at run time.
+    computed_jumps: bool,
+    /// Cells written in position mode.
+    writes: BTreeSet<usize>,
+    /// Cells read in position mode.
+    reads: BTreeSet<usize>,
+    relative_writes: bool,
+    relative_reads: bool,
+}
+
+impl Analysis {
+    /// Follows control flow from address 0. Jumps with constant conditions
+    /// only go the one way.
+    fn new(memory: &[i64]) -> Self {
+        let mut analysis = Analysis {
+            code: BTreeMap::new(),
+            computed_jumps: false,
+            writes: BTreeSet::new(),
+            reads: BTreeSet::new(),
+            relative_writes: false,
+            relative_reads: false,
+        };
+        let mut pending = vec![0];
+        while let Some(address) = pending.pop() {
+            if analysis.code.contains_key(&address) {
+                continue;
+            }
+            // Running something that doesn't decode is an error, which ends
+            // that path
+            let decoded = match Decoded::at(memory, address) {
+                Some(decoded) => decoded,
+                None => continue,
+            };
+            match decoded.inst {
+                Instruction::Halt => {}
+                Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..) => {
+                    let condition = decoded.constant_condition();
+                    if condition != Some(true) {
+                        pending.push(address + decoded.width());
+                    }
+                    if condition != Some(false) {
+                        match decoded.target() {
+                            Some(target) => pending.push(target),
+                            None => analysis.computed_jumps |= decoded.inst.modes()[1] != ParameterMode::Immediate,
+                        }
+                    }
+                }
+                _ => pending.push(address + decoded.width()),
+            }
+            for (i, &mode) in decoded.inst.modes().iter().enumerate() {
+                let written = decoded.written_param() == Some(i + 1);
+                match (mode, written) {
+                    (ParameterMode::Position, _) => {
+                        if let Ok(cell) = usize::try_from(decoded.params[i]) {
+                            if written {
+                                analysis.writes.insert(cell);
+                            } else {
+                                analysis.reads.insert(cell);
+                            }
+                        }
+                    }
+                    (ParameterMode::Relative, true) => analysis.relative_writes = true,
+                    (ParameterMode::Relative, false) => analysis.relative_reads = true,
+                    (ParameterMode::Immediate, _) => {}
+                }
+            }
+            analysis.code.insert(address, decoded);
+        }
+        analysis
+    }
+
+    fn code_cells(&self) -> BTreeSet<usize> {
+        self.code
+            .values()
+            .flat_map(|decoded| decoded.address..decoded.address + decoded.width())
+            .collect()
+    }
+}
+
+/// Optimizes a program. If it can't be done safely, gives back the program
+/// as it was, saying why.
+pub fn optimize(program: &[i64], options: &Options) -> Optimized {
+    let mut memory = program.to_vec();
+    let mut rewrites = Vec::new();
+    let skip = |reason: &str| Optimized {
+        program: program.to_vec(),
+        rewrites: Vec::new(),
+        skipped: Some(reason.to_string()),
+    };
+    let analysis = Analysis::new(&memory);
+    if analysis.computed_jumps {
+        return skip("Jumps to computed addresses");
+    }
+    if analysis.relative_writes && !options.separate_stack {
+        return skip("Writes in relative mode");
+    }
+    // These could read anything, including code that's about to be rewritten
+    if analysis.relative_reads && !options.separate_stack {
+        return skip("Reads in relative mode");
+    }
+    let code_cells = analysis.code_cells();
+    if !analysis.writes.is_disjoint(&code_cells) {
+        return skip("Self-modifying code");
+    }
+    if !analysis.reads.is_disjoint(&code_cells) {
+        return skip("Reads its own code");
+    }
+
+    // Folding. Since no code is ever written or read, rewriting it is safe.
+    // Values come from the original, not anything already folded
+    for decoded in analysis.code.values() {
+        let mut modes = decoded.inst.modes();
+        let mut changed = false;
+        for (i, mode) in modes.iter_mut().enumerate() {
+            let read_only = usize::try_from(decoded.params[i])
+                .map(|cell| !analysis.writes.contains(&cell))
+                .unwrap_or(false);
+            if *mode == ParameterMode::Position && decoded.written_param() != Some(i + 1) && read_only {
+                let value = program.get(decoded.params[i] as usize).copied().unwrap_or(0);
+                memory[decoded.address + i + 1] = value;
+                *mode = ParameterMode::Immediate;
+                changed = true;
+                rewrites.push(Rewrite::Folded {
+                    address: decoded.address,
+                    param: i + 1,
+                    value,
+                });
+            }
+        }
+        if changed {
+            let inst = Instruction::from_parts(decoded.inst.opcode(), &modes).unwrap();
+            memory[decoded.address] = inst.encode();
+        }
+    }
+
+    // Threading, now more conditions are constant
+    let analysis = Analysis::new(&memory);
+    for decoded in analysis.code.values() {
+        let from = match decoded.target() {
+            Some(target) => target,
+            None => continue,
+        };
+        let mut to = from;
+        let mut seen = BTreeSet::new();
+        while seen.insert(to) {
+            let next = match Decoded::at(&memory, to) {
+                Some(next) => next,
+                None => break,
+            };
+            to = match (next.constant_condition(), next.target()) {
+                (Some(true), Some(target)) => target,
+                (Some(false), _) => to + next.width(),
+                _ => break,
+            };
+        }
+        if to != from {
+            memory[decoded.address + 2] = to as i64;
+            rewrites.push(Rewrite::Threaded {
+                address: decoded.address,
+                from,
+                to,
+            });
+        }
+    }
+
+    // Removal, with whatever the threading skips over now unreachable
+    let analysis = Analysis::new(&memory);
+    let keep: BTreeSet<usize> = analysis.code_cells().union(&analysis.reads).copied().collect();
+    let mut address = 0;
+    while address < memory.len() {
+        if keep.contains(&address) || memory[address] == 0 {
+            address += 1;
+            continue;
+        }
+        let start = address;
+        while address < memory.len() && !keep.contains(&address) {
+            memory[address] = 0;
+            address += 1;
+        }
+        rewrites.push(Rewrite::Removed {
+            start,
+            end: address - 1,
+        });
+    }
+    while memory.last() == Some(&0) {
+        memory.pop();
+    }
+    Optimized {
+        program: memory,
+        rewrites,
+        skipped: None,
+    }
+}
+
+/// How a run ended: everything it output, and whether it halted, wanted more
+/// input, or failed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Outcome {
+    pub output: Vec<i64>,
+    pub result: Result<State, InterpreterError>,
+}
+
+/// A run where the optimized program didn't do the same as the original.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Mismatch {
+    pub input: Vec<i64>,
+    pub original: Outcome,
+    pub optimized: Outcome,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Optimizer error: With input {:?}, the original gave {:?} but the optimized program gave {:?}",
+            self.input, self.original, self.optimized
+        )
+    }
+}
+
+impl Error for Mismatch {}
+
+fn run(program: &[i64], input: &[i64], step_limit: u64) -> Outcome {
+    let mut machine = Machine::new(program.to_vec());
+    machine.set_step_limit(Some(step_limit));
+    machine.extend_input(input.iter().copied());
+    let result = machine.run();
+    Outcome {
+        output: machine.take_output(),
+        result,
+    }
+}
+
+/// Runs both programs on each set of input, checking they output the same
+/// things and end the same way. Errors only have to match in kind, since the
+/// optimized program can fail at a different step or address. If either runs
+/// past `step_limit`, that input doesn't prove anything either way.
+pub fn differential_test(
+    original: &[i64],
+    optimized: &[i64],
+    inputs: &[Vec<i64>],
+    step_limit: u64,
+) -> Result<(), Box<Mismatch>> {
+    for input in inputs {
+        let (before, after) = (run(original, input, step_limit), run(optimized, input, step_limit));
+        let limited = |outcome: &Outcome| matches!(outcome.result, Err(InterpreterError::StepLimitExceeded { .. }));
+        if limited(&before) || limited(&after) {
+            continue;
+        }
+        let same_end = match (&before.result, &after.result) {
+            (Ok(before), Ok(after)) => before == after,
+            (Err(before), Err(after)) => std::mem::discriminant(before) == std::mem::discriminant(after),
+            _ => false,
+        };
+        if !same_end || before.output != after.output {
+            return Err(Box::new(Mismatch {
+                input: input.clone(),
+                original: before,
+                optimized: after,
+            }));
+        }
+    }
+    Ok(())
+}
+
+/// Optimizes a program, then checks it with `differential_test`.
+pub fn optimize_checked(
+    program: &[i64],
+    options: &Options,
+    inputs: &[Vec<i64>],
+    step_limit: u64,
+) -> Result<Optimized, Box<Mismatch>> {
+    let optimized = optimize(program, options);
+    differential_test(program, &optimized.program, inputs, step_limit)?;
+    Ok(optimized)
+}
+
+#[cfg(test)]
+fn steps(program: &[i64], input: &[i64]) -> u64 {
+    let mut machine = Machine::new(program.to_vec());
+    machine.run_with_input(input.iter().copied()).unwrap();
+    machine.steps()
+}
+
+#[test]
+fn test_optimize() {
+    use crate::intcode;
+    // Counts down from the input, outputting each number times a constant,
+    // via a chain of jumps and some dead code
+    let program = intcode! {
+        in -> [n];
+        top: mul [n], [scale] -> [out];
+        out [out];
+        add [n], [minus_one] -> [n];
+        jt [n], hop;
+        halt;
+        hop: jt 1, hop2;
+        dead: out 666;
+        hop2: jf [zero], top;
+        halt;
+        n: data 0;
+        out: data 0;
+        scale: data 3;
+        minus_one: data (-1);
+        zero: data 0;
+        unused: data 42, 43
+    };
+    let inputs: Vec<Vec<i64>> = (1..10).map(|n| vec![n]).collect();
+    let optimized = optimize_checked(&program, &Options::default(), &inputs, 10_000).unwrap();
+    assert_eq!(optimized.skipped, None);
+    let rewrites: Vec<String> = optimized.rewrites.iter().map(Rewrite::to_string).collect();
+    assert_eq!(
+        rewrites,
+        vec![
+            "2: folded parameter 2 to 3",
+            "8: folded parameter 2 to -1",
+            "21: folded parameter 1 to 0",
+            "12: jump to 16 now goes to 2",
+            "16: jump to 21 now goes to 2",
+            "16-24: removed",
+            "27-31: removed",
+        ]
+    );
+    // The loop is down from six instructions to four, and the variables at
+    // the end start out as 0, so they don't need to be there either
+    assert_eq!(optimized.program.len(), 16);
+    assert!(steps(&optimized.program, &[5]) < steps(&program, &[5]));
+    assert_eq!(Machine::new(optimized.program).run_with_input(vec![2]), Ok(vec![6, 3]));
+}
+
+#[test]
+fn test_left_alone() {
+    // Day 2's example, which writes over its own code
+    let program = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+    let optimized = optimize(&program, &Options::default());
+    assert_eq!(optimized.program, program);
+    assert_eq!(optimized.skipped.as_deref(), Some("Self-modifying code"));
+    // A jump to wherever the input says
+    let program = vec![3, 7, 5, 7, 7, 99, 99, 0];
+    assert_eq!(
+        optimize(&program, &Options::default()).skipped.as_deref(),
+        Some("Jumps to computed addresses")
+    );
+    // Only a stack in relative mode, which is fine if told so
+    let program = vec![109, 10, 203, 0, 204, 0, 99];
+    assert_eq!(
+        optimize(&program, &Options::default()).skipped.as_deref(),
+        Some("Writes in relative mode")
+    );
+    let options = Options { separate_stack: true };
+    assert_eq!(
+        optimize_checked(&program, &options, &[vec![4]], 100).unwrap().skipped,
+        None
+    );
+    // Outputs 7 and then its second instruction's opcode, which folding the
+    // first one mustn't change
+    let program = vec![4, 5, 4, 0, 99, 7];
+    let optimized = optimize_checked(&program, &Options::default(), &[vec![]], 100).unwrap();
+    assert_eq!(optimized.program, program);
+    assert_eq!(optimized.skipped.as_deref(), Some("Reads its own code"));
+    // Outputs 42 and then its first instruction's parameter, through relative
+    // mode, so folding that one mustn't happen either
+    let program = vec![4, 7, 204, 1, 99, 0, 0, 42];
+    let optimized = optimize_checked(&program, &Options::default(), &[vec![]], 100).unwrap();
+    assert_eq!(optimized.program, program);
+    assert_eq!(optimized.skipped.as_deref(), Some("Reads in relative mode"));
+}
+
+#[test]
+fn test_differential() {
+    let echo = vec![3, 5, 4, 5, 99, 0];
+    let double = vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0];
+    assert_eq!(differential_test(&echo, &echo, &[vec![1], vec![]], 100), Ok(()));
+    let mismatch = differential_test(&echo, &double, &[vec![0], vec![3]], 100).unwrap_err();
+    assert_eq!(mismatch.input, vec![3]);
+    assert_eq!(mismatch.original.output, vec![3]);
+    assert_eq!(mismatch.optimized.output, vec![6]);
+}
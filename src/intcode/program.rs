@@ -0,0 +1,248 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Loading IntCode programs. The usual format is one long line of
+//! comma-separated numbers, but this also copes with whitespace anywhere,
+//! programs split over several lines, a trailing comma, and `#` comments
+//! running to the end of a line.
+//!
+//! Programs can also come wrapped up with some metadata, see `image`. Those
+//! load here too, with their patches applied, as long as they start at 0.
+
+use super::image::Image;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::ops::Deref;
+use std::path::Path;
+use std::str::FromStr;
+
+/// An IntCode program, as loaded, before anything has run it.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Program(Vec<i64>);
+
+#[derive(Debug)]
+/// Why a program couldn't be loaded. Lines and columns count from 1.
+pub enum ProgramError {
+    Io(io::Error),
+    /// Something that isn't a number.
+    BadValue {
+        line: usize,
+        column: usize,
+        token: String,
+    },
+    /// A comma with nothing before it, like `1,,2`. The position is the comma.
+    MissingValue { line: usize, column: usize },
+    /// A `#!` line of an image that doesn't make sense.
+    BadMetadata { line: usize, message: String },
+    /// An image whose program doesn't have the hash it says it should.
+    HashMismatch { expected: u64, actual: u64 },
+    /// An image that starts somewhere other than 0, loaded as a plain program,
+    /// which has nowhere to keep its entry point.
+    EntryNotSupported { entry: usize },
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let err: String = match self {
+            ProgramError::Io(err) => format!("{}", err),
+            ProgramError::BadValue { line, column, token } => {
+                format!("`{}` at line {}, column {} is not a number", token, line, column)
+            }
+            ProgramError::MissingValue { line, column } => {
+                format!("Missing value before the comma at line {}, column {}", line, column)
+            }
+            ProgramError::BadMetadata { line, message } => format!("{} at line {}", message, line),
+            ProgramError::HashMismatch { expected, actual } => format!(
+                "The program's hash is {:016x}, but its metadata says {:016x}",
+                actual, expected
+            ),
+            ProgramError::EntryNotSupported { entry } => format!(
+                "The image starts at {}, but a plain program can only start at 0, so load it as an image",
+                entry
+            ),
+        };
+        write!(f, "Program error: {}", err)
+    }
+}
+
+impl Error for ProgramError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProgramError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ProgramError {
+    fn from(err: io::Error) -> Self {
+        ProgramError::Io(err)
+    }
+}
+
+impl Program {
+    /// Loads a program from a file, like `input/day2.txt`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ProgramError> {
+        Program::from_reader(File::open(path)?)
+    }
+
+    /// Loads a program from anything readable, like `io::stdin()`.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, ProgramError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        text.parse()
+    }
+
+    pub fn into_vec(self) -> Vec<i64> {
+        self.0
+    }
+
+    /// A 64-bit FNV-1a hash of the program written out as usual, so it's
+    /// easy to check with other tools.
+    pub fn hash(&self) -> u64 {
+        crate::fnv1a(self.to_string().as_bytes())
+    }
+}
+
+/// Parses the program itself, ignoring any metadata since it's all in
+/// comments.
+pub(crate) fn parse_words(text: &str) -> Result<Program, ProgramError> {
+    let mut words = Vec::new();
+    // Whether there's been a comma since the last value. Line breaks
+    // separate values too, so this carries across lines.
+    let mut after_comma = false;
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let code = line.split('#').next().unwrap_or("");
+        let mut column = 1;
+        for (piece_index, piece) in code.split(',').enumerate() {
+            if piece_index > 0 {
+                // The comma just before this piece
+                if after_comma || words.is_empty() {
+                    return Err(ProgramError::MissingValue {
+                        line: line_number,
+                        column: column - 1,
+                    });
+                }
+                after_comma = true;
+            }
+            let token = piece.trim();
+            if !token.is_empty() {
+                let start = column + (piece.len() - piece.trim_start().len());
+                let value = token.parse().map_err(|_| ProgramError::BadValue {
+                    line: line_number,
+                    column: start,
+                    token: token.to_string(),
+                })?;
+                words.push(value);
+                after_comma = false;
+            }
+            column += piece.len() + 1;
+        }
+    }
+    Ok(Program(words))
+}
+
+impl FromStr for Program {
+    type Err = ProgramError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if Image::is_image(text) {
+            let image: Image = text.parse()?;
+            if image.metadata.entry != 0 {
+                return Err(ProgramError::EntryNotSupported {
+                    entry: image.metadata.entry,
+                });
+            }
+            Ok(image.patched())
+        } else {
+            parse_words(text)
+        }
+    }
+}
+
+/// Writes the program back out in the usual one line, comma-separated form.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, word) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", word)?;
+        }
+        Ok(())
+    }
+}
+
+impl Deref for Program {
+    type Target = [i64];
+
+    fn deref(&self) -> &[i64] {
+        &self.0
+    }
+}
+
+impl From<Vec<i64>> for Program {
+    fn from(words: Vec<i64>) -> Self {
+        Program(words)
+    }
+}
+
+impl From<Program> for Vec<i64> {
+    fn from(program: Program) -> Self {
+        program.0
+    }
+}
+
+#[test]
+fn test_parse() {
+    let parse = |text: &str| text.parse::<Program>().unwrap().into_vec();
+    assert_eq!(parse("1,0,0,0,99\n"), vec![1, 0, 0, 0, 99]);
+    assert_eq!(parse(" 1, -2 ,3,\r\n"), vec![1, -2, 3]);
+    assert_eq!(parse("1,0,\n0,0 # add\n99\n\n"), vec![1, 0, 0, 0, 99]);
+    assert_eq!(parse("# nothing here\n"), Vec::<i64>::new());
+    let program: Program = "1, 2,3\n4".parse().unwrap();
+    assert_eq!(program.to_string(), "1,2,3,4");
+    assert_eq!(program.to_string().parse::<Program>().unwrap(), program);
+}
+
+#[test]
+fn test_parse_errors() {
+    let err = |text: &str| text.parse::<Program>().unwrap_err().to_string();
+    assert_eq!(
+        err("1,2,\n3, x4 ,5"),
+        "Program error: `x4` at line 2, column 4 is not a number"
+    );
+    assert_eq!(
+        err("1,2,,3"),
+        "Program error: Missing value before the comma at line 1, column 5"
+    );
+    assert!(matches!(
+        ",1".parse::<Program>(),
+        Err(ProgramError::MissingValue { line: 1, column: 1 })
+    ));
+    assert!(matches!(
+        Program::from_file("input/no such day.txt"),
+        Err(ProgramError::Io(_))
+    ));
+}
+
+#[test]
+fn test_from_reader() {
+    let program = Program::from_reader("3,0,\n4,0,99\n".as_bytes()).unwrap();
+    assert_eq!(&program[..], &[3, 0, 4, 0, 99]);
+}
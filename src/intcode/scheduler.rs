@@ -0,0 +1,305 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Runs a bunch of machines together, like day 7's amplifiers, with the
+//! output of some fed into the input of others. Machines take turns, and a
+//! policy decides who goes next and for how long.
+
+use super::{InterpreterError, Machine, State};
+use std::error::Error;
+use std::fmt;
+
+/// Decides which machine runs next.
+pub trait Policy {
+    /// Picks one of the machines that can run, and how many instructions it
+    /// gets, or `None` to let it run until it halts or needs input.
+    /// `runnable` is never empty, and picking anything not in it is an error.
+    /// A turn of 0 instructions still runs one, so things always move along.
+    fn next(&mut self, runnable: &[usize]) -> (usize, Option<u64>);
+}
+
+/// Each machine gets a turn in order.
+#[derive(Debug, Clone)]
+pub struct RoundRobin {
+    quantum: Option<u64>,
+    last: Option<usize>,
+}
+
+impl RoundRobin {
+    /// Turns of up to `quantum` instructions each. A quantum of 0 would
+    /// never get anywhere, so it's treated as 1.
+    pub fn new(quantum: u64) -> Self {
+        RoundRobin {
+            quantum: Some(quantum.max(1)),
+            last: None,
+        }
+    }
+
+    /// Turns that last until the machine halts or needs input.
+    pub fn until_blocked() -> Self {
+        RoundRobin {
+            quantum: None,
+            last: None,
+        }
+    }
+}
+
+impl Policy for RoundRobin {
+    fn next(&mut self, runnable: &[usize]) -> (usize, Option<u64>) {
+        let next = match self.last {
+            Some(last) => runnable.iter().copied().find(|&id| id > last).unwrap_or(runnable[0]),
+            None => runnable[0],
+        };
+        self.last = Some(next);
+        (next, self.quantum)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// Why the machines couldn't all run to the end.
+pub enum SchedulerError {
+    /// One of the machines failed.
+    Interpreter { machine: usize, error: InterpreterError },
+    /// Every machine still going is waiting for input nobody's going to
+    /// send.
+    Deadlock { waiting: Vec<usize> },
+    /// The policy picked a machine that couldn't run.
+    NotRunnable { machine: usize, runnable: Vec<usize> },
+}
+
+impl fmt::Display for SchedulerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let err: String = match self {
+            SchedulerError::Interpreter { machine, error } => format!("Machine {}: {}", machine, error),
+            SchedulerError::Deadlock { waiting } => {
+                format!("Deadlock, with machines {:?} all waiting for input", waiting)
+            }
+            SchedulerError::NotRunnable { machine, runnable } => format!(
+                "The policy picked machine {}, but only {:?} can run",
+                machine, runnable
+            ),
+        };
+        write!(f, "Scheduler error: {}", err)
+    }
+}
+
+impl Error for SchedulerError {}
+
+/// Some machines, what's connected to what, and the policy for running them.
+pub struct Scheduler<P: Policy = RoundRobin> {
+    machines: Vec<Machine>,
+    /// Where each machine's output goes.
+    links: Vec<Vec<usize>>,
+    last_output: Vec<Option<i64>>,
+    policy: P,
+}
+
+impl<P: Policy> Scheduler<P> {
+    pub fn new(policy: P) -> Self {
+        Scheduler {
+            machines: Vec::new(),
+            links: Vec::new(),
+            last_output: Vec::new(),
+            policy,
+        }
+    }
+
+    /// Adds a machine, returning its id. Ids count up from 0.
+    pub fn add(&mut self, machine: Machine) -> usize {
+        self.machines.push(machine);
+        self.links.push(Vec::new());
+        self.last_output.push(None);
+        self.machines.len() - 1
+    }
+
+    /// Sends everything `from` outputs to `to` as input. If there's more than
+    /// one place to send it, they all get a copy. Machines that aren't
+    /// connected to anything keep their output.
+    pub fn connect(&mut self, from: usize, to: usize) {
+        self.links[from].push(to);
+    }
+
+    pub fn machine(&self, id: usize) -> &Machine {
+        &self.machines[id]
+    }
+
+    pub fn machine_mut(&mut self, id: usize) -> &mut Machine {
+        &mut self.machines[id]
+    }
+
+    /// The last thing a machine output, even if it's been sent on since.
+    pub fn last_output(&self, id: usize) -> Option<i64> {
+        self.last_output[id]
+    }
+
+    /// The machines that can do something: not halted, and not waiting for
+    /// input that isn't there.
+    fn runnable(&self) -> Vec<usize> {
+        (0..self.machines.len())
+            .filter(|&id| match self.machines[id].state() {
+                State::Running => true,
+                State::WaitingForInput => self.machines[id].input_len() > 0,
+                State::Halted => false,
+            })
+            .collect()
+    }
+
+    /// Gives one machine a turn, then sends on what it output. Returns
+    /// `false` once every machine has halted.
+    pub fn step(&mut self) -> Result<bool, SchedulerError> {
+        let runnable = self.runnable();
+        if runnable.is_empty() {
+            let waiting: Vec<usize> = (0..self.machines.len())
+                .filter(|&id| self.machines[id].state() != State::Halted)
+                .collect();
+            return if waiting.is_empty() {
+                Ok(false)
+            } else {
+                Err(SchedulerError::Deadlock { waiting })
+            };
+        }
+        let (id, quantum) = self.policy.next(&runnable);
+        if !runnable.contains(&id) {
+            return Err(SchedulerError::NotRunnable { machine: id, runnable });
+        }
+        let machine = &mut self.machines[id];
+        let result = match quantum {
+            Some(quantum) => (0..quantum.max(1))
+                .map(|_| machine.step())
+                .find(|state| *state != Ok(State::Running))
+                .unwrap_or(Ok(State::Running)),
+            None => machine.run(),
+        };
+        result.map_err(|error| SchedulerError::Interpreter { machine: id, error })?;
+        if let Some(last) = self.machines[id].last_output() {
+            self.last_output[id] = Some(last);
+        }
+        if !self.links[id].is_empty() {
+            let output = self.machines[id].take_output();
+            for &to in &self.links[id] {
+                self.machines[to].extend_input(output.iter().copied());
+            }
+        }
+        Ok(true)
+    }
+
+    /// Runs until every machine has halted.
+    pub fn run(&mut self) -> Result<(), SchedulerError> {
+        while self.step()? {}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn amplifiers<P: Policy>(program: &[i64], phases: &[i64], feedback: bool, policy: P) -> Result<i64, SchedulerError> {
+    let mut scheduler = Scheduler::new(policy);
+    for &phase in phases {
+        let mut machine = Machine::new(program.to_vec());
+        machine.push_input(phase);
+        scheduler.add(machine);
+    }
+    for id in 1..phases.len() {
+        scheduler.connect(id - 1, id);
+    }
+    if feedback {
+        scheduler.connect(phases.len() - 1, 0);
+    }
+    scheduler.machine_mut(0).push_input(0);
+    scheduler.run()?;
+    Ok(scheduler.last_output(phases.len() - 1).unwrap())
+}
+
+#[test]
+fn test_amplifiers() {
+    // Day 7's examples
+    let program = [3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0];
+    assert_eq!(amplifiers(&program, &[4, 3, 2, 1, 0], false, RoundRobin::until_blocked()), Ok(43210));
+    let program = [
+        3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1, 28, 1005, 28, 6, 99, 0, 0,
+        5,
+    ];
+    for policy in [RoundRobin::until_blocked(), RoundRobin::new(0), RoundRobin::new(1), RoundRobin::new(7)] {
+        assert_eq!(amplifiers(&program, &[9, 8, 7, 6, 5], true, policy), Ok(139629729));
+    }
+}
+
+#[test]
+fn test_custom_policy() {
+    // Always runs the lowest numbered machine it can
+    struct Lowest(Vec<usize>);
+    impl Policy for Lowest {
+        fn next(&mut self, runnable: &[usize]) -> (usize, Option<u64>) {
+            self.0.push(runnable[0]);
+            (runnable[0], Some(1))
+        }
+    }
+    let mut scheduler = Scheduler::new(Lowest(Vec::new()));
+    let echo = vec![3, 5, 4, 5, 99, 0];
+    let first = scheduler.add(Machine::new(echo.clone()));
+    let second = scheduler.add(Machine::new(echo));
+    scheduler.connect(first, second);
+    scheduler.machine_mut(first).push_input(7);
+    scheduler.run().unwrap();
+    assert_eq!(scheduler.machine_mut(second).take_output(), vec![7]);
+    assert_eq!(scheduler.policy.0, vec![0, 0, 0, 1, 1, 1]);
+}
+
+#[test]
+fn test_deadlock() {
+    // Each wants input from the other first
+    let echo = vec![3, 5, 4, 5, 99, 0];
+    let mut scheduler = Scheduler::new(RoundRobin::new(5));
+    let first = scheduler.add(Machine::new(echo.clone()));
+    let second = scheduler.add(Machine::new(echo.clone()));
+    let third = scheduler.add(Machine::new(echo));
+    scheduler.connect(first, second);
+    scheduler.connect(second, first);
+    scheduler.machine_mut(third).push_input(1);
+    assert_eq!(scheduler.run(), Err(SchedulerError::Deadlock { waiting: vec![0, 1] }));
+    assert_eq!(scheduler.machine(third).state(), State::Halted);
+}
+
+#[test]
+fn test_bad_policy() {
+    // Picks whatever it's told to, runnable or not, for no instructions
+    struct Fixed(usize);
+    impl Policy for Fixed {
+        fn next(&mut self, _runnable: &[usize]) -> (usize, Option<u64>) {
+            (self.0, Some(0))
+        }
+    }
+    let echo = vec![3, 5, 4, 5, 99, 0];
+    // A turn of 0 still runs an instruction, so this gets to the end
+    let mut scheduler = Scheduler::new(Fixed(0));
+    scheduler.add(Machine::new(echo.clone()));
+    scheduler.machine_mut(0).push_input(7);
+    scheduler.run().unwrap();
+    assert_eq!(scheduler.machine_mut(0).take_output(), vec![7]);
+    // The second machine is stuck waiting for input, and 5 doesn't exist
+    for id in [1, 5] {
+        let mut scheduler = Scheduler::new(Fixed(id));
+        scheduler.add(Machine::new(echo.clone()));
+        scheduler.add(Machine::new(echo.clone()));
+        scheduler.machine_mut(0).push_input(7);
+        assert_eq!(scheduler.machine_mut(1).run(), Ok(State::WaitingForInput));
+        assert_eq!(
+            scheduler.step(),
+            Err(SchedulerError::NotRunnable {
+                machine: id,
+                runnable: vec![0]
+            })
+        );
+    }
+}
@@ -0,0 +1,319 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Brute force searches for patches to a program, like day 2's noun and verb.
+//! Every combination of patched values gets its own run, spread across
+//! threads.
+
+use super::memory::PagedMemory;
+use super::table::InstructionTable;
+use super::Machine;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Options {
+    /// How many steps each run gets before it's given up on, since a bad
+    /// patch can easily send a program round a loop forever.
+    pub step_limit: u64,
+}
+
+impl Options {
+    pub const DEFAULT_STEP_LIMIT: u64 = 1_000_000;
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            step_limit: Options::DEFAULT_STEP_LIMIT,
+        }
+    }
+}
+
+/// An address to patch, and the values to try there.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Patch {
+    pub address: usize,
+    pub values: RangeInclusive<i64>,
+}
+
+impl Patch {
+    pub fn new(address: usize, values: RangeInclusive<i64>) -> Self {
+        Patch { address, values }
+    }
+
+    /// How many values there are to try, or `None` if that doesn't fit in a
+    /// `usize`.
+    fn len(&self) -> Option<usize> {
+        if self.values.is_empty() {
+            Some(0)
+        } else {
+            let span = self.values.end().checked_sub(*self.values.start())?;
+            usize::try_from(span).ok()?.checked_add(1)
+        }
+    }
+}
+
+/// Counts of what happened over a search.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct SearchStats {
+    /// How many combinations were actually run.
+    pub runs: usize,
+    /// How many runs ended in an interpreter error, and so were never checked.
+    /// That includes going past the step limit.
+    pub errors: usize,
+    pub matches: usize,
+}
+
+impl SearchStats {
+    fn add(&mut self, other: &SearchStats) {
+        self.runs += other.runs;
+        self.errors += other.errors;
+        self.matches += other.matches;
+    }
+}
+
+/// Every combination that matched, as the values patched in, in the same
+/// order as the patches.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SearchResults {
+    pub matches: Vec<Vec<i64>>,
+    pub stats: SearchStats,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SearchError {
+    /// There are more combinations than can be counted, so there's no way
+    /// they'd all get run anyway.
+    TooManyCombinations,
+    /// Nothing matched. Holds the stats anyway, since it's useful to know
+    /// whether that's because everything errored.
+    NoMatch(SearchStats),
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchError::TooManyCombinations => write!(f, "Search error: Too many combinations to try"),
+            SearchError::NoMatch(stats) => write!(
+                f,
+                "Search error: No inputs matched ({} runs, {} errors)",
+                stats.runs, stats.errors
+            ),
+        }
+    }
+}
+
+impl Error for SearchError {}
+
+/// Counts the combinations, if there aren't too many.
+fn count(patches: &[Patch]) -> Result<usize, SearchError> {
+    patches.iter().try_fold(1usize, |total, patch| {
+        patch
+            .len()
+            .and_then(|len| total.checked_mul(len))
+            .ok_or(SearchError::TooManyCombinations)
+    })
+}
+
+/// Works out the patched values for the nth combination. The last patch
+/// changes fastest, like nested loops would. Only call this once `count` has
+/// checked the lengths.
+fn combination(patches: &[Patch], mut index: usize) -> Vec<i64> {
+    let mut values = vec![0; patches.len()];
+    for (i, patch) in patches.iter().enumerate().rev() {
+        let len = patch.len().unwrap();
+        values[i] = patch.values.start() + (index % len) as i64;
+        index /= len;
+    }
+    values
+}
+
+/// The matches, with the index of the combination each one came from, and
+/// the stats.
+type Found = (Vec<(usize, Vec<i64>)>, SearchStats);
+
+/// Runs the combinations, with each of the worker threads taking every nth
+/// one. If only the first match is wanted, workers stop once they're past the
+/// earliest match anyone has found so far.
+fn run_search<F>(
+    program: &[i64],
+    patches: &[Patch],
+    options: &Options,
+    predicate: F,
+    first_only: bool,
+) -> Result<Found, SearchError>
+where
+    F: Fn(&Machine) -> bool + Sync,
+{
+    let total = count(patches)?;
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(total.max(1));
+    let best = AtomicUsize::new(usize::MAX);
+    let found = Mutex::new((Vec::new(), SearchStats::default()));
+    // Paged memory means each run only copies the pages it writes to
+    let mut template = Machine::with_memory(PagedMemory::from(program.to_vec()), InstructionTable::standard());
+    template.set_step_limit(Some(options.step_limit));
+    thread::scope(|scope| {
+        for worker in 0..workers {
+            let (best, found, template, predicate) = (&best, &found, &template, &predicate);
+            scope.spawn(move || {
+                let mut matches = Vec::new();
+                let mut stats = SearchStats::default();
+                for index in (worker..total).step_by(workers) {
+                    if first_only && index > best.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let values = combination(patches, index);
+                    let mut machine = template.clone();
+                    for (patch, &value) in patches.iter().zip(values.iter()) {
+                        machine.poke(patch.address, value);
+                    }
+                    stats.runs += 1;
+                    if machine.run().is_err() {
+                        stats.errors += 1;
+                    } else if predicate(&machine) {
+                        stats.matches += 1;
+                        matches.push((index, values));
+                        if first_only {
+                            best.fetch_min(index, Ordering::Relaxed);
+                            break;
+                        }
+                    }
+                }
+                let mut found = found.lock().unwrap();
+                found.0.extend(matches);
+                found.1.add(&stats);
+            });
+        }
+    });
+    let (mut matches, stats) = found.into_inner().unwrap();
+    matches.sort();
+    Ok((matches, stats))
+}
+
+/// Tries every combination of patched values, and returns all of the ones
+/// where the predicate holds for the machine once it's stopped. Runs that
+/// fail with an interpreter error, or take more than `options.step_limit`
+/// steps, are counted, but otherwise skipped.
+///
+/// The machine gets no input, so a program that wants some is checked as it
+/// is when it stops to wait.
+pub fn search_all<F>(
+    program: &[i64],
+    patches: &[Patch],
+    options: &Options,
+    predicate: F,
+) -> Result<SearchResults, SearchError>
+where
+    F: Fn(&Machine) -> bool + Sync,
+{
+    let (matches, stats) = run_search(program, patches, options, predicate, false)?;
+    Ok(SearchResults {
+        matches: matches.into_iter().map(|(_, values)| values).collect(),
+        stats,
+    })
+}
+
+/// Like `search_all`, but stops as soon as it's sure which combination is the
+/// first that matches, in the same order nested loops over the patches would
+/// go.
+pub fn search_first<F>(
+    program: &[i64],
+    patches: &[Patch],
+    options: &Options,
+    predicate: F,
+) -> Result<(Vec<i64>, SearchStats), SearchError>
+where
+    F: Fn(&Machine) -> bool + Sync,
+{
+    let (matches, stats) = run_search(program, patches, options, predicate, true)?;
+    match matches.into_iter().next() {
+        Some((_, values)) => Ok((values, stats)),
+        None => Err(SearchError::NoMatch(stats)),
+    }
+}
+
+#[test]
+fn test_combination() {
+    let patches = vec![Patch::new(1, 0..=2), Patch::new(2, 5..=6)];
+    assert_eq!(combination(&patches, 0), vec![0, 5]);
+    assert_eq!(combination(&patches, 1), vec![0, 6]);
+    assert_eq!(combination(&patches, 5), vec![2, 6]);
+}
+
+#[test]
+fn test_search() {
+    // Adds whatever the noun and verb point at, so 100 is 99 + 1, where the
+    // 1 is either the opcode or the noun itself
+    let program = vec![1, 0, 0, 0, 99];
+    let patches = vec![Patch::new(1, 0..=4), Patch::new(2, 0..=4)];
+    let options = Options::default();
+    let results = search_all(&program, &patches, &options, |m| m.peek(0) == 100).unwrap();
+    assert_eq!(results.matches, vec![vec![0, 4], vec![1, 4], vec![4, 0]]);
+    assert_eq!(results.stats, SearchStats { runs: 25, errors: 0, matches: 3 });
+    let (first, _) = search_first(&program, &patches, &options, |m| m.peek(0) == 100).unwrap();
+    assert_eq!(first, vec![0, 4]);
+    assert_eq!(
+        search_first(&program, &patches, &options, |m| m.peek(0) == 1000),
+        Err(SearchError::NoMatch(SearchStats { runs: 25, errors: 0, matches: 0 }))
+    );
+}
+
+#[test]
+fn test_search_errors() {
+    // Opcode 0 isn't an instruction, so a third of these fail
+    let program = vec![1, 0, 0, 0, 99];
+    let results = search_all(&program, &[Patch::new(0, 0..=2)], &Options::default(), |_| true).unwrap();
+    assert_eq!(results.matches, vec![vec![1], vec![2]]);
+    assert_eq!(results.stats, SearchStats { runs: 3, errors: 1, matches: 2 });
+}
+
+#[test]
+fn test_search_step_limit() {
+    // With a noun of 1 this jumps back to itself forever
+    let program = vec![1105, 0, 0, 99];
+    let options = Options { step_limit: 100 };
+    let (first, _) = search_first(&program, &[Patch::new(1, 0..=1)], &options, |_| true).unwrap();
+    assert_eq!(first, vec![0]);
+    let results = search_all(&program, &[Patch::new(1, 0..=1)], &options, |_| true).unwrap();
+    assert_eq!(results.matches, vec![vec![0]]);
+    assert_eq!(results.stats, SearchStats { runs: 2, errors: 1, matches: 1 });
+}
+
+#[test]
+fn test_too_many_combinations() {
+    let program = vec![1, 0, 0, 0, 99];
+    let options = Options::default();
+    // The span of this one doesn't even fit in an i64
+    assert_eq!(Patch::new(1, i64::MIN..=i64::MAX).len(), None);
+    assert_eq!(
+        search_all(&program, &[Patch::new(1, i64::MIN..=i64::MAX)], &options, |_| true),
+        Err(SearchError::TooManyCombinations)
+    );
+    // Each of these is fine, but all of them together overflow
+    let patches = vec![Patch::new(1, 0..=i64::from(u32::MAX)); 3];
+    assert_eq!(
+        search_first(&program, &patches, &options, |_| true),
+        Err(SearchError::TooManyCombinations)
+    );
+    // Nothing to try isn't an overflow, just no runs
+    let results = search_all(&program, &[Patch::new(1, RangeInclusive::new(1, 0))], &options, |_| true).unwrap();
+    assert_eq!(results.stats, SearchStats::default());
+}
@@ -0,0 +1,223 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Recording and replaying the input to a machine, so an interactive run
+//! can be reproduced exactly. Machines are deterministic, so feeding the same
+//! input back in gets the same state, and the step each value was consumed
+//! at is kept to check that it really does.
+//!
+//! Sessions are saved as text, one input a line, as the step and the value:
+//!
+//! ```text
+//! # intcode session
+//! 3 -1
+//! 57 0
+//! ```
+
+use super::{InterpreterError, Machine, State};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, prelude::*, BufReader, BufWriter};
+use std::path::Path;
+
+/// One value of input, and the step (counting from 1) that consumed it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct InputEvent {
+    pub step: u64,
+    pub value: i64,
+}
+
+/// All the input a machine consumed, in order.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Session {
+    pub inputs: Vec<InputEvent>,
+}
+
+#[derive(Debug)]
+/// Reasons a session couldn't be loaded or replayed.
+pub enum SessionError {
+    Io(io::Error),
+    /// A line of a session file (counting from 1) that doesn't make sense.
+    BadLine(usize),
+    Interpreter(InterpreterError),
+    /// The machine wanted input at a different step than it did when the
+    /// session was recorded. Probably not the same program.
+    Diverged {
+        index: usize,
+        expected_step: u64,
+        actual_step: u64,
+    },
+    /// The machine halted with input left over.
+    Unconsumed { consumed: usize, remaining: usize },
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let err: String = match self {
+            SessionError::Io(err) => format!("{}", err),
+            SessionError::BadLine(line) => format!("Can't read line {} of the session", line),
+            SessionError::Interpreter(err) => format!("{}", err),
+            SessionError::Diverged {
+                index,
+                expected_step,
+                actual_step,
+            } => format!(
+                "Input {} was consumed at step {}, but the replay wanted it at step {}",
+                index, expected_step, actual_step
+            ),
+            SessionError::Unconsumed { consumed, remaining } => format!(
+                "Machine halted after {} inputs with {} left over",
+                consumed, remaining
+            ),
+        };
+        write!(f, "Session error: {}", err)
+    }
+}
+
+impl Error for SessionError {}
+
+impl From<io::Error> for SessionError {
+    fn from(err: io::Error) -> Self {
+        SessionError::Io(err)
+    }
+}
+
+impl From<InterpreterError> for SessionError {
+    fn from(err: InterpreterError) -> Self {
+        SessionError::Interpreter(err)
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, SessionError> {
+        let mut session = Session::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let event = match fields[..] {
+                [step, value] => match (step.parse(), value.parse()) {
+                    (Ok(step), Ok(value)) => Some(InputEvent { step, value }),
+                    _ => None,
+                },
+                _ => None,
+            };
+            match event {
+                Some(event) => session.inputs.push(event),
+                _ => return Err(SessionError::BadLine(index + 1)),
+            }
+        }
+        Ok(session)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "# intcode session")?;
+        for event in &self.inputs {
+            writeln!(writer, "{} {}", event.step, event.value)?;
+        }
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SessionError> {
+        Session::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Feeds the recorded input to a machine, one value at a time as it
+    /// asks, checking each is wanted at the same step as before. Once it's
+    /// all been consumed, the machine runs until it halts or wants more.
+    ///
+    /// The machine should be in the same state as when recording started.
+    pub fn replay(&self, machine: &mut Machine) -> Result<State, SessionError> {
+        for (index, event) in self.inputs.iter().enumerate() {
+            if machine.run()? == State::Halted {
+                return Err(SessionError::Unconsumed {
+                    consumed: index,
+                    remaining: self.inputs.len() - index,
+                });
+            }
+            // The input instruction will be the next step
+            let actual_step = machine.steps() + 1;
+            if actual_step != event.step {
+                return Err(SessionError::Diverged {
+                    index,
+                    expected_step: event.step,
+                    actual_step,
+                });
+            }
+            machine.push_input(event.value);
+        }
+        Ok(machine.run()?)
+    }
+}
+
+#[cfg(test)]
+const ECHO: [i64; 10] = [3, 9, 4, 9, 1005, 9, 0, 99, 0, 0];
+
+#[test]
+fn test_record_and_replay() {
+    // Echoes input until it gets a zero
+    let mut machine = Machine::new(ECHO.to_vec());
+    machine.start_recording();
+    for value in [4, -2, 0] {
+        machine.push_input(value);
+        machine.run().unwrap();
+    }
+    let session = machine.take_recording().unwrap();
+    assert_eq!(
+        session.inputs,
+        vec![
+            InputEvent { step: 1, value: 4 },
+            InputEvent { step: 4, value: -2 },
+            InputEvent { step: 7, value: 0 },
+        ]
+    );
+    let mut text = Vec::new();
+    session.write(&mut text).unwrap();
+    assert_eq!(String::from_utf8(text.clone()).unwrap(), "# intcode session\n1 4\n4 -2\n7 0\n");
+    let session = Session::read(&text[..]).unwrap();
+    let mut replayed = Machine::new(ECHO.to_vec());
+    assert_eq!(session.replay(&mut replayed).unwrap(), State::Halted);
+    assert_eq!(replayed.take_output(), machine.take_output());
+    assert_eq!(replayed.steps(), machine.steps());
+}
+
+#[test]
+fn test_replay_errors() {
+    let session = Session::read("1 4\n5 0\n".as_bytes()).unwrap();
+    assert!(matches!(
+        session.replay(&mut Machine::new(ECHO.to_vec())),
+        Err(SessionError::Diverged { index: 1, expected_step: 5, actual_step: 4 })
+    ));
+    let session = Session::read("1 0\n4 1\n".as_bytes()).unwrap();
+    assert!(matches!(
+        session.replay(&mut Machine::new(ECHO.to_vec())),
+        Err(SessionError::Unconsumed { consumed: 1, remaining: 1 })
+    ));
+    assert!(matches!(Session::read("1 2 3".as_bytes()), Err(SessionError::BadLine(1))));
+}
@@ -0,0 +1,292 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The table of opcodes the machine, disassembler, and assembler all work
+//! from. The standard instructions are always there; custom ones can be
+//! registered on top, with a handler the machine calls to execute them.
+
+use super::instruction::{check_unexpected_digits, DecodeError, ParameterMode};
+use super::{InterpreterError, Machine};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, OnceLock};
+
+/// A decoded parameter: the raw parameter word, how to interpret it, and
+/// where in memory the word itself is.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Operand {
+    pub address: usize,
+    pub value: i64,
+    pub mode: ParameterMode,
+}
+
+/// What a custom instruction wants to happen once it's done.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Flow {
+    /// Carry on with the instruction after this one.
+    Next,
+    Jump(usize),
+    Halt,
+}
+
+/// Executes a custom instruction, given its operands.
+pub type Handler = Arc<dyn Fn(&mut Machine, &[Operand]) -> Result<Flow, InterpreterError> + Send + Sync>;
+
+/// Everything known about an opcode.
+#[derive(Clone)]
+pub struct OpcodeInfo {
+    pub opcode: i64,
+    pub mnemonic: String,
+    pub params: usize,
+    /// Only custom instructions have a handler; the machine knows how to run
+    /// the standard ones itself.
+    pub handler: Option<Handler>,
+}
+
+impl fmt::Debug for OpcodeInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpcodeInfo")
+            .field("opcode", &self.opcode)
+            .field("mnemonic", &self.mnemonic)
+            .field("params", &self.params)
+            .field("custom", &self.handler.is_some())
+            .finish()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+/// Reasons a custom opcode can't be registered.
+pub enum TableError {
+    /// Opcodes have to fit in the last two digits of an instruction.
+    OutOfRange(i64),
+    /// Something already has that opcode.
+    OpcodeTaken(i64),
+    /// Something already has that mnemonic.
+    MnemonicTaken(String),
+    /// More parameters than there's room for modes of in an instruction word.
+    TooManyParams(usize),
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::OutOfRange(opcode) => write!(f, "Opcode {} is not in 1..=99", opcode),
+            TableError::OpcodeTaken(opcode) => write!(f, "Opcode {} is already registered", opcode),
+            TableError::MnemonicTaken(name) => write!(f, "Mnemonic {} is already registered", name),
+            TableError::TooManyParams(params) => {
+                write!(f, "{} parameters is more than the {} that fit", params, MAX_PARAMS)
+            }
+        }
+    }
+}
+
+impl Error for TableError {}
+
+/// The most parameters an instruction can have, since that's how many mode
+/// digits fit in an `i64` after the opcode.
+pub const MAX_PARAMS: usize = 17;
+
+/// The modes of an instruction's parameters. Machines decode every step, so
+/// this keeps them in place rather than in a `Vec`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Modes {
+    modes: [ParameterMode; MAX_PARAMS],
+    len: usize,
+}
+
+impl Deref for Modes {
+    type Target = [ParameterMode];
+
+    fn deref(&self) -> &[ParameterMode] {
+        &self.modes[..self.len]
+    }
+}
+
+/// The opcodes a machine understands.
+#[derive(Debug, Clone)]
+pub struct InstructionTable {
+    opcodes: BTreeMap<i64, OpcodeInfo>,
+}
+
+const STANDARD: [(i64, &str, usize); 10] = [
+    (1, "add", 3),
+    (2, "mul", 3),
+    (3, "in", 1),
+    (4, "out", 1),
+    (5, "jt", 2),
+    (6, "jf", 2),
+    (7, "lt", 3),
+    (8, "eq", 3),
+    (9, "arb", 1),
+    (99, "hlt", 0),
+];
+
+impl InstructionTable {
+    /// A table with just the standard instructions.
+    pub fn new() -> Self {
+        let opcodes = STANDARD
+            .iter()
+            .map(|&(opcode, mnemonic, params)| {
+                let info = OpcodeInfo {
+                    opcode,
+                    mnemonic: mnemonic.to_string(),
+                    params,
+                    handler: None,
+                };
+                (opcode, info)
+            })
+            .collect();
+        InstructionTable { opcodes }
+    }
+
+    /// A shared copy of the standard table, so every machine doesn't need
+    /// its own.
+    pub fn standard() -> Arc<Self> {
+        static STANDARD_TABLE: OnceLock<Arc<InstructionTable>> = OnceLock::new();
+        STANDARD_TABLE.get_or_init(|| Arc::new(InstructionTable::new())).clone()
+    }
+
+    /// Adds a custom instruction.
+    pub fn register<F>(&mut self, opcode: i64, mnemonic: &str, params: usize, handler: F) -> Result<(), TableError>
+    where
+        F: Fn(&mut Machine, &[Operand]) -> Result<Flow, InterpreterError> + Send + Sync + 'static,
+    {
+        if !(1..=99).contains(&opcode) {
+            return Err(TableError::OutOfRange(opcode));
+        }
+        if self.opcodes.contains_key(&opcode) {
+            return Err(TableError::OpcodeTaken(opcode));
+        }
+        if self.by_mnemonic(mnemonic).is_some() {
+            return Err(TableError::MnemonicTaken(mnemonic.to_string()));
+        }
+        if params > MAX_PARAMS {
+            return Err(TableError::TooManyParams(params));
+        }
+        let info = OpcodeInfo {
+            opcode,
+            mnemonic: mnemonic.to_string(),
+            params,
+            handler: Some(Arc::new(handler)),
+        };
+        self.opcodes.insert(opcode, info);
+        Ok(())
+    }
+
+    /// Takes an instruction out, standard or not, so machines using the
+    /// table treat it as invalid. Registering something else with the same
+    /// opcode afterwards replaces it.
+    pub fn remove(&mut self, opcode: i64) -> Option<OpcodeInfo> {
+        self.opcodes.remove(&opcode)
+    }
+
+    pub fn get(&self, opcode: i64) -> Option<&OpcodeInfo> {
+        self.opcodes.get(&opcode)
+    }
+
+    pub fn by_mnemonic(&self, mnemonic: &str) -> Option<&OpcodeInfo> {
+        self.opcodes.values().find(|info| info.mnemonic == mnemonic)
+    }
+
+    /// Splits an instruction word into its opcode and the mode of each
+    /// parameter. Fails if the opcode isn't in the table or there are mode
+    /// digits that don't make sense, including any for parameters the
+    /// instruction doesn't have.
+    pub fn decode(&self, word: i64) -> Result<(&OpcodeInfo, Modes), DecodeError> {
+        if word < 0 {
+            return Err(DecodeError::UnknownOpcode);
+        }
+        let info = self.get(word % 100).ok_or(DecodeError::UnknownOpcode)?;
+        let mut modes = word / 100;
+        let mut decoded = Modes {
+            modes: [ParameterMode::Position; MAX_PARAMS],
+            len: info.params,
+        };
+        for (index, slot) in decoded.modes[..info.params].iter_mut().enumerate() {
+            let mode = modes % 10;
+            *slot = ParameterMode::try_from(mode as i8).map_err(|_| DecodeError::InvalidParameterMode {
+                param: index + 1,
+                mode,
+            })?;
+            modes /= 10;
+        }
+        check_unexpected_digits(word, info.params)?;
+        Ok((info, decoded))
+    }
+}
+
+impl Default for InstructionTable {
+    fn default() -> Self {
+        InstructionTable::new()
+    }
+}
+
+/// Builds an instruction word out of an opcode and parameter modes.
+pub fn encode_word(opcode: i64, modes: &[ParameterMode]) -> i64 {
+    modes
+        .iter()
+        .rev()
+        .fold(0, |acc, &mode| acc * 10 + mode as i64)
+        * 100
+        + opcode
+}
+
+#[test]
+fn test_decode() {
+    let table = InstructionTable::new();
+    let (info, modes) = table.decode(1002).unwrap();
+    assert_eq!(info.mnemonic, "mul");
+    assert_eq!(*modes, [ParameterMode::Position, ParameterMode::Immediate, ParameterMode::Position]);
+    assert_eq!(table.decode(42).unwrap_err(), DecodeError::UnknownOpcode);
+    assert_eq!(
+        table.decode(304).unwrap_err(),
+        DecodeError::InvalidParameterMode { param: 1, mode: 3 }
+    );
+    assert_eq!(
+        table.decode(1099).unwrap_err(),
+        DecodeError::UnexpectedDigit { param: 2, digit: 1 }
+    );
+    assert_eq!(encode_word(2, &modes), 1002);
+}
+
+#[test]
+fn test_register() {
+    let mut table = InstructionTable::new();
+    let nop = |_: &mut Machine, _: &[Operand]| Ok(Flow::Next);
+    assert_eq!(table.register(42, "nop", 0, nop), Ok(()));
+    assert_eq!(table.register(42, "nop2", 0, nop), Err(TableError::OpcodeTaken(42)));
+    assert_eq!(table.register(1, "plus", 3, nop), Err(TableError::OpcodeTaken(1)));
+    assert_eq!(table.register(43, "add", 3, nop), Err(TableError::MnemonicTaken("add".to_string())));
+    assert_eq!(table.register(100, "big", 0, nop), Err(TableError::OutOfRange(100)));
+    assert_eq!(table.register(44, "wide", 18, nop), Err(TableError::TooManyParams(18)));
+    assert_eq!(table.by_mnemonic("nop").map(|info| info.opcode), Some(42));
+}
+
+#[test]
+fn test_custom_encoding() {
+    use ParameterMode::*;
+    let mut table = InstructionTable::new();
+    let nop = |_: &mut Machine, _: &[Operand]| Ok(Flow::Next);
+    table.register(42, "three", 3, nop).unwrap();
+    let modes = [Position, Immediate, Relative];
+    for combination in 0..27 {
+        let chosen = [modes[combination % 3], modes[combination / 3 % 3], modes[combination / 9]];
+        let (info, decoded) = table.decode(encode_word(42, &chosen)).unwrap();
+        assert_eq!((info.mnemonic.as_str(), &decoded[..]), ("three", &chosen[..]));
+    }
+}
@@ -0,0 +1,284 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Taint tracking, for finding out which parts of a program actually depend
+//! on its input. Every cell has a shadow set of labels saying which sources
+//! its value came from, either cells marked with `Machine::taint_cell` (like
+//! day 2's noun and verb) or values read by input instructions.
+//!
+//! Labels flow from the operands of an instruction to whatever it writes. An
+//! operand's labels are those of the cell holding the operand itself (so a
+//! patched address taints what's loaded through it), plus those of the cell
+//! it points at, plus the relative base's for relative mode. Writes pick up
+//! the labels of the destination operand too (but not of the old value), since
+//! a tainted pointer decides which cell ends up with the value. Outputs and
+//! jumps that depend on any source are kept for the report. Only data flow is
+//! followed, so a constant picked by a tainted jump isn't tainted itself, but
+//! the jump shows up. Custom instructions don't propagate anything.
+
+use super::instruction::ParameterMode;
+use super::table::Operand;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+
+/// Where a value came from.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Source {
+    /// A cell marked as a source, by its address.
+    Cell(usize),
+    /// The value read by an input instruction, counting from 0.
+    Input(usize),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Cell(address) => write!(f, "cell {}", address),
+            Source::Input(index) => write!(f, "input {}", index),
+        }
+    }
+}
+
+pub type Labels = BTreeSet<Source>;
+
+/// A value output by the instruction at `ip` that depended on some sources.
+/// `index` counts every output from 0, tainted or not.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TaintedOutput {
+    pub index: usize,
+    pub ip: usize,
+    pub value: i64,
+    pub sources: Labels,
+}
+
+/// A jump at `ip` whose condition or target depended on some sources.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Decision {
+    pub ip: usize,
+    pub taken: bool,
+    pub sources: Labels,
+}
+
+/// The shadow state, and what it's found so far.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Taint {
+    cells: BTreeMap<usize, Labels>,
+    relative_base: Labels,
+    inputs: usize,
+    outputs: usize,
+    tainted_outputs: Vec<TaintedOutput>,
+    decisions: Vec<Decision>,
+}
+
+fn describe(labels: &Labels) -> String {
+    labels.iter().map(Source::to_string).collect::<Vec<_>>().join(", ")
+}
+
+impl Taint {
+    pub fn new() -> Self {
+        Taint::default()
+    }
+
+    /// Makes a cell a source, with its value labelled as itself.
+    pub fn mark(&mut self, address: usize) {
+        self.cells.insert(address, Some(Source::Cell(address)).into_iter().collect());
+    }
+
+    /// Where the value in a cell came from, if it's tainted at all.
+    pub fn cell(&self, address: usize) -> Option<&Labels> {
+        self.cells.get(&address)
+    }
+
+    /// Every tainted cell, in address order.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, &Labels)> {
+        self.cells.iter().map(|(&address, labels)| (address, labels))
+    }
+
+    pub fn relative_base(&self) -> &Labels {
+        &self.relative_base
+    }
+
+    pub fn outputs(&self) -> &[TaintedOutput] {
+        &self.tainted_outputs
+    }
+
+    pub fn decisions(&self) -> &[Decision] {
+        &self.decisions
+    }
+
+    /// The labels of an operand's value, given the address it refers to
+    /// (which is `None` for immediate mode).
+    pub(crate) fn operand(&self, operand: &Operand, target: Option<usize>) -> Labels {
+        let mut labels = self.cells.get(&operand.address).cloned().unwrap_or_default();
+        if let Some(target) = target {
+            labels.extend(self.cells.get(&target).into_iter().flatten());
+        }
+        if operand.mode == ParameterMode::Relative {
+            labels.extend(&self.relative_base);
+        }
+        labels
+    }
+
+    /// Sets the labels of a cell that's been written to. Writing an untainted
+    /// value cleans it.
+    pub(crate) fn write(&mut self, address: usize, labels: Labels) {
+        if labels.is_empty() {
+            self.cells.remove(&address);
+        } else {
+            self.cells.insert(address, labels);
+        }
+    }
+
+    /// An input instruction wrote its value to `address`, through a
+    /// destination operand with labels `pointer`.
+    pub(crate) fn input(&mut self, address: usize, mut pointer: Labels) {
+        pointer.insert(Source::Input(self.inputs));
+        self.inputs += 1;
+        self.write(address, pointer);
+    }
+
+    pub(crate) fn output(&mut self, ip: usize, value: i64, sources: Labels) {
+        if !sources.is_empty() {
+            self.tainted_outputs.push(TaintedOutput {
+                index: self.outputs,
+                ip,
+                value,
+                sources,
+            });
+        }
+        self.outputs += 1;
+    }
+
+    pub(crate) fn decision(&mut self, ip: usize, taken: bool, sources: Labels) {
+        if !sources.is_empty() {
+            self.decisions.push(Decision { ip, taken, sources });
+        }
+    }
+
+    pub(crate) fn adjust_relative_base(&mut self, labels: Labels) {
+        self.relative_base.extend(labels);
+    }
+
+    /// What depended on the sources: outputs, then jumps (each address once,
+    /// with how often it went each way), then the cells still tainted.
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "Tainted outputs:").unwrap();
+        for output in &self.tainted_outputs {
+            writeln!(
+                out,
+                "  output {} = {} at {}: {}",
+                output.index,
+                output.value,
+                output.ip,
+                describe(&output.sources)
+            )
+            .unwrap();
+        }
+        writeln!(out, "Tainted jumps:").unwrap();
+        let mut jumps: BTreeMap<usize, (u64, u64, Labels)> = BTreeMap::new();
+        for decision in &self.decisions {
+            let (taken, not_taken, sources) = jumps.entry(decision.ip).or_default();
+            if decision.taken {
+                *taken += 1;
+            } else {
+                *not_taken += 1;
+            }
+            sources.extend(&decision.sources);
+        }
+        for (ip, (taken, not_taken, sources)) in jumps {
+            writeln!(
+                out,
+                "  {}: jumped {}, fell through {}: {}",
+                ip,
+                taken,
+                not_taken,
+                describe(&sources)
+            )
+            .unwrap();
+        }
+        writeln!(out, "Tainted cells:").unwrap();
+        for (address, labels) in &self.cells {
+            writeln!(out, "  {}: {}", address, describe(labels)).unwrap();
+        }
+        out
+    }
+}
+
+#[test]
+fn test_noun_and_verb() {
+    use super::Machine;
+    // Day 2's first example: [0] = ([9] + [10]) * [11], with the 9 and 10
+    // being the noun and verb
+    let mut machine = Machine::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+    machine.taint_cell(1);
+    machine.taint_cell(2);
+    machine.run().unwrap();
+    let taint = machine.take_taint().unwrap();
+    let both: Labels = [Source::Cell(1), Source::Cell(2)].iter().copied().collect();
+    assert_eq!(taint.cell(0), Some(&both));
+    assert_eq!(taint.cell(3), Some(&both));
+    assert_eq!(taint.cell(11), None);
+    assert!(taint.outputs().is_empty());
+}
+
+#[test]
+fn test_input_and_branches() {
+    use super::Machine;
+    // Outputs 999 if the input is below 8, 1000 if it's 8, and 1001 if it's
+    // more (day 5)
+    let program = vec![
+        3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0, 0, 1002, 21, 125, 20, 4,
+        20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105, 1, 46, 98, 99,
+    ];
+    let mut machine = Machine::new(program.clone());
+    machine.enable_taint();
+    assert_eq!(machine.run_with_input(vec![8]), Ok(vec![1000]));
+    assert_eq!(
+        machine.taint().unwrap().report(),
+        "Tainted outputs:
+  output 0 = 1000 at 26: input 0
+Tainted jumps:
+  6: jumped 1, fell through 0: input 0
+Tainted cells:
+  20: input 0
+  21: input 0
+"
+    );
+    // Here the output is picked by the jumps, but is a constant, so only the
+    // jumps depend on the input
+    let mut machine = Machine::new(program);
+    machine.enable_taint();
+    assert_eq!(machine.run_with_input(vec![9]), Ok(vec![1001]));
+    let taint = machine.taint().unwrap();
+    assert!(taint.outputs().is_empty());
+    let jumps: Vec<(usize, bool)> = taint.decisions().iter().map(|d| (d.ip, d.taken)).collect();
+    assert_eq!(jumps, vec![(6, false), (13, false)]);
+    assert_eq!(taint.cell(20), None);
+}
+
+#[test]
+fn test_tainted_destination() {
+    use super::Machine;
+    // [7] = 2 + 3 and [8] = input, with both destinations patched in
+    let mut machine = Machine::new(vec![1101, 2, 3, 7, 3, 8, 99, 0, 0]);
+    machine.taint_cell(3);
+    machine.taint_cell(5);
+    assert_eq!(machine.run_with_input(vec![4]), Ok(vec![]));
+    let taint = machine.take_taint().unwrap();
+    let labels = |sources: &[Source]| sources.iter().copied().collect::<Labels>();
+    assert_eq!(taint.cell(7), Some(&labels(&[Source::Cell(3)])));
+    assert_eq!(taint.cell(8), Some(&labels(&[Source::Cell(5), Source::Input(0)])));
+}
@@ -0,0 +1,506 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Translates IntCode programs into Rust ahead of time, for when
+//! interpreting is too slow.
+//!
+//! The generated module has a `run` function that works like `Machine::run`
+//! on a machine loaded with the program. It's a loop with a match on the
+//! instruction pointer, where each arm is a block of straight-line code that
+//! starts somewhere a jump (or a return from a subroutine) can land. The
+//! program is assumed not to modify its own code, and operands get baked in
+//! as constants, so:
+//!
+//! - if the machine's code doesn't match the program when `run` is called,
+//!   it's all interpreted instead;
+//! - if a write lands on code, the machine is handed to the interpreter from
+//!   the next instruction on;
+//! - jumping somewhere that isn't the start of a block, hitting an error, or
+//!   waiting for input also hand over to the interpreter, which then takes
+//!   care of reporting it properly.
+//!
+//! Translated code doesn't count steps, so `Machine::steps` and step limits
+//! only cover what ends up interpreted.
+//!
+//! To compile a puzzle input into a binary, call `build` from the build
+//! script of a crate that depends on this one:
+//!
+//! ```ignore
+//! // build.rs
+//! fn main() {
+//!     aoc_2019::intcode::translate::build("input/day9.txt", "day9").unwrap();
+//! }
+//!
+//! // src/main.rs
+//! include!(concat!(env!("OUT_DIR"), "/day9.rs"));
+//! let mut machine = Machine::new(day9::PROGRAM.to_vec());
+//! machine.push_input(1);
+//! day9::run(&mut machine)?;
+//! ```
+//!
+//! This crate can't be its own build dependency, so inside it there's the
+//! `translate` binary instead, which writes the module wherever it's told.
+
+use super::disasm::{always_jumps, trace, Item};
+use super::instruction::ParameterMode;
+use super::table::{InstructionTable, Operand};
+use super::Program;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The reachable code, by address, and the addresses blocks start at.
+struct Analysis {
+    code: BTreeMap<usize, Item>,
+    leaders: BTreeSet<usize>,
+}
+
+fn operands(item: &Item) -> &[Operand] {
+    match item {
+        Item::Instruction { operands, .. } => operands,
+        Item::Data { .. } => &[],
+    }
+}
+
+fn mnemonic(item: &Item) -> &str {
+    match item {
+        Item::Instruction { mnemonic, .. } => mnemonic,
+        Item::Data { .. } => "data",
+    }
+}
+
+fn analyse(program: &[i64]) -> Analysis {
+    let table = InstructionTable::standard();
+    let mut entries = vec![0];
+    let code = loop {
+        let code = trace(program, &table, &entries);
+        // Anything right after a jump that's always taken can only be reached
+        // by a computed jump. If there's a constant pointing there, it's
+        // probably a return address.
+        let after_jumps: BTreeSet<usize> = code
+            .iter()
+            .filter(|item| always_jumps(item))
+            .map(|item| item.address() + item.width())
+            .collect();
+        let returns: Vec<usize> = code
+            .iter()
+            .flat_map(|item| operands(item).iter())
+            .filter(|op| op.mode == ParameterMode::Immediate && op.value >= 0)
+            .map(|op| op.value as usize)
+            .filter(|addr| after_jumps.contains(addr) && !entries.contains(addr))
+            .collect();
+        if returns.is_empty() {
+            break code;
+        }
+        entries.extend(returns);
+    };
+    let code: BTreeMap<usize, Item> = code.into_iter().map(|item| (item.address(), item)).collect();
+    let mut leaders: BTreeSet<usize> = entries.into_iter().collect();
+    for item in code.values() {
+        match mnemonic(item) {
+            "jt" | "jf" => {
+                leaders.insert(item.address() + item.width());
+                let target = operands(item)[1];
+                if target.mode == ParameterMode::Immediate && target.value >= 0 {
+                    leaders.insert(target.value as usize);
+                }
+            }
+            // So it can carry on from there after waiting for input
+            "in" => {
+                leaders.insert(item.address());
+            }
+            _ => {}
+        }
+    }
+    leaders.retain(|addr| code.contains_key(addr));
+    Analysis { code, leaders }
+}
+
+/// The ranges of addresses the code covers, inclusive.
+fn code_ranges(code: &BTreeMap<usize, Item>) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for item in code.values() {
+        let (start, end) = (item.address(), item.address() + item.width() - 1);
+        match ranges.last_mut() {
+            Some(last) if last.1 + 1 >= start => last.1 = last.1.max(end),
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+/// An expression for the value an operand refers to, if it can be read.
+fn load(op: Operand, ip: usize) -> Option<String> {
+    match op.mode {
+        ParameterMode::Immediate if op.value < 0 => Some(format!("({})", op.value)),
+        ParameterMode::Immediate => Some(op.value.to_string()),
+        ParameterMode::Position if op.value < 0 => None,
+        ParameterMode::Position => Some(format!("m.peek({})", op.value)),
+        ParameterMode::Relative => Some(format!("m.peek(rel!(m, {}, {}))", op.value, ip)),
+    }
+}
+
+/// An expression for the address an operand refers to, if it can be written.
+fn store(op: Operand, ip: usize) -> Option<String> {
+    match op.mode {
+        ParameterMode::Immediate => None,
+        ParameterMode::Position if op.value < 0 => None,
+        ParameterMode::Position => Some(op.value.to_string()),
+        ParameterMode::Relative => Some(format!("rel!(m, {}, {})", op.value, ip)),
+    }
+}
+
+/// Writes out an instruction. Returns whether it ends the block.
+fn instruction(out: &mut String, item: &Item) -> bool {
+    let ip = item.address();
+    let next = ip + item.width();
+    let ops = operands(item);
+    let pad = " ".repeat(20);
+    writeln!(out, "{}// {}: {}", pad, ip, item).unwrap();
+    let bail = |out: &mut String| {
+        writeln!(out, "{}bail!(m, {});", pad, ip).unwrap();
+        true
+    };
+    let write_to = |out: &mut String, dest: &str, value: &str| {
+        writeln!(out, "{}{{", pad).unwrap();
+        writeln!(out, "{}    let addr = {};", pad, dest).unwrap();
+        writeln!(out, "{}    m.poke(addr, {});", pad, value).unwrap();
+        writeln!(out, "{}    if is_code(addr) {{", pad).unwrap();
+        writeln!(out, "{}        bail!(m, {});", pad, next).unwrap();
+        writeln!(out, "{}    }}", pad).unwrap();
+        writeln!(out, "{}}}", pad).unwrap();
+    };
+    match mnemonic(item) {
+        op @ ("add" | "mul" | "lt" | "eq") => {
+            let (a, b, dest) = match (load(ops[0], ip), load(ops[1], ip), store(ops[2], ip)) {
+                (Some(a), Some(b), Some(dest)) => (a, b, dest),
+                _ => return bail(out),
+            };
+            let value = match op {
+                "add" => format!("{} + {}", a, b),
+                "mul" => format!("{} * {}", a, b),
+                "lt" => format!("({} < {}) as i64", a, b),
+                _ => format!("({} == {}) as i64", a, b),
+            };
+            write_to(out, &dest, &value);
+            false
+        }
+        "in" => {
+            let dest = match store(ops[0], ip) {
+                Some(dest) => dest,
+                None => return bail(out),
+            };
+            // Working out where it goes first, so if that fails the input's
+            // still there for the interpreter
+            writeln!(out, "{}{{", pad).unwrap();
+            writeln!(out, "{}    let addr = {};", pad, dest).unwrap();
+            writeln!(out, "{}    let input = match m.pop_input() {{", pad).unwrap();
+            writeln!(out, "{}        Some(value) => value,", pad).unwrap();
+            writeln!(out, "{}        None => bail!(m, {}),", pad, ip).unwrap();
+            writeln!(out, "{}    }};", pad).unwrap();
+            writeln!(out, "{}    m.poke(addr, input);", pad).unwrap();
+            writeln!(out, "{}    if is_code(addr) {{", pad).unwrap();
+            writeln!(out, "{}        bail!(m, {});", pad, next).unwrap();
+            writeln!(out, "{}    }}", pad).unwrap();
+            writeln!(out, "{}}}", pad).unwrap();
+            false
+        }
+        "out" => match load(ops[0], ip) {
+            Some(value) => {
+                writeln!(out, "{}m.push_output({});", pad, value).unwrap();
+                false
+            }
+            None => bail(out),
+        },
+        jump @ ("jt" | "jf") => {
+            let target = match ops[1] {
+                Operand { mode: ParameterMode::Immediate, value, .. } if value >= 0 => value.to_string(),
+                op => match load(op, ip) {
+                    Some(target) => format!(
+                        "match usize::try_from({}) {{ Ok(target) => target, Err(_) => bail!(m, {}) }}",
+                        target, ip
+                    ),
+                    None => return bail(out),
+                },
+            };
+            if ops[0].mode == ParameterMode::Immediate {
+                // Always or never jumps
+                let taken = (ops[0].value != 0) == (jump == "jt");
+                let ip = if taken { target } else { next.to_string() };
+                writeln!(out, "{}m.set_ip({});", pad, ip).unwrap();
+                return true;
+            }
+            let condition = match load(ops[0], ip) {
+                Some(condition) => condition,
+                None => return bail(out),
+            };
+            let test = if jump == "jt" { "!=" } else { "==" };
+            writeln!(out, "{}if {} {} 0 {{", pad, condition, test).unwrap();
+            writeln!(out, "{}    m.set_ip({});", pad, target).unwrap();
+            writeln!(out, "{}}} else {{", pad).unwrap();
+            writeln!(out, "{}    m.set_ip({});", pad, next).unwrap();
+            writeln!(out, "{}}}", pad).unwrap();
+            true
+        }
+        "arb" => match load(ops[0], ip) {
+            Some(value) => {
+                writeln!(out, "{}m.set_relative_base(m.relative_base() + {});", pad, value).unwrap();
+                false
+            }
+            None => bail(out),
+        },
+        // Halting, like anything else the interpreter has to deal with
+        _ => bail(out),
+    }
+}
+
+/// Translates a program into a Rust module called `module`, using this
+/// crate as `aoc_2019`.
+pub fn translate(program: &[i64], module: &str) -> String {
+    translate_in(program, module, "aoc_2019")
+}
+
+/// Like `translate`, but with a different path to this crate, for using
+/// the generated code from inside it.
+pub fn translate_in(program: &[i64], module: &str, krate: &str) -> String {
+    let analysis = analyse(program);
+    let ranges = code_ranges(&analysis.code);
+    let mut out = String::new();
+    writeln!(out, "// Translated from an IntCode program by aoc_2019::intcode::translate. Don't edit.").unwrap();
+    writeln!(out, "#[allow(unused, unreachable_code, clippy::all)]").unwrap();
+    writeln!(out, "pub mod {} {{", module).unwrap();
+    writeln!(out, "    use {}::intcode::{{InterpreterError, Machine, State}};", krate).unwrap();
+    writeln!(out, "    use std::convert::TryFrom;").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    /// The program this was translated from.").unwrap();
+    let words: Vec<String> = program.iter().map(i64::to_string).collect();
+    writeln!(out, "    pub const PROGRAM: &[i64] = &[{}];", words.join(", ")).unwrap();
+    writeln!(out).unwrap();
+    let ranges_list: Vec<String> = ranges.iter().map(|(start, end)| format!("({}, {})", start, end)).collect();
+    writeln!(out, "    const CODE: &[(usize, usize)] = &[{}];", ranges_list.join(", ")).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    fn is_code(addr: usize) -> bool {{").unwrap();
+    if ranges.is_empty() {
+        writeln!(out, "        false").unwrap();
+    } else {
+        let patterns: Vec<String> = ranges.iter().map(|(start, end)| format!("{}..={}", start, end)).collect();
+        writeln!(out, "        matches!(addr, {})", patterns.join(" | ")).unwrap();
+    }
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    /// Whether the machine's code is still what was translated.").unwrap();
+    writeln!(out, "    pub fn matches(m: &Machine) -> bool {{").unwrap();
+    writeln!(
+        out,
+        "        CODE.iter().all(|&(start, end)| (start..=end).all(|addr| m.peek(addr) == PROGRAM[addr]))"
+    )
+    .unwrap();
+    writeln!(out, "    }}").unwrap();
+    out.push_str(
+        "
+    // Hands the machine over to the interpreter, from the instruction at `ip`.
+    macro_rules! bail {
+        ($m:ident, $ip:expr) => {{
+            $m.set_ip($ip);
+            return $m.run();
+        }};
+    }
+
+    macro_rules! rel {
+        ($m:ident, $offset:expr, $ip:expr) => {
+            match usize::try_from($m.relative_base() + $offset) {
+                Ok(addr) => addr,
+                Err(_) => bail!($m, $ip),
+            }
+        };
+    }
+
+    /// Runs the machine like `Machine::run`, natively for as long as it can.
+    pub fn run(m: &mut Machine) -> Result<State, InterpreterError> {
+        if m.state() == State::Halted || !matches(m) {
+            return m.run();
+        }
+        loop {
+            match m.ip() {
+",
+    );
+    for &leader in &analysis.leaders {
+        writeln!(out, "                {} => {{", leader).unwrap();
+        let mut address = leader;
+        loop {
+            // Falling through to something that isn't code, like data or the
+            // end of the program, is for the interpreter to report
+            let item = match analysis.code.get(&address) {
+                Some(item) => item,
+                None => {
+                    writeln!(out, "                    bail!(m, {});", address).unwrap();
+                    break;
+                }
+            };
+            if instruction(&mut out, item) {
+                break;
+            }
+            address += item.width();
+            // Everything that doesn't end a block falls through to more code
+            if analysis.leaders.contains(&address) {
+                writeln!(out, "                    m.set_ip({});", address).unwrap();
+                break;
+            }
+        }
+        writeln!(out, "                }}").unwrap();
+    }
+    out.push_str(
+        "                _ => return m.run(),
+            }
+        }
+    }
+}
+",
+    );
+    out
+}
+
+/// For build scripts: translates the program in `input` to
+/// `$OUT_DIR/<module>.rs`, ready to be `include!`d.
+pub fn build(input: &str, module: &str) -> Result<(), Box<dyn Error>> {
+    println!("cargo:rerun-if-changed={}", input);
+    let output = PathBuf::from(env::var("OUT_DIR")?).join(format!("{}.rs", module));
+    translate_file(input, module, output)
+}
+
+/// Translates the program in the file `input` into a module called `module`,
+/// and writes it to `output`.
+pub fn translate_file<P: AsRef<Path>, Q: AsRef<Path>>(input: P, module: &str, output: Q) -> Result<(), Box<dyn Error>> {
+    let program = Program::from_file(input)?;
+    fs::write(output, translate(&program, module))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod translated {
+    include!("translate_fixture.rs");
+}
+
+/// The programs in `translate_fixture.rs`, which is what these translate to.
+#[cfg(test)]
+fn fixture_programs() -> Vec<(&'static str, Vec<i64>)> {
+    use super::asm::assemble;
+    let table = InstructionTable::new();
+    // Doubles each input using a subroutine, until it gets a zero
+    let doubler = "
+                arb stack
+        loop:   in [rb+1]
+                jf [rb+1], done
+                add ret, 0, [rb+0]
+                jt 1, double
+        ret:    out [rb+1]
+                jt 1, loop
+        done:   hlt
+        double: add [rb+1], [rb+1], [rb+1]
+                jt 1, [rb+0]
+        stack:  data 0
+    ";
+    vec![
+        ("quine", vec![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99]),
+        (
+            "compare",
+            vec![
+                3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0, 0, 1002, 21,
+                125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105, 1, 46, 98, 99,
+            ],
+        ),
+        ("doubler", assemble(doubler, &table).unwrap().program),
+        // Overwrites its last output with a halt
+        ("overwrite", vec![1101, 0, 99, 6, 104, 1, 104, 2, 99]),
+        // Run off the end of the program, and into data
+        ("off_the_end", vec![104, 1]),
+        ("into_data", vec![104, 1, 42]),
+        // Reads into a negative address
+        ("negative_input", vec![109, -5, 203, 0, 99]),
+    ]
+}
+
+#[cfg(test)]
+fn fixture_translation() -> String {
+    fixture_programs()
+        .iter()
+        .map(|(name, program)| translate_in(program, name, "crate"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn test_translate_fixture() {
+    // If this fails after changing the translation on purpose, check the new
+    // output over and update the fixture with
+    // `cargo test --lib regenerate_translate_fixture -- --ignored`
+    assert_eq!(fixture_translation(), include_str!("translate_fixture.rs"));
+}
+
+#[test]
+#[ignore]
+fn regenerate_translate_fixture() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/intcode/translate_fixture.rs");
+    fs::write(path, fixture_translation()).unwrap();
+}
+
+#[cfg(test)]
+type Run = fn(&mut super::Machine) -> Result<super::State, super::InterpreterError>;
+
+#[test]
+fn test_translated_matches_interpreter() {
+    use super::Machine;
+    let runs: [(&str, Run, Vec<i64>); 9] = [
+        ("quine", translated::quine::run, vec![]),
+        ("compare", translated::compare::run, vec![7]),
+        ("compare", translated::compare::run, vec![8]),
+        ("compare", translated::compare::run, vec![9]),
+        ("doubler", translated::doubler::run, vec![1, 5, -3, 0]),
+        ("overwrite", translated::overwrite::run, vec![]),
+        ("off_the_end", translated::off_the_end::run, vec![]),
+        ("into_data", translated::into_data::run, vec![]),
+        ("negative_input", translated::negative_input::run, vec![7, 8]),
+    ];
+    let programs: BTreeMap<_, _> = fixture_programs().into_iter().collect();
+    for (name, run, input) in runs.iter() {
+        let mut interpreted = Machine::new(programs[name].clone());
+        interpreted.extend_input(input.iter().copied());
+        let mut compiled = interpreted.clone();
+        assert_eq!(run(&mut compiled), interpreted.run(), "{}", name);
+        assert_eq!(compiled.take_output(), interpreted.take_output(), "{}", name);
+        assert_eq!(compiled.input_len(), interpreted.input_len(), "{}", name);
+        assert_eq!(compiled.into_memory(), interpreted.into_memory(), "{}", name);
+    }
+}
+
+#[test]
+fn test_translated_resumes_after_input() {
+    use super::{Machine, State};
+    let mut machine = Machine::new(translated::doubler::PROGRAM.to_vec());
+    assert_eq!(translated::doubler::run(&mut machine), Ok(State::WaitingForInput));
+    machine.push_input(21);
+    assert_eq!(translated::doubler::run(&mut machine), Ok(State::WaitingForInput));
+    assert_eq!(machine.take_output(), vec![42]);
+    // None of that needed the interpreter
+    assert_eq!(machine.steps(), 0);
+    // Code that isn't the same as what was translated is all interpreted
+    machine.poke(0, 99);
+    machine.set_ip(0);
+    assert_eq!(translated::doubler::run(&mut machine), Ok(State::Halted));
+}
+
@@ -0,0 +1,573 @@
+// Translated from an IntCode program by aoc_2019::intcode::translate. Don't edit.
+#[allow(unused, unreachable_code, clippy::all)]
+pub mod quine {
+    use crate::intcode::{InterpreterError, Machine, State};
+    use std::convert::TryFrom;
+
+    /// The program this was translated from.
+    pub const PROGRAM: &[i64] = &[109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99];
+
+    const CODE: &[(usize, usize)] = &[(0, 15)];
+
+    fn is_code(addr: usize) -> bool {
+        matches!(addr, 0..=15)
+    }
+
+    /// Whether the machine's code is still what was translated.
+    pub fn matches(m: &Machine) -> bool {
+        CODE.iter().all(|&(start, end)| (start..=end).all(|addr| m.peek(addr) == PROGRAM[addr]))
+    }
+
+    // Hands the machine over to the interpreter, from the instruction at `ip`.
+    macro_rules! bail {
+        ($m:ident, $ip:expr) => {{
+            $m.set_ip($ip);
+            return $m.run();
+        }};
+    }
+
+    macro_rules! rel {
+        ($m:ident, $offset:expr, $ip:expr) => {
+            match usize::try_from($m.relative_base() + $offset) {
+                Ok(addr) => addr,
+                Err(_) => bail!($m, $ip),
+            }
+        };
+    }
+
+    /// Runs the machine like `Machine::run`, natively for as long as it can.
+    pub fn run(m: &mut Machine) -> Result<State, InterpreterError> {
+        if m.state() == State::Halted || !matches(m) {
+            return m.run();
+        }
+        loop {
+            match m.ip() {
+                0 => {
+                    // 0: arb 1
+                    m.set_relative_base(m.relative_base() + 1);
+                    // 2: out [rb-1]
+                    m.push_output(m.peek(rel!(m, -1, 2)));
+                    // 4: add [100], 1, [100]
+                    {
+                        let addr = 100;
+                        m.poke(addr, m.peek(100) + 1);
+                        if is_code(addr) {
+                            bail!(m, 8);
+                        }
+                    }
+                    // 8: eq [100], 16, [101]
+                    {
+                        let addr = 101;
+                        m.poke(addr, (m.peek(100) == 16) as i64);
+                        if is_code(addr) {
+                            bail!(m, 12);
+                        }
+                    }
+                    // 12: jf [101], 0
+                    if m.peek(101) == 0 {
+                        m.set_ip(0);
+                    } else {
+                        m.set_ip(15);
+                    }
+                }
+                15 => {
+                    // 15: hlt
+                    bail!(m, 15);
+                }
+                _ => return m.run(),
+            }
+        }
+    }
+}
+
+// Translated from an IntCode program by aoc_2019::intcode::translate. Don't edit.
+#[allow(unused, unreachable_code, clippy::all)]
+pub mod compare {
+    use crate::intcode::{InterpreterError, Machine, State};
+    use std::convert::TryFrom;
+
+    /// The program this was translated from.
+    pub const PROGRAM: &[i64] = &[3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105, 1, 46, 98, 99];
+
+    const CODE: &[(usize, usize)] = &[(0, 18), (22, 44), (46, 46)];
+
+    fn is_code(addr: usize) -> bool {
+        matches!(addr, 0..=18 | 22..=44 | 46..=46)
+    }
+
+    /// Whether the machine's code is still what was translated.
+    pub fn matches(m: &Machine) -> bool {
+        CODE.iter().all(|&(start, end)| (start..=end).all(|addr| m.peek(addr) == PROGRAM[addr]))
+    }
+
+    // Hands the machine over to the interpreter, from the instruction at `ip`.
+    macro_rules! bail {
+        ($m:ident, $ip:expr) => {{
+            $m.set_ip($ip);
+            return $m.run();
+        }};
+    }
+
+    macro_rules! rel {
+        ($m:ident, $offset:expr, $ip:expr) => {
+            match usize::try_from($m.relative_base() + $offset) {
+                Ok(addr) => addr,
+                Err(_) => bail!($m, $ip),
+            }
+        };
+    }
+
+    /// Runs the machine like `Machine::run`, natively for as long as it can.
+    pub fn run(m: &mut Machine) -> Result<State, InterpreterError> {
+        if m.state() == State::Halted || !matches(m) {
+            return m.run();
+        }
+        loop {
+            match m.ip() {
+                0 => {
+                    // 0: in [21]
+                    {
+                        let addr = 21;
+                        let input = match m.pop_input() {
+                            Some(value) => value,
+                            None => bail!(m, 0),
+                        };
+                        m.poke(addr, input);
+                        if is_code(addr) {
+                            bail!(m, 2);
+                        }
+                    }
+                    // 2: eq [21], 8, [20]
+                    {
+                        let addr = 20;
+                        m.poke(addr, (m.peek(21) == 8) as i64);
+                        if is_code(addr) {
+                            bail!(m, 6);
+                        }
+                    }
+                    // 6: jt [20], 22
+                    if m.peek(20) != 0 {
+                        m.set_ip(22);
+                    } else {
+                        m.set_ip(9);
+                    }
+                }
+                9 => {
+                    // 9: lt 8, [21], [20]
+                    {
+                        let addr = 20;
+                        m.poke(addr, (8 < m.peek(21)) as i64);
+                        if is_code(addr) {
+                            bail!(m, 13);
+                        }
+                    }
+                    // 13: jf [20], 31
+                    if m.peek(20) == 0 {
+                        m.set_ip(31);
+                    } else {
+                        m.set_ip(16);
+                    }
+                }
+                16 => {
+                    // 16: jf 0, 36
+                    m.set_ip(36);
+                }
+                22 => {
+                    // 22: mul [21], 125, [20]
+                    {
+                        let addr = 20;
+                        m.poke(addr, m.peek(21) * 125);
+                        if is_code(addr) {
+                            bail!(m, 26);
+                        }
+                    }
+                    // 26: out [20]
+                    m.push_output(m.peek(20));
+                    // 28: jt 1, 46
+                    m.set_ip(46);
+                }
+                31 => {
+                    // 31: out 999
+                    m.push_output(999);
+                    // 33: jt 1, 46
+                    m.set_ip(46);
+                }
+                36 => {
+                    // 36: add 1000, 1, [20]
+                    {
+                        let addr = 20;
+                        m.poke(addr, 1000 + 1);
+                        if is_code(addr) {
+                            bail!(m, 40);
+                        }
+                    }
+                    // 40: out [20]
+                    m.push_output(m.peek(20));
+                    // 42: jt 1, 46
+                    m.set_ip(46);
+                }
+                46 => {
+                    // 46: hlt
+                    bail!(m, 46);
+                }
+                _ => return m.run(),
+            }
+        }
+    }
+}
+
+// Translated from an IntCode program by aoc_2019::intcode::translate. Don't edit.
+#[allow(unused, unreachable_code, clippy::all)]
+pub mod doubler {
+    use crate::intcode::{InterpreterError, Machine, State};
+    use std::convert::TryFrom;
+
+    /// The program this was translated from.
+    pub const PROGRAM: &[i64] = &[109, 27, 203, 1, 1206, 1, 19, 21101, 14, 0, 0, 1105, 1, 20, 204, 1, 1105, 1, 2, 99, 22201, 1, 1, 1, 2105, 1, 0, 0];
+
+    const CODE: &[(usize, usize)] = &[(0, 26)];
+
+    fn is_code(addr: usize) -> bool {
+        matches!(addr, 0..=26)
+    }
+
+    /// Whether the machine's code is still what was translated.
+    pub fn matches(m: &Machine) -> bool {
+        CODE.iter().all(|&(start, end)| (start..=end).all(|addr| m.peek(addr) == PROGRAM[addr]))
+    }
+
+    // Hands the machine over to the interpreter, from the instruction at `ip`.
+    macro_rules! bail {
+        ($m:ident, $ip:expr) => {{
+            $m.set_ip($ip);
+            return $m.run();
+        }};
+    }
+
+    macro_rules! rel {
+        ($m:ident, $offset:expr, $ip:expr) => {
+            match usize::try_from($m.relative_base() + $offset) {
+                Ok(addr) => addr,
+                Err(_) => bail!($m, $ip),
+            }
+        };
+    }
+
+    /// Runs the machine like `Machine::run`, natively for as long as it can.
+    pub fn run(m: &mut Machine) -> Result<State, InterpreterError> {
+        if m.state() == State::Halted || !matches(m) {
+            return m.run();
+        }
+        loop {
+            match m.ip() {
+                0 => {
+                    // 0: arb 27
+                    m.set_relative_base(m.relative_base() + 27);
+                    m.set_ip(2);
+                }
+                2 => {
+                    // 2: in [rb+1]
+                    {
+                        let addr = rel!(m, 1, 2);
+                        let input = match m.pop_input() {
+                            Some(value) => value,
+                            None => bail!(m, 2),
+                        };
+                        m.poke(addr, input);
+                        if is_code(addr) {
+                            bail!(m, 4);
+                        }
+                    }
+                    // 4: jf [rb+1], 19
+                    if m.peek(rel!(m, 1, 4)) == 0 {
+                        m.set_ip(19);
+                    } else {
+                        m.set_ip(7);
+                    }
+                }
+                7 => {
+                    // 7: add 14, 0, [rb+0]
+                    {
+                        let addr = rel!(m, 0, 7);
+                        m.poke(addr, 14 + 0);
+                        if is_code(addr) {
+                            bail!(m, 11);
+                        }
+                    }
+                    // 11: jt 1, 20
+                    m.set_ip(20);
+                }
+                14 => {
+                    // 14: out [rb+1]
+                    m.push_output(m.peek(rel!(m, 1, 14)));
+                    // 16: jt 1, 2
+                    m.set_ip(2);
+                }
+                19 => {
+                    // 19: hlt
+                    bail!(m, 19);
+                }
+                20 => {
+                    // 20: add [rb+1], [rb+1], [rb+1]
+                    {
+                        let addr = rel!(m, 1, 20);
+                        m.poke(addr, m.peek(rel!(m, 1, 20)) + m.peek(rel!(m, 1, 20)));
+                        if is_code(addr) {
+                            bail!(m, 24);
+                        }
+                    }
+                    // 24: jt 1, [rb+0]
+                    m.set_ip(match usize::try_from(m.peek(rel!(m, 0, 24))) { Ok(target) => target, Err(_) => bail!(m, 24) });
+                }
+                _ => return m.run(),
+            }
+        }
+    }
+}
+
+// Translated from an IntCode program by aoc_2019::intcode::translate. Don't edit.
+#[allow(unused, unreachable_code, clippy::all)]
+pub mod overwrite {
+    use crate::intcode::{InterpreterError, Machine, State};
+    use std::convert::TryFrom;
+
+    /// The program this was translated from.
+    pub const PROGRAM: &[i64] = &[1101, 0, 99, 6, 104, 1, 104, 2, 99];
+
+    const CODE: &[(usize, usize)] = &[(0, 8)];
+
+    fn is_code(addr: usize) -> bool {
+        matches!(addr, 0..=8)
+    }
+
+    /// Whether the machine's code is still what was translated.
+    pub fn matches(m: &Machine) -> bool {
+        CODE.iter().all(|&(start, end)| (start..=end).all(|addr| m.peek(addr) == PROGRAM[addr]))
+    }
+
+    // Hands the machine over to the interpreter, from the instruction at `ip`.
+    macro_rules! bail {
+        ($m:ident, $ip:expr) => {{
+            $m.set_ip($ip);
+            return $m.run();
+        }};
+    }
+
+    macro_rules! rel {
+        ($m:ident, $offset:expr, $ip:expr) => {
+            match usize::try_from($m.relative_base() + $offset) {
+                Ok(addr) => addr,
+                Err(_) => bail!($m, $ip),
+            }
+        };
+    }
+
+    /// Runs the machine like `Machine::run`, natively for as long as it can.
+    pub fn run(m: &mut Machine) -> Result<State, InterpreterError> {
+        if m.state() == State::Halted || !matches(m) {
+            return m.run();
+        }
+        loop {
+            match m.ip() {
+                0 => {
+                    // 0: add 0, 99, [6]
+                    {
+                        let addr = 6;
+                        m.poke(addr, 0 + 99);
+                        if is_code(addr) {
+                            bail!(m, 4);
+                        }
+                    }
+                    // 4: out 1
+                    m.push_output(1);
+                    // 6: out 2
+                    m.push_output(2);
+                    // 8: hlt
+                    bail!(m, 8);
+                }
+                _ => return m.run(),
+            }
+        }
+    }
+}
+
+// Translated from an IntCode program by aoc_2019::intcode::translate. Don't edit.
+#[allow(unused, unreachable_code, clippy::all)]
+pub mod off_the_end {
+    use crate::intcode::{InterpreterError, Machine, State};
+    use std::convert::TryFrom;
+
+    /// The program this was translated from.
+    pub const PROGRAM: &[i64] = &[104, 1];
+
+    const CODE: &[(usize, usize)] = &[(0, 1)];
+
+    fn is_code(addr: usize) -> bool {
+        matches!(addr, 0..=1)
+    }
+
+    /// Whether the machine's code is still what was translated.
+    pub fn matches(m: &Machine) -> bool {
+        CODE.iter().all(|&(start, end)| (start..=end).all(|addr| m.peek(addr) == PROGRAM[addr]))
+    }
+
+    // Hands the machine over to the interpreter, from the instruction at `ip`.
+    macro_rules! bail {
+        ($m:ident, $ip:expr) => {{
+            $m.set_ip($ip);
+            return $m.run();
+        }};
+    }
+
+    macro_rules! rel {
+        ($m:ident, $offset:expr, $ip:expr) => {
+            match usize::try_from($m.relative_base() + $offset) {
+                Ok(addr) => addr,
+                Err(_) => bail!($m, $ip),
+            }
+        };
+    }
+
+    /// Runs the machine like `Machine::run`, natively for as long as it can.
+    pub fn run(m: &mut Machine) -> Result<State, InterpreterError> {
+        if m.state() == State::Halted || !matches(m) {
+            return m.run();
+        }
+        loop {
+            match m.ip() {
+                0 => {
+                    // 0: out 1
+                    m.push_output(1);
+                    bail!(m, 2);
+                }
+                _ => return m.run(),
+            }
+        }
+    }
+}
+
+// Translated from an IntCode program by aoc_2019::intcode::translate. Don't edit.
+#[allow(unused, unreachable_code, clippy::all)]
+pub mod into_data {
+    use crate::intcode::{InterpreterError, Machine, State};
+    use std::convert::TryFrom;
+
+    /// The program this was translated from.
+    pub const PROGRAM: &[i64] = &[104, 1, 42];
+
+    const CODE: &[(usize, usize)] = &[(0, 1)];
+
+    fn is_code(addr: usize) -> bool {
+        matches!(addr, 0..=1)
+    }
+
+    /// Whether the machine's code is still what was translated.
+    pub fn matches(m: &Machine) -> bool {
+        CODE.iter().all(|&(start, end)| (start..=end).all(|addr| m.peek(addr) == PROGRAM[addr]))
+    }
+
+    // Hands the machine over to the interpreter, from the instruction at `ip`.
+    macro_rules! bail {
+        ($m:ident, $ip:expr) => {{
+            $m.set_ip($ip);
+            return $m.run();
+        }};
+    }
+
+    macro_rules! rel {
+        ($m:ident, $offset:expr, $ip:expr) => {
+            match usize::try_from($m.relative_base() + $offset) {
+                Ok(addr) => addr,
+                Err(_) => bail!($m, $ip),
+            }
+        };
+    }
+
+    /// Runs the machine like `Machine::run`, natively for as long as it can.
+    pub fn run(m: &mut Machine) -> Result<State, InterpreterError> {
+        if m.state() == State::Halted || !matches(m) {
+            return m.run();
+        }
+        loop {
+            match m.ip() {
+                0 => {
+                    // 0: out 1
+                    m.push_output(1);
+                    bail!(m, 2);
+                }
+                _ => return m.run(),
+            }
+        }
+    }
+}
+
+// Translated from an IntCode program by aoc_2019::intcode::translate. Don't edit.
+#[allow(unused, unreachable_code, clippy::all)]
+pub mod negative_input {
+    use crate::intcode::{InterpreterError, Machine, State};
+    use std::convert::TryFrom;
+
+    /// The program this was translated from.
+    pub const PROGRAM: &[i64] = &[109, -5, 203, 0, 99];
+
+    const CODE: &[(usize, usize)] = &[(0, 4)];
+
+    fn is_code(addr: usize) -> bool {
+        matches!(addr, 0..=4)
+    }
+
+    /// Whether the machine's code is still what was translated.
+    pub fn matches(m: &Machine) -> bool {
+        CODE.iter().all(|&(start, end)| (start..=end).all(|addr| m.peek(addr) == PROGRAM[addr]))
+    }
+
+    // Hands the machine over to the interpreter, from the instruction at `ip`.
+    macro_rules! bail {
+        ($m:ident, $ip:expr) => {{
+            $m.set_ip($ip);
+            return $m.run();
+        }};
+    }
+
+    macro_rules! rel {
+        ($m:ident, $offset:expr, $ip:expr) => {
+            match usize::try_from($m.relative_base() + $offset) {
+                Ok(addr) => addr,
+                Err(_) => bail!($m, $ip),
+            }
+        };
+    }
+
+    /// Runs the machine like `Machine::run`, natively for as long as it can.
+    pub fn run(m: &mut Machine) -> Result<State, InterpreterError> {
+        if m.state() == State::Halted || !matches(m) {
+            return m.run();
+        }
+        loop {
+            match m.ip() {
+                0 => {
+                    // 0: arb -5
+                    m.set_relative_base(m.relative_base() + (-5));
+                    m.set_ip(2);
+                }
+                2 => {
+                    // 2: in [rb+0]
+                    {
+                        let addr = rel!(m, 0, 2);
+                        let input = match m.pop_input() {
+                            Some(value) => value,
+                            None => bail!(m, 2),
+                        };
+                        m.poke(addr, input);
+                        if is_code(addr) {
+                            bail!(m, 4);
+                        }
+                    }
+                    // 4: hlt
+                    bail!(m, 4);
+                }
+                _ => return m.run(),
+            }
+        }
+    }
+}
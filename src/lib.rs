@@ -13,10 +13,22 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::{self, prelude::*, BufReader};
+use std::str::FromStr;
 
+pub mod answers;
+pub mod arcade;
+pub mod beam;
+pub mod days;
+pub mod droid;
+pub mod geometry;
+pub mod hull;
 pub mod intcode;
+pub mod timing;
 
 pub fn read_input(name: &str) -> io::Result<Vec<String>> {
     let file = File::open(name)?;
@@ -27,11 +39,88 @@ pub fn read_input(name: &str) -> io::Result<Vec<String>> {
 pub fn read_simple_input(name: &str) -> io::Result<String> {
     read_input(name)?
         .first()
-        .ok_or(io::Error::new(
-            io::ErrorKind::Other,
-            "No first line in input",
-        ))
-        .map(|l| l.clone())
+        .ok_or_else(|| io::Error::other("No first line in input"))
+        .cloned()
+}
+
+/// 64-bit FNV-1a, for telling inputs and programs apart. Not secure, but
+/// simple and stable.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Which half of a day's puzzle.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Part {
+    One,
+    Two,
+}
+
+impl Part {
+    pub fn both() -> [Part; 2] {
+        [Part::One, Part::Two]
+    }
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Part::One => write!(f, "1"),
+            Part::Two => write!(f, "2"),
+        }
+    }
+}
+
+impl FromStr for Part {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "1" => Ok(Part::One),
+            "2" => Ok(Part::Two),
+            _ => Err(format!("There's no part `{}`, only 1 and 2", text)),
+        }
+    }
+}
+
+/// A day's puzzle. The input is parsed once, then shared by both parts, and
+/// answers are strings since some days' aren't numbers.
+pub trait Solution {
+    type Input;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, Box<dyn Error>>;
+    fn part1(&self, input: &Self::Input) -> Result<String, Box<dyn Error>>;
+    fn part2(&self, input: &Self::Input) -> Result<String, Box<dyn Error>>;
+}
+
+/// `Solution` with the input type hidden, so every day can go in one table.
+/// Anything that's a `Solution` is one of these too.
+pub trait AnySolution: Sync {
+    fn parse_any(&self, input: &str) -> Result<Box<dyn Any>, Box<dyn Error>>;
+    /// Solves a part, given what `parse_any` returned.
+    fn solve_any(&self, input: &dyn Any, part: Part) -> Result<String, Box<dyn Error>>;
+}
+
+impl<S> AnySolution for S
+where
+    S: Solution + Sync,
+    S::Input: 'static,
+{
+    fn parse_any(&self, input: &str) -> Result<Box<dyn Any>, Box<dyn Error>> {
+        Ok(Box::new(self.parse(input)?))
+    }
+
+    fn solve_any(&self, input: &dyn Any, part: Part) -> Result<String, Box<dyn Error>> {
+        let input = input
+            .downcast_ref::<S::Input>()
+            .expect("input parsed by a different solution");
+        match part {
+            Part::One => self.part1(input),
+            Part::Two => self.part2(input),
+        }
+    }
 }
 
 #[cfg(test)]
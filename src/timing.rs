@@ -0,0 +1,194 @@
+// Copyright (C) 2019 Glowpelt <glowpelt@chakat.space>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Timing solutions, to see which are slow. Parsing and each part are timed
+//! separately, over as many runs as asked for, and summed up as the min,
+//! median and max, either as a table or as JSON for keeping track over time.
+
+use crate::days::Day;
+use crate::Part;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Something that gets timed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Phase {
+    Parse,
+    Part(Part),
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Parse => write!(f, "parse"),
+            Phase::Part(part) => write!(f, "part {}", part),
+        }
+    }
+}
+
+/// How long a phase took over several runs.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Stats {
+    pub runs: usize,
+    pub min: Duration,
+    pub median: Duration,
+    pub max: Duration,
+}
+
+impl Stats {
+    /// Panics if there aren't any samples.
+    pub fn new(mut samples: Vec<Duration>) -> Self {
+        samples.sort_unstable();
+        let middle = samples.len() / 2;
+        let median = if samples.len().is_multiple_of(2) {
+            (samples[middle - 1] + samples[middle]) / 2
+        } else {
+            samples[middle]
+        };
+        Stats {
+            runs: samples.len(),
+            min: samples[0],
+            median,
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+/// A day's answers, and how long each phase took.
+#[derive(Debug, Clone)]
+pub struct Timed {
+    pub day: u32,
+    pub answers: Vec<(Part, String)>,
+    pub phases: Vec<(Phase, Stats)>,
+}
+
+/// Solves the parts asked for `runs` times over (at least once), timing
+/// each phase. The answers are from the first run.
+pub fn time(day: &Day, input: &str, parts: &[Part], runs: usize) -> Result<Timed, Box<dyn Error>> {
+    let mut answers = Vec::new();
+    let mut samples = vec![Vec::new(); parts.len() + 1];
+    for run in 0..runs.max(1) {
+        let start = Instant::now();
+        let parsed = day.solution.parse_any(input)?;
+        samples[0].push(start.elapsed());
+        for (index, &part) in parts.iter().enumerate() {
+            let start = Instant::now();
+            let answer = day.solution.solve_any(parsed.as_ref(), part)?;
+            samples[index + 1].push(start.elapsed());
+            if run == 0 {
+                answers.push((part, answer));
+            }
+        }
+    }
+    let phases = Some(Phase::Parse)
+        .into_iter()
+        .chain(parts.iter().map(|&part| Phase::Part(part)))
+        .zip(samples.into_iter().map(Stats::new))
+        .collect();
+    Ok(Timed {
+        day: day.number,
+        answers,
+        phases,
+    })
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// The timings as a table, a phase a line, in milliseconds.
+pub fn table(timings: &[Timed]) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{:>3}  {:<8} {:>5} {:>12} {:>12} {:>12}",
+        "day", "phase", "runs", "min ms", "median ms", "max ms"
+    )
+    .unwrap();
+    for timed in timings {
+        for (phase, stats) in &timed.phases {
+            writeln!(
+                out,
+                "{:>3}  {:<8} {:>5} {:>12.3} {:>12.3} {:>12.3}",
+                timed.day,
+                phase.to_string(),
+                stats.runs,
+                millis(stats.min),
+                millis(stats.median),
+                millis(stats.max)
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+/// The timings and answers as JSON, with times in milliseconds.
+pub fn to_json(timings: &[Timed]) -> Value {
+    let days: Vec<Value> = timings
+        .iter()
+        .map(|timed| {
+            let phases: Vec<Value> = timed
+                .phases
+                .iter()
+                .map(|(phase, stats)| {
+                    json!({
+                        "phase": phase.to_string(),
+                        "runs": stats.runs,
+                        "min_ms": millis(stats.min),
+                        "median_ms": millis(stats.median),
+                        "max_ms": millis(stats.max),
+                    })
+                })
+                .collect();
+            let answers: Vec<Value> = timed
+                .answers
+                .iter()
+                .map(|(part, answer)| json!({ "part": part.to_string(), "answer": answer }))
+                .collect();
+            json!({ "day": timed.day, "answers": answers, "phases": phases })
+        })
+        .collect();
+    json!({ "days": days })
+}
+
+#[test]
+fn test_stats() {
+    let ms = Duration::from_millis;
+    let stats = Stats::new(vec![ms(5), ms(1), ms(3)]);
+    assert_eq!(
+        (stats.runs, stats.min, stats.median, stats.max),
+        (3, ms(1), ms(3), ms(5))
+    );
+    assert_eq!(Stats::new(vec![ms(4), ms(1), ms(2), ms(9)]).median, ms(3));
+}
+
+#[test]
+fn test_time() {
+    let day = Day::find(1).unwrap();
+    let timed = time(day, "12\n1969\n", &[Part::Two], 3).unwrap();
+    assert_eq!(timed.answers, vec![(Part::Two, "968".to_string())]);
+    let phases: Vec<(Phase, usize)> = timed.phases.iter().map(|(phase, stats)| (*phase, stats.runs)).collect();
+    assert_eq!(phases, vec![(Phase::Parse, 3), (Phase::Part(Part::Two), 3)]);
+    let table = table(std::slice::from_ref(&timed));
+    assert!(table.starts_with("day  phase     runs       min ms    median ms       max ms\n  1  parse        3 "));
+    assert_eq!(table.lines().count(), 3);
+    let json = to_json(&[timed]);
+    assert_eq!(json["days"][0]["answers"], json!([{ "part": "2", "answer": "968" }]));
+    assert_eq!(json["days"][0]["phases"][1]["phase"], "part 2");
+    assert!(json["days"][0]["phases"][1]["max_ms"].is_f64());
+}